@@ -0,0 +1,202 @@
+//! Measures how long it takes to render the default scene on the host, and how
+//! the layout of sphere geometry affects the intersection loop.
+//!
+//! Run with `cargo bench`. Timings on the host are only a rough proxy for
+//! those in the browser, but they are good enough to compare two versions of
+//! the tracer against each other.
+
+extern crate raymond;
+
+use raymond::{Image, Scene};
+use std::hint::black_box;
+use std::time::Instant;
+
+const WIDTH: usize = 640;
+const HEIGHT: usize = 360;
+const FRAMES: u32 = 20;
+
+// How many spheres are added to the default scene for the crowded cases.
+const EXTRA_SPHERES: usize = 200;
+
+const EPSILON: f64 = 1e-10;
+
+fn main() {
+    bench_render("default scene", Scene::new());
+    bench_render("crowded scene", crowded_scene());
+
+    for &count in &[8, 8 + EXTRA_SPHERES] {
+        bench_layouts(count);
+    }
+}
+
+// The default scene, with a grid of small spheres added behind it.
+fn crowded_scene() -> Scene {
+    let mut scene = Scene::new();
+    for i in 0..EXTRA_SPHERES {
+        let (column, row) = ((i % 20) as f64, (i / 20) as f64);
+        scene.add_sphere(column * 2. - 19., row * 2. - 6., 40., 0.6);
+    }
+    scene
+}
+
+fn bench_render(name: &str, mut scene: Scene) {
+    let mut img = Image::new(WIDTH, HEIGHT);
+
+    // Warm up the caches before timing anything.
+    scene.render(&mut img);
+
+    for &packets in &[false, true] {
+        scene.set_packet_tracing(packets);
+
+        let start = Instant::now();
+        for _ in 0..FRAMES {
+            scene.render(&mut img);
+        }
+        let elapsed = start.elapsed() / FRAMES;
+
+        println!(
+            "render {} {}x{} (packet tracing {}): {:.2} ms/frame",
+            name,
+            WIDTH,
+            HEIGHT,
+            if packets { "on" } else { "off" },
+            elapsed.as_secs_f64() * 1000.
+        );
+    }
+}
+
+// A sphere as the tracer stored it before its geometry moved into a
+// structure of arrays: the center and radius interleaved with the surface.
+struct AosSphere {
+    center: [f64; 3],
+    radius: f64,
+    _color: [f64; 3],
+    _glossiness: f64,
+}
+
+// The same spheres, with each component in an array of its own, as in the
+// tracer's `SphereSet`.
+struct SoaSpheres {
+    cx: Vec<f64>,
+    cy: Vec<f64>,
+    cz: Vec<f64>,
+    radius: Vec<f64>,
+}
+
+// Times the nearest-hit loop over `count` spheres laid out both ways, for one
+// ray per pixel of a frame.
+fn bench_layouts(count: usize) {
+    let mut seed = 1u64;
+    let mut random = move || {
+        seed = seed
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (seed >> 11) as f64 / (1u64 << 53) as f64
+    };
+
+    let aos: Vec<AosSphere> = (0..count)
+        .map(|_| AosSphere {
+            center: [
+                random() * 40. - 20.,
+                random() * 24. - 12.,
+                random() * 30. + 10.,
+            ],
+            radius: random() * 2. + 0.5,
+            _color: [random(), random(), random()],
+            _glossiness: random(),
+        })
+        .collect();
+    let soa = SoaSpheres {
+        cx: aos.iter().map(|s| s.center[0]).collect(),
+        cy: aos.iter().map(|s| s.center[1]).collect(),
+        cz: aos.iter().map(|s| s.center[2]).collect(),
+        radius: aos.iter().map(|s| s.radius).collect(),
+    };
+
+    let rays: Vec<[f64; 6]> = (0..WIDTH * HEIGHT)
+        .map(|i| {
+            let x = (i % WIDTH) as f64 / WIDTH as f64 * 8. - 4.;
+            let y = (i / WIDTH) as f64 / HEIGHT as f64 * 4.5 - 2.25;
+            let len = (x * x + y * y + 36.).sqrt();
+            [0., 0., -6., x / len, y / len, 6. / len]
+        })
+        .collect();
+
+    let time = |name: &str, nearest: &dyn Fn(&[f64; 6]) -> f64| {
+        let start = Instant::now();
+        let mut hits = 0;
+        for _ in 0..FRAMES {
+            for ray in &rays {
+                if black_box(nearest(ray)) < f64::INFINITY {
+                    hits += 1;
+                }
+            }
+        }
+        let elapsed = start.elapsed() / FRAMES;
+        println!(
+            "nearest hit, {} spheres ({}): {:.2} ms/frame, {} hits",
+            count,
+            name,
+            elapsed.as_secs_f64() * 1000.,
+            hits / FRAMES
+        );
+    };
+
+    time("array of structs", &|ray| nearest_aos(black_box(&aos), ray));
+    time("structure of arrays", &|ray| {
+        nearest_soa(black_box(&soa), ray)
+    });
+}
+
+fn nearest_aos(spheres: &[AosSphere], ray: &[f64; 6]) -> f64 {
+    spheres.iter().fold(f64::INFINITY, |min_t, s| {
+        let oc = [
+            ray[0] - s.center[0],
+            ray[1] - s.center[1],
+            ray[2] - s.center[2],
+        ];
+        let dot = ray[3] * oc[0] + ray[4] * oc[1] + ray[5] * oc[2];
+        let c = oc[0] * oc[0] + oc[1] * oc[1] + oc[2] * oc[2] - s.radius * s.radius;
+        nearest_root(dot, c).min(min_t)
+    })
+}
+
+fn nearest_soa(spheres: &SoaSpheres, ray: &[f64; 6]) -> f64 {
+    let n = spheres.radius.len();
+    let (cx, cy, cz, radius) = (
+        &spheres.cx[..n],
+        &spheres.cy[..n],
+        &spheres.cz[..n],
+        &spheres.radius[..n],
+    );
+
+    let mut min_t = f64::INFINITY;
+    for i in 0..n {
+        let (ocx, ocy, ocz) = (ray[0] - cx[i], ray[1] - cy[i], ray[2] - cz[i]);
+        let dot = ray[3] * ocx + ray[4] * ocy + ray[5] * ocz;
+        let c = ocx * ocx + ocy * ocy + ocz * ocz - radius[i] * radius[i];
+        min_t = nearest_root(dot, c).min(min_t);
+    }
+    min_t
+}
+
+// As in the tracer: the nearest root in front of the ray's origin, or
+// infinity if there is none.
+fn nearest_root(dot: f64, c: f64) -> f64 {
+    let sqrt_term = dot * dot - c;
+    if sqrt_term < 0. {
+        return f64::INFINITY;
+    }
+
+    let sqrt = sqrt_term.sqrt();
+    let near = -dot - sqrt;
+    let far = -dot + sqrt;
+
+    if near >= EPSILON {
+        near
+    } else if far >= EPSILON {
+        far
+    } else {
+        f64::INFINITY
+    }
+}
@@ -0,0 +1,787 @@
+use crate::packet::PACKET_SIZE;
+use crate::spheres::SphereSet;
+use crate::stats::Counters;
+use crate::{utils, Ray, Scene, EPSILON};
+use std::cell::Cell;
+use wasm_bindgen::prelude::*;
+
+// The ways of finding the spheres that a ray might hit. Testing every sphere
+// is hard to beat in small scenes, which is why it's the default; the others
+// pay for a build step every time the spheres change with fewer tests per ray,
+// and suit different scenes: a BVH copes with spheres of any size, a grid with
+// many spheres of similar size spread evenly, and a kd-tree with clumps.
+#[wasm_bindgen]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Accelerator {
+    BruteForce,
+    Bvh,
+    Grid,
+    KdTree,
+}
+
+#[derive(Copy, Clone)]
+pub struct AccelSettings {
+    pub kind: Accelerator,
+    // The most spheres a BVH leaf holds before it's split.
+    pub bvh_leaf_size: usize,
+    // How much worse a refitted BVH can get, as a multiple of the cost of the
+    // tree it was refitted from when that was built, before it's rebuilt.
+    pub bvh_refit_threshold: f64,
+    // How many grid cells to make for every sphere.
+    pub grid_density: f64,
+    // How deep a kd-tree can grow, and the most spheres one of its leaves
+    // holds before it's split, if it hasn't reached that depth.
+    pub kd_max_depth: usize,
+    pub kd_leaf_size: usize,
+}
+
+impl Default for AccelSettings {
+    fn default() -> Self {
+        Self {
+            kind: Accelerator::BruteForce,
+            bvh_leaf_size: 4,
+            bvh_refit_threshold: 1.5,
+            grid_density: 2.,
+            kd_max_depth: 16,
+            kd_leaf_size: 4,
+        }
+    }
+}
+
+// No grid gets more cells than this along any axis, however dense, so that a
+// single huge sphere can't take all the memory there is.
+const MAX_GRID_DIM: usize = 64;
+
+// An index over the spheres of a `SphereSet`, referring to them by their
+// position in the set.
+#[derive(Clone)]
+pub enum Accel {
+    Bvh(Bvh),
+    Grid(Grid),
+    KdTree(KdTree),
+}
+
+impl Accel {
+    // Like `build`, but given the index over the same spheres before they
+    // last changed. As long as they haven't moved too far, a BVH keeps the
+    // shape of its tree and only has its boxes refitted around them, which
+    // costs much less than building it afresh.
+    pub fn rebuild(previous: &Accel, set: &SphereSet, settings: &AccelSettings) -> Option<Self> {
+        if let (Accelerator::Bvh, Accel::Bvh(bvh)) = (settings.kind, previous) {
+            if bvh.leaf_size == settings.bvh_leaf_size {
+                let bounds: Vec<Bounds> =
+                    (0..set.index.len()).map(|j| Bounds::of(set, j)).collect();
+                let refitted = bvh.refit(&bounds);
+                if refitted.cost() <= bvh.built_cost * settings.bvh_refit_threshold {
+                    return Some(Accel::Bvh(refitted));
+                }
+            }
+        }
+
+        Self::build(set, settings)
+    }
+
+    // Builds the index chosen in `settings` over `set`, or nothing for brute
+    // force, which needs none.
+    pub fn build(set: &SphereSet, settings: &AccelSettings) -> Option<Self> {
+        if settings.kind == Accelerator::BruteForce || set.index.is_empty() {
+            return None;
+        }
+
+        let bounds: Vec<Bounds> = (0..set.index.len()).map(|j| Bounds::of(set, j)).collect();
+
+        match settings.kind {
+            Accelerator::BruteForce => None,
+            Accelerator::Bvh => Some(Accel::Bvh(Bvh::new(&bounds, settings.bvh_leaf_size))),
+            Accelerator::Grid => Some(Accel::Grid(Grid::new(&bounds, settings.grid_density))),
+            Accelerator::KdTree => Some(Accel::KdTree(KdTree::new(
+                &bounds,
+                settings.kd_max_depth,
+                settings.kd_leaf_size,
+            ))),
+        }
+    }
+
+    // Walks the parts of the index that `ray` passes through before `max_t`,
+    // roughly nearest first, calling `visit` with the spheres in each. Those
+    // may turn up more than once. `visit` returns the distance beyond which
+    // there's no need to look any further: the nearest hit so far, when looking
+    // for the nearest, or -∞ to stop straight away.
+    pub fn traverse<F>(&self, ray: &Ray, max_t: f64, stats: &Counters, visit: F)
+    where
+        F: FnMut(&[usize]) -> f64,
+    {
+        let ray = Slabs::new(ray);
+        match self {
+            Accel::Bvh(bvh) => bvh.traverse(&ray, max_t, stats, visit),
+            Accel::Grid(grid) => grid.traverse(&ray, max_t, stats, visit),
+            Accel::KdTree(tree) => tree.traverse(&ray, max_t, stats, visit),
+        }
+    }
+
+    // Like `traverse`, for a packet of rays. `visit` is also given the lanes
+    // of the packet whose rays pass through the part of the index that the
+    // spheres come from, and returns the distance beyond which there's no
+    // need to look any further for each lane. A BVH is walked by the whole
+    // packet at once, so that neighbouring rays share node visits; grids and
+    // kd-trees are walked one ray at a time.
+    pub fn traverse_packet<F>(&self, rays: &[Ray; PACKET_SIZE], stats: &Counters, mut visit: F)
+    where
+        F: FnMut(&[usize], &[bool; PACKET_SIZE]) -> [f64; PACKET_SIZE],
+    {
+        if let Accel::Bvh(bvh) = self {
+            let rays = rays.map(|ray| Slabs::new(&ray));
+            return bvh.traverse_packet(&rays, stats, visit);
+        }
+
+        for (lane, ray) in rays.iter().enumerate() {
+            let mut lanes = [false; PACKET_SIZE];
+            lanes[lane] = true;
+            self.traverse(ray, f64::INFINITY, stats, |items| {
+                visit(items, &lanes)[lane]
+            });
+        }
+    }
+}
+
+// A ray, set up for testing against axis-aligned boxes.
+struct Slabs {
+    origin: [f64; 3],
+    direction: [f64; 3],
+    inv: [f64; 3],
+}
+
+impl Slabs {
+    fn new(ray: &Ray) -> Self {
+        let o = &ray.origin;
+        let d = &ray.direction;
+        Self {
+            origin: [o.x, o.y, o.z],
+            direction: [d.x, d.y, d.z],
+            inv: [1. / d.x, 1. / d.y, 1. / d.z],
+        }
+    }
+}
+
+// An axis-aligned box.
+#[derive(Copy, Clone)]
+struct Bounds {
+    min: [f64; 3],
+    max: [f64; 3],
+}
+
+impl Bounds {
+    fn empty() -> Self {
+        Self {
+            min: [f64::INFINITY; 3],
+            max: [f64::NEG_INFINITY; 3],
+        }
+    }
+
+    // The box around the sphere at position `j` in `set`, padded so that
+    // hits right on its surface aren't lost to rounding.
+    fn of(set: &SphereSet, j: usize) -> Self {
+        let r = set.radius[j] + EPSILON;
+        let c = [set.cx[j], set.cy[j], set.cz[j]];
+        Self {
+            min: [c[0] - r, c[1] - r, c[2] - r],
+            max: [c[0] + r, c[1] + r, c[2] + r],
+        }
+    }
+
+    fn union(&self, other: &Self) -> Self {
+        let mut union = *self;
+        for a in 0..3 {
+            union.min[a] = union.min[a].min(other.min[a]);
+            union.max[a] = union.max[a].max(other.max[a]);
+        }
+        union
+    }
+
+    fn extent(&self, axis: usize) -> f64 {
+        self.max[axis] - self.min[axis]
+    }
+
+    fn longest_axis(&self) -> usize {
+        (0..3)
+            .max_by(|&a, &b| self.extent(a).total_cmp(&self.extent(b)))
+            .unwrap_or(0)
+    }
+
+    fn area(&self) -> f64 {
+        let (x, y, z) = (self.extent(0), self.extent(1), self.extent(2));
+        2. * (x * y + y * z + z * x)
+    }
+
+    fn centroid(&self, axis: usize) -> f64 {
+        (self.min[axis] + self.max[axis]) * 0.5
+    }
+
+    // The distances at which `ray` enters and leaves the box, clipped to
+    // start at its origin. The ray misses if the first is beyond the second.
+    // Axes along which the ray doesn't move give NaNs, which `min` and `max`
+    // skip over, leaving the other axes to decide.
+    fn clip(&self, ray: &Slabs) -> (f64, f64) {
+        let (mut near, mut far) = (0., f64::INFINITY);
+        for a in 0..3 {
+            let t0 = (self.min[a] - ray.origin[a]) * ray.inv[a];
+            let t1 = (self.max[a] - ray.origin[a]) * ray.inv[a];
+            near = t0.min(t1).max(near);
+            far = t0.max(t1).min(far);
+        }
+        (near, far)
+    }
+}
+
+// A bounding volume hierarchy: a binary tree of boxes, each splitting its
+// spheres into two halves by the position of their centers.
+#[derive(Clone)]
+pub struct Bvh {
+    // Nodes come before their children.
+    nodes: Vec<BvhNode>,
+    // The spheres, in the order the leaves refer to them.
+    items: Vec<usize>,
+    leaf_size: usize,
+    // The cost of the tree when it was built, which refitted versions of it
+    // are compared against.
+    built_cost: f64,
+}
+
+// The cost of visiting a node, relative to that of testing a sphere.
+const NODE_COST: f64 = 1.;
+
+#[derive(Clone)]
+struct BvhNode {
+    bounds: Bounds,
+    kind: BvhKind,
+}
+
+#[derive(Clone)]
+enum BvhKind {
+    Leaf {
+        start: usize,
+        end: usize,
+    },
+    Inner {
+        axis: usize,
+        left: usize,
+        right: usize,
+    },
+}
+
+impl Bvh {
+    fn new(bounds: &[Bounds], leaf_size: usize) -> Self {
+        let leaf_size = leaf_size.max(1);
+        let mut bvh = Self {
+            nodes: vec![],
+            items: (0..bounds.len()).collect(),
+            leaf_size,
+            built_cost: 0.,
+        };
+        bvh.split(bounds, 0, bounds.len(), leaf_size);
+        bvh.built_cost = bvh.cost();
+        bvh
+    }
+
+    // The same tree, with its boxes fitted around the spheres in `bounds`.
+    fn refit(&self, bounds: &[Bounds]) -> Self {
+        let mut bvh = self.clone();
+        for i in (0..bvh.nodes.len()).rev() {
+            bvh.nodes[i].bounds = match bvh.nodes[i].kind {
+                BvhKind::Leaf { start, end } => bvh.items[start..end]
+                    .iter()
+                    .fold(Bounds::empty(), |acc, &j| acc.union(&bounds[j])),
+                BvhKind::Inner { left, right, .. } => {
+                    bvh.nodes[left].bounds.union(&bvh.nodes[right].bounds)
+                }
+            };
+        }
+        bvh
+    }
+
+    // The expected cost of tracing a ray through the tree, by the surface
+    // area heuristic: a ray passing through the root visits each node with a
+    // probability given by the ratio of their surface areas, and then tests
+    // every sphere in it, if it's a leaf.
+    fn cost(&self) -> f64 {
+        let root = self.nodes[0].bounds.area();
+        if root <= 0. {
+            return 0.;
+        }
+
+        let cost: f64 = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let work = match node.kind {
+                    BvhKind::Leaf { start, end } => (end - start) as f64,
+                    BvhKind::Inner { .. } => NODE_COST,
+                };
+                node.bounds.area() * work
+            })
+            .sum();
+        cost / root
+    }
+
+    // Adds the node for `items[start..end]`, and everything below it,
+    // returning its index.
+    fn split(&mut self, bounds: &[Bounds], start: usize, end: usize, leaf_size: usize) -> usize {
+        let items = &mut self.items[start..end];
+        let node_bounds = items
+            .iter()
+            .fold(Bounds::empty(), |acc, &j| acc.union(&bounds[j]));
+
+        let index = self.nodes.len();
+        if items.len() <= leaf_size {
+            self.nodes.push(BvhNode {
+                bounds: node_bounds,
+                kind: BvhKind::Leaf { start, end },
+            });
+            return index;
+        }
+
+        let centers = items.iter().fold(Bounds::empty(), |acc, &j| {
+            let c = [
+                bounds[j].centroid(0),
+                bounds[j].centroid(1),
+                bounds[j].centroid(2),
+            ];
+            acc.union(&Bounds { min: c, max: c })
+        });
+        let axis = centers.longest_axis();
+        let mid = items.len() / 2;
+        items.select_nth_unstable_by(mid, |&a, &b| {
+            bounds[a]
+                .centroid(axis)
+                .total_cmp(&bounds[b].centroid(axis))
+        });
+
+        // The children are filled in once they've been built.
+        self.nodes.push(BvhNode {
+            bounds: node_bounds,
+            kind: BvhKind::Leaf { start, end },
+        });
+        let left = self.split(bounds, start, start + mid, leaf_size);
+        let right = self.split(bounds, start + mid, end, leaf_size);
+        self.nodes[index].kind = BvhKind::Inner { axis, left, right };
+        index
+    }
+
+    fn traverse<F>(&self, ray: &Slabs, max_t: f64, stats: &Counters, mut visit: F)
+    where
+        F: FnMut(&[usize]) -> f64,
+    {
+        let mut bound = max_t;
+        let mut stack = vec![0];
+
+        while let Some(i) = stack.pop() {
+            let node = &self.nodes[i];
+            let (near, far) = node.bounds.clip(ray);
+            if near > far || near > bound {
+                continue;
+            }
+
+            Counters::bump(&stats.node_visits, 1);
+            match node.kind {
+                BvhKind::Leaf { start, end } => bound = visit(&self.items[start..end]),
+                BvhKind::Inner { axis, left, right } => {
+                    // The child on the side the ray comes from goes on top.
+                    if ray.direction[axis] < 0. {
+                        stack.extend_from_slice(&[left, right]);
+                    } else {
+                        stack.extend_from_slice(&[right, left]);
+                    }
+                }
+            }
+        }
+    }
+
+    // Like `traverse`, for a packet of rays sharing one stack of nodes. Each
+    // node carries the lanes whose rays reached it, and those that miss its
+    // box, or have already found something nearer, drop out on the way down.
+    fn traverse_packet<F>(&self, rays: &[Slabs; PACKET_SIZE], stats: &Counters, mut visit: F)
+    where
+        F: FnMut(&[usize], &[bool; PACKET_SIZE]) -> [f64; PACKET_SIZE],
+    {
+        let mut bound = [f64::INFINITY; PACKET_SIZE];
+        let mut stack = vec![(0, [true; PACKET_SIZE])];
+
+        while let Some((i, lanes)) = stack.pop() {
+            let node = &self.nodes[i];
+            let mut active = [false; PACKET_SIZE];
+            for (lane, ray) in rays.iter().enumerate() {
+                if lanes[lane] {
+                    let (near, far) = node.bounds.clip(ray);
+                    active[lane] = near <= far && near <= bound[lane];
+                }
+            }
+            let first = match active.iter().position(|&a| a) {
+                Some(lane) => lane,
+                None => continue,
+            };
+
+            Counters::bump(&stats.node_visits, 1);
+            match node.kind {
+                BvhKind::Leaf { start, end } => bound = visit(&self.items[start..end], &active),
+                BvhKind::Inner { axis, left, right } => {
+                    // Neighbouring rays mostly agree on which child is nearer,
+                    // so the first ray still in the packet decides.
+                    if rays[first].direction[axis] < 0. {
+                        stack.extend_from_slice(&[(left, active), (right, active)]);
+                    } else {
+                        stack.extend_from_slice(&[(right, active), (left, active)]);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// A uniform grid over the scene, listing the spheres that overlap each cell.
+#[derive(Clone)]
+pub struct Grid {
+    bounds: Bounds,
+    dims: [usize; 3],
+    cell_size: [f64; 3],
+    cells: Vec<Vec<usize>>,
+}
+
+impl Grid {
+    fn new(bounds: &[Bounds], density: f64) -> Self {
+        let scene = bounds.iter().fold(Bounds::empty(), |acc, b| acc.union(b));
+
+        // Cells are cubes, as far as the scene's proportions allow.
+        let target = (density * bounds.len() as f64).max(1.);
+        let volume: f64 = (0..3).map(|a| scene.extent(a).max(EPSILON)).product();
+        let side = (volume / target).cbrt();
+
+        let mut dims = [1; 3];
+        let mut cell_size = [0.; 3];
+        for a in 0..3 {
+            let extent = scene.extent(a).max(EPSILON);
+            dims[a] = ((extent / side).ceil() as usize).clamp(1, MAX_GRID_DIM);
+            cell_size[a] = extent / dims[a] as f64;
+        }
+
+        let mut grid = Self {
+            bounds: scene,
+            dims,
+            cell_size,
+            cells: vec![vec![]; dims[0] * dims[1] * dims[2]],
+        };
+
+        for (j, b) in bounds.iter().enumerate() {
+            let lo = grid.cell_of(&b.min);
+            let hi = grid.cell_of(&b.max);
+            for z in lo[2]..=hi[2] {
+                for y in lo[1]..=hi[1] {
+                    for x in lo[0]..=hi[0] {
+                        let cell = grid.flatten(&[x, y, z]);
+                        grid.cells[cell].push(j);
+                    }
+                }
+            }
+        }
+
+        grid
+    }
+
+    // The cell containing `point`, or the nearest one to it.
+    fn cell_of(&self, point: &[f64; 3]) -> [usize; 3] {
+        let mut cell = [0; 3];
+        for a in 0..3 {
+            let i = ((point[a] - self.bounds.min[a]) / self.cell_size[a]).floor();
+            cell[a] = (i.max(0.) as usize).min(self.dims[a] - 1);
+        }
+        cell
+    }
+
+    fn flatten(&self, cell: &[usize; 3]) -> usize {
+        (cell[2] * self.dims[1] + cell[1]) * self.dims[0] + cell[0]
+    }
+
+    // Steps through the cells along the ray, one at a time, as described in
+    // Amanatides and Woo's "A Fast Voxel Traversal Algorithm for Ray Tracing".
+    fn traverse<F>(&self, ray: &Slabs, max_t: f64, stats: &Counters, mut visit: F)
+    where
+        F: FnMut(&[usize]) -> f64,
+    {
+        let (near, far) = self.bounds.clip(ray);
+        let mut bound = max_t;
+        if near > far || near > bound {
+            return;
+        }
+
+        let entry = [0, 1, 2].map(|a| ray.origin[a] + ray.direction[a] * near);
+        let mut cell = self.cell_of(&entry);
+
+        // The distance along the ray to the next cell boundary on each axis,
+        // and between consecutive boundaries.
+        let mut next = [f64::INFINITY; 3];
+        let mut delta = [f64::INFINITY; 3];
+        for a in 0..3 {
+            let d = ray.direction[a];
+            if d == 0. {
+                continue;
+            }
+            let edge = if d > 0. { cell[a] + 1 } else { cell[a] };
+            let edge = self.bounds.min[a] + edge as f64 * self.cell_size[a];
+            next[a] = (edge - ray.origin[a]) * ray.inv[a];
+            delta[a] = self.cell_size[a] * ray.inv[a].abs();
+        }
+
+        loop {
+            Counters::bump(&stats.node_visits, 1);
+            let items = &self.cells[self.flatten(&cell)];
+            if !items.is_empty() {
+                bound = visit(items);
+            }
+
+            let axis = (0..3)
+                .min_by(|&a, &b| next[a].total_cmp(&next[b]))
+                .unwrap_or(0);
+            let exit = next[axis];
+            if exit >= bound || exit > far {
+                return;
+            }
+
+            if ray.direction[axis] > 0. {
+                if cell[axis] + 1 == self.dims[axis] {
+                    return;
+                }
+                cell[axis] += 1;
+            } else {
+                if cell[axis] == 0 {
+                    return;
+                }
+                cell[axis] -= 1;
+            }
+            next[axis] += delta[axis];
+        }
+    }
+}
+
+// A kd-tree: a binary tree of boxes, each cut in half through the middle of
+// its longest side. Spheres straddling a cut belong to both halves.
+#[derive(Clone)]
+pub struct KdTree {
+    bounds: Bounds,
+    nodes: Vec<KdNode>,
+    // The spheres, in the order the leaves refer to them.
+    items: Vec<usize>,
+}
+
+#[derive(Clone)]
+enum KdNode {
+    Leaf {
+        start: usize,
+        end: usize,
+    },
+    Inner {
+        axis: usize,
+        split: f64,
+        below: usize,
+        above: usize,
+    },
+}
+
+impl KdTree {
+    fn new(bounds: &[Bounds], max_depth: usize, leaf_size: usize) -> Self {
+        let scene = bounds.iter().fold(Bounds::empty(), |acc, b| acc.union(b));
+        let mut tree = Self {
+            bounds: scene,
+            nodes: vec![],
+            items: vec![],
+        };
+
+        let all: Vec<usize> = (0..bounds.len()).collect();
+        tree.split(bounds, all, scene, max_depth, leaf_size.max(1));
+        tree
+    }
+
+    // Adds the node for `items`, which lie in `node_bounds`, and everything
+    // below it, returning its index.
+    fn split(
+        &mut self,
+        bounds: &[Bounds],
+        items: Vec<usize>,
+        node_bounds: Bounds,
+        depth: usize,
+        leaf_size: usize,
+    ) -> usize {
+        let index = self.nodes.len();
+        let leaf = |tree: &mut Self, items: Vec<usize>| {
+            let start = tree.items.len();
+            tree.items.extend(items);
+            tree.nodes.push(KdNode::Leaf {
+                start,
+                end: tree.items.len(),
+            });
+            index
+        };
+
+        if depth == 0 || items.len() <= leaf_size {
+            return leaf(self, items);
+        }
+
+        let axis = node_bounds.longest_axis();
+        let split = node_bounds.centroid(axis);
+        let below: Vec<usize> = items
+            .iter()
+            .copied()
+            .filter(|&j| bounds[j].min[axis] <= split)
+            .collect();
+        let above: Vec<usize> = items
+            .iter()
+            .copied()
+            .filter(|&j| bounds[j].max[axis] >= split)
+            .collect();
+
+        // Give up on cuts that every sphere straddles, which only add work.
+        if below.len() == items.len() && above.len() == items.len() {
+            return leaf(self, items);
+        }
+
+        let (mut lower, mut upper) = (node_bounds, node_bounds);
+        lower.max[axis] = split;
+        upper.min[axis] = split;
+
+        // The children are filled in once they've been built.
+        self.nodes.push(KdNode::Leaf { start: 0, end: 0 });
+        let below = self.split(bounds, below, lower, depth - 1, leaf_size);
+        let above = self.split(bounds, above, upper, depth - 1, leaf_size);
+        self.nodes[index] = KdNode::Inner {
+            axis,
+            split,
+            below,
+            above,
+        };
+        index
+    }
+
+    fn traverse<F>(&self, ray: &Slabs, max_t: f64, stats: &Counters, mut visit: F)
+    where
+        F: FnMut(&[usize]) -> f64,
+    {
+        let (near, far) = self.bounds.clip(ray);
+        let mut bound = max_t;
+        if near > far {
+            return;
+        }
+
+        // Nodes are pushed along with the stretch of the ray inside them.
+        let mut stack = vec![(0, near, far)];
+        while let Some((i, near, far)) = stack.pop() {
+            if near > bound {
+                continue;
+            }
+
+            Counters::bump(&stats.node_visits, 1);
+            match self.nodes[i] {
+                KdNode::Leaf { start, end } => bound = visit(&self.items[start..end]),
+                KdNode::Inner {
+                    axis,
+                    split,
+                    below,
+                    above,
+                } => {
+                    let o = ray.origin[axis];
+                    let (first, second) = if o < split || (o == split && ray.direction[axis] <= 0.)
+                    {
+                        (below, above)
+                    } else {
+                        (above, below)
+                    };
+
+                    // A ray lying in the cut itself gives a NaN here, and can
+                    // only hit the spheres straddling it, which both halves hold.
+                    let t = (split - o) * ray.inv[axis];
+                    if t.is_nan() || t > far || t <= 0. {
+                        stack.push((first, near, far));
+                    } else if t < near {
+                        stack.push((second, near, far));
+                    } else {
+                        stack.push((second, t, far));
+                        stack.push((first, near, t));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Chooses how rays find the spheres they might hit. This changes how fast
+    // frames render, and what `stats` reports, but not what they look like,
+    // so nothing is redrawn.
+    #[wasm_bindgen(js_name = setAccelerator)]
+    pub fn set_accelerator(&mut self, kind: Accelerator) {
+        self.accel.kind = kind;
+        self.rebuild_geometry();
+    }
+
+    // Sets the most spheres that a leaf of the BVH holds.
+    #[wasm_bindgen(js_name = setBvhLeafSize)]
+    pub fn set_bvh_leaf_size(&mut self, size: usize) {
+        self.accel.bvh_leaf_size = size.max(1);
+        self.rebuild_geometry();
+    }
+
+    // Sets how much worse, by the surface area heuristic, the BVH can get as
+    // spheres move before it's rebuilt, rather than refitted around them, as
+    // a multiple of its cost when it was built. Values below 1 turn refitting
+    // off, rebuilding the tree every time.
+    #[wasm_bindgen(js_name = setBvhRefitThreshold)]
+    pub fn set_bvh_refit_threshold(&mut self, ratio: f64) {
+        self.accel.bvh_refit_threshold = ratio;
+    }
+
+    // Sets how many cells the grid has for every sphere in the scene.
+    #[wasm_bindgen(js_name = setGridDensity)]
+    pub fn set_grid_density(&mut self, cells_per_sphere: f64) {
+        if cells_per_sphere > 0. {
+            self.accel.grid_density = cells_per_sphere;
+            self.rebuild_geometry();
+        }
+    }
+
+    // Sets how deep the kd-tree can grow, and the most spheres that one of its
+    // leaves holds before it's split, if it hasn't grown that deep.
+    #[wasm_bindgen(js_name = setKdTreeLimits)]
+    pub fn set_kd_tree_limits(&mut self, max_depth: usize, leaf_size: usize) {
+        self.accel.kd_max_depth = max_depth;
+        self.accel.kd_leaf_size = leaf_size.max(1);
+        self.rebuild_geometry();
+    }
+}
+
+impl Scene {
+    // Indexes `set` with the scene's accelerator, adding the time it takes to
+    // `clock`. Where `previous` holds the same spheres, its index is reused
+    // if it can be.
+    pub(crate) fn accelerate(
+        &self,
+        mut set: SphereSet,
+        previous: Option<&SphereSet>,
+        clock: &Cell<f64>,
+    ) -> SphereSet {
+        // The index is built around where the spheres start out, so it would
+        // lose track of any that move.
+        if set.moving {
+            return set;
+        }
+
+        let start = utils::now();
+        set.accel = match previous {
+            Some(SphereSet {
+                index,
+                accel: Some(accel),
+                ..
+            }) if *index == set.index => Accel::rebuild(accel, &set, &self.accel),
+            _ => Accel::build(&set, &self.accel),
+        };
+        clock.set(clock.get() + utils::now() - start);
+        set
+    }
+}
@@ -0,0 +1,60 @@
+use crate::raster::unpack;
+use crate::region::Region;
+use crate::{Scene, Vec3, RGB};
+use wasm_bindgen::prelude::*;
+
+// A cheap stand-in for light bouncing around the environment: three colors for
+// the sky above, the horizon and the ground below, blended according to the
+// direction a surface faces. It costs no extra rays, so it is a good way of
+// filling in the shadows of preview renders.
+#[derive(Copy, Clone)]
+pub struct AmbientGradient {
+    sky: RGB,
+    horizon: RGB,
+    ground: RGB,
+}
+
+impl AmbientGradient {
+    // Returns the illuminance, in lux, received by a surface with the given
+    // normal. Surfaces facing straight up only see the sky, and those facing
+    // straight down only the ground; those in between see more and more of
+    // the horizon as they turn towards it.
+    pub fn irradiance(&self, normal: &Vec3) -> RGB {
+        let (far, t) = if normal.y >= 0. {
+            (&self.sky, normal.y)
+        } else {
+            (&self.ground, -normal.y)
+        };
+        self.horizon.scale(1. - t).add(&far.scale(t))
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Lights the scene with a gradient from the sky to the horizon to the
+    // ground, given as 0xRRGGBB colors, at the given illuminance in lux.
+    // Passing an illuminance of zero turns it off again.
+    #[wasm_bindgen(js_name = setAmbientGradient)]
+    pub fn set_ambient_gradient(&mut self, sky: u32, horizon: u32, ground: u32, lux: f64) {
+        self.ambient = if lux > 0. {
+            Some(AmbientGradient {
+                sky: unpack(sky).scale(lux),
+                horizon: unpack(horizon).scale(lux),
+                ground: unpack(ground).scale(lux),
+            })
+        } else {
+            None
+        };
+        self.forget_irradiance();
+        self.dirty = Some(Region::full());
+    }
+
+    // Lights the scene evenly from every direction with a 0xRRGGBB color at
+    // the given illuminance in lux, so that surfaces in shadow aren't left
+    // pitch black. This replaces any ambient gradient, and an illuminance of
+    // zero turns it off.
+    #[wasm_bindgen(js_name = setAmbientLight)]
+    pub fn set_ambient_light(&mut self, color: u32, lux: f64) {
+        self.set_ambient_gradient(color, color, color, lux);
+    }
+}
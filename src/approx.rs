@@ -0,0 +1,82 @@
+use cfg_if::cfg_if;
+
+cfg_if! {
+    // When the `fast-math` feature is enabled, the functions below trade a
+    // small, bounded amount of accuracy for speed. Their errors are far below
+    // what survives quantisation to 8 bits per channel, but they can shift
+    // the odd pixel by one level compared to a regular build.
+    if #[cfg(feature = "fast-math")] {
+        // Returns 1 / √x, with a relative error below 5e-6.
+        #[inline]
+        pub fn inv_sqrt(x: f64) -> f64 {
+            // Start from the classic bit-level estimate, which is within 3.5%,
+            // then refine it with two Newton-Raphson iterations.
+            let mut y = f64::from_bits(0x5FE6_EB50_C7B5_37A9 - (x.to_bits() >> 1));
+            y *= 1.5 - 0.5 * x * y * y;
+            y *= 1.5 - 0.5 * x * y * y;
+            y
+        }
+
+        // Returns x raised to the power y, for x ≥ 0, as 2^(y·log₂x). The
+        // relative error is below 2e-5 · |y|.
+        #[inline]
+        pub fn powf(x: f64, y: f64) -> f64 {
+            if x <= 0. {
+                return 0.;
+            }
+            exp2(y * log2(x))
+        }
+
+        // Chebyshev fits of log₂(1 + t) and 2^t over [0, 1), with an absolute
+        // error below 1.7e-5 and a relative error below 1.1e-7 respectively.
+        const LOG2: [f64; 6] = [
+            1.651_467_088e-5,
+            1.441_492_412,
+            -7.064_864_491e-1,
+            4.094_702_987e-1,
+            -1.874_886_046e-1,
+            4.300_495_779e-2,
+        ];
+        const EXP2: [f64; 6] = [
+            9.999_998_984e-1,
+            6.931_544_897e-1,
+            2.401_418_182e-1,
+            5.586_033_708e-2,
+            8.949_590_423e-3,
+            1.893_754_058e-3,
+        ];
+
+        fn polynomial(coefficients: &[f64], x: f64) -> f64 {
+            coefficients.iter().rev().fold(0., |acc, &c| acc * x + c)
+        }
+
+        fn log2(x: f64) -> f64 {
+            // Split x into 2^e · m, with m in [1, 2), straight from its bits.
+            let bits = x.to_bits();
+            let e = ((bits >> 52) & 0x7FF) as f64 - 1023.;
+            let m = f64::from_bits((bits & 0x000F_FFFF_FFFF_FFFF) | 0x3FF0_0000_0000_0000);
+            e + polynomial(&LOG2, m - 1.)
+        }
+
+        fn exp2(x: f64) -> f64 {
+            let i = x.floor();
+            if i < -1022. {
+                return 0.;
+            }
+
+            // 2^i is computed exactly by writing i straight into the exponent.
+            let scale = f64::from_bits(((i as i64 + 1023) as u64) << 52);
+            scale * polynomial(&EXP2, x - i)
+        }
+    } else {
+        #[inline]
+        pub fn inv_sqrt(x: f64) -> f64 {
+            1. / x.sqrt()
+        }
+
+        #[inline]
+        pub fn powf(x: f64, y: f64) -> f64 {
+            x.powf(y)
+        }
+    }
+}
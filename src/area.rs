@@ -0,0 +1,151 @@
+use crate::clusters::LightClusters;
+use crate::material::basis;
+use crate::rng::Rng;
+use crate::{Light, Ray, Scene, Vec3};
+use std::f64::consts::PI;
+use wasm_bindgen::prelude::*;
+
+// The shape of the surface a light shines from. Point lights cast hard
+// shadows, while area lights are sampled with several shadow rays from points
+// spread over their surface, whose shadows blend into a soft penumbra.
+#[derive(Copy, Clone)]
+pub enum Shape {
+    Point,
+    Sphere { radius: f64 },
+    // A rectangle centered on the light, with the given edges.
+    Rect { width: Vec3, height: Vec3 },
+}
+
+// The number of shadow rays cast towards an area light from each point, until
+// set otherwise.
+pub const DEFAULT_SAMPLES: usize = 16;
+
+// The fractional part of the golden ratio, which spreads consecutive samples
+// evenly across the light.
+const GOLDEN: f64 = 0.618_033_988_749_895;
+
+impl Light {
+    // Lights `point` with the light through `illuminate`, one sample at a
+    // time for area lights, adding up the illuminance of each. The direction
+    // returned is the one towards the middle of the light.
+    pub fn over_area<F>(&self, point: &Vec3, illuminate: F) -> (Vec3, f64)
+    where
+        F: Fn(&Light) -> (Vec3, f64),
+    {
+        if let Shape::Point = self.shape {
+            return illuminate(self);
+        }
+
+        let (ray, _) = Ray::cast(point, &self.pos);
+        let lux = self.samples(point).map(|s| illuminate(&s).1).sum();
+        (ray.direction, lux)
+    }
+
+    // Stands in a number of point lights, spread over the light's surface and
+    // sharing out its power, for the light as seen from `point`. Each point
+    // sees the same pattern of samples, shifted by a random offset of its own,
+    // which turns the banding of overlapping hard shadows into fine noise.
+    fn samples(&self, point: &Vec3) -> impl Iterator<Item = Light> + '_ {
+        let n = self.samples.max(1);
+        let seed = point.x.to_bits()
+            ^ point.y.to_bits().rotate_left(21)
+            ^ point.z.to_bits().rotate_left(42);
+        let mut rng = Rng::new(seed);
+        let (du, dv) = (rng.next_f64(), rng.next_f64());
+
+        let (towards, _) = Ray::cast(&self.pos, point);
+        let (tangent, bitangent) = basis(&towards.direction);
+
+        (0..n).map(move |i| {
+            let u = ((i as f64 + 0.5) / n as f64 + du).fract();
+            let v = (i as f64 * GOLDEN + dv).fract();
+
+            let offset = match self.shape {
+                Shape::Point => Vec3::new(0., 0., 0.),
+                // Seen from far enough away, a sphere looks like a disc facing
+                // the viewer, which is sampled in its place.
+                Shape::Sphere { radius } => {
+                    let (r, phi) = (radius * u.sqrt(), 2. * PI * v);
+                    tangent
+                        .scale(r * phi.cos())
+                        .add(&bitangent.scale(r * phi.sin()))
+                }
+                Shape::Rect { width, height } => width.scale(u - 0.5).add(&height.scale(v - 0.5)),
+            };
+
+            Light {
+                pos: self.pos.add(&offset),
+                power: self.power / n as f64,
+                shape: Shape::Point,
+                ..*self
+            }
+        })
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Turns the light at `index` into a sphere of the given radius, which
+    // casts soft shadows. A radius of zero makes it a point light again.
+    #[wasm_bindgen(js_name = setLightSphere)]
+    pub fn set_light_sphere(&mut self, index: usize, radius: f64) {
+        self.set_light_shape(
+            index,
+            if radius > 0. {
+                Shape::Sphere { radius }
+            } else {
+                Shape::Point
+            },
+        );
+    }
+
+    // Turns the light at `index` into a `width` × `height` rectangle, facing
+    // the direction given, which casts soft shadows. A rectangle with no area
+    // makes it a point light again. Rectangles shine both ways, and as much
+    // sideways as straight ahead, much like a point light.
+    #[wasm_bindgen(js_name = setLightRect)]
+    pub fn set_light_rect(
+        &mut self,
+        index: usize,
+        width: f64,
+        height: f64,
+        nx: f64,
+        ny: f64,
+        nz: f64,
+    ) {
+        let shape = if width > 0. && height > 0. {
+            let (tangent, bitangent) = basis(&Vec3::new(nx, ny, nz).unit());
+            Shape::Rect {
+                width: tangent.scale(width),
+                height: bitangent.scale(height),
+            }
+        } else {
+            Shape::Point
+        };
+        self.set_light_shape(index, shape);
+    }
+
+    // Sets the number of shadow rays cast towards the light at `index` from
+    // each point it lights, once it's an area light. More rays make for
+    // smoother penumbrae, at a proportional cost.
+    #[wasm_bindgen(js_name = setLightSamples)]
+    pub fn set_light_samples(&mut self, index: usize, samples: usize) {
+        match self.lights.get_mut(index) {
+            Some(light) => light.samples = samples.max(1),
+            None => return,
+        }
+        self.light_clusters = LightClusters::new(&self.lights);
+        self.invalidate_lighting();
+    }
+}
+
+impl Scene {
+    fn set_light_shape(&mut self, index: usize, shape: Shape) {
+        match self.lights.get_mut(index) {
+            Some(light) => light.shape = shape,
+            None => return,
+        }
+        self.light_clusters = LightClusters::new(&self.lights);
+        self.invalidate_lighting();
+    }
+}
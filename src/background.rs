@@ -0,0 +1,130 @@
+use crate::cubemap::Cubemap;
+use crate::raster::unpack;
+use crate::region::Region;
+use crate::starfield::Starfield;
+use crate::texture::MipLevel;
+use crate::{Scene, Vec3, RGB};
+use std::f64::consts::PI;
+use wasm_bindgen::prelude::*;
+
+// The size of the equirectangular image that a background callback is baked
+// into. Backgrounds are usually smooth, so a coarse image will do.
+const BAKED_WIDTH: usize = 256;
+const BAKED_HEIGHT: usize = 128;
+
+// What's seen where rays escape the scene, when neither an environment map
+// nor a physical sky is set.
+pub enum Background {
+    // The gradient the renderer has always drawn: green and magenta, darkest
+    // towards the zenith and the nadir.
+    Classic,
+    Solid(RGB),
+    // Blends from `bottom`, straight down, to `top`, straight up.
+    Gradient { top: RGB, bottom: RGB },
+    // A callback's colors, as an equirectangular image laid out like an
+    // environment map.
+    Baked(MipLevel),
+    Cubemap(Box<Cubemap>),
+    Starfield(Starfield),
+}
+
+impl Background {
+    // The color seen in the given direction, a unit vector.
+    pub fn radiance(&self, direction: &Vec3) -> RGB {
+        match self {
+            Background::Classic => {
+                let y = 0.7 - direction.y.abs();
+                let mut x = direction.x / 2.0;
+                if x < y {
+                    x = y
+                }
+                RGB::new(x, y, x)
+            }
+            Background::Solid(color) => *color,
+            Background::Gradient { top, bottom } => {
+                let t = 0.5 * (direction.y.clamp(-1., 1.) + 1.);
+                bottom.scale(1. - t).add(&top.scale(t))
+            }
+            Background::Baked(image) => {
+                let u = 0.5 + direction.x.atan2(direction.z) / (2. * PI);
+                let v = direction.y.clamp(-1., 1.).acos() / PI;
+                image.bilinear(u, v)
+            }
+            Background::Cubemap(cubemap) => cubemap.radiance(direction),
+            Background::Starfield(starfield) => starfield.radiance(direction),
+        }
+    }
+
+    // Bakes the colors that `color` returns for the direction through the
+    // middle of each texel, or returns `None` as soon as it does.
+    fn bake(mut color: impl FnMut(&Vec3) -> Option<RGB>) -> Option<Self> {
+        let mut texels = Vec::with_capacity(BAKED_WIDTH * BAKED_HEIGHT);
+        for y in 0..BAKED_HEIGHT {
+            let polar = PI * (y as f64 + 0.5) / BAKED_HEIGHT as f64;
+            for x in 0..BAKED_WIDTH {
+                let azimuth = 2. * PI * ((x as f64 + 0.5) / BAKED_WIDTH as f64 - 0.5);
+                let direction = Vec3::new(
+                    polar.sin() * azimuth.sin(),
+                    polar.cos(),
+                    polar.sin() * azimuth.cos(),
+                );
+                texels.push(color(&direction)?);
+            }
+        }
+        Some(Background::Baked(MipLevel::new(
+            BAKED_WIDTH,
+            BAKED_HEIGHT,
+            texels,
+        )))
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Fills the background with a single 0xRRGGBB color.
+    #[wasm_bindgen(js_name = setBackgroundColor)]
+    pub fn set_background_color(&mut self, color: u32) {
+        self.set_background(Background::Solid(unpack(color)));
+    }
+
+    // Fills the background with a vertical gradient between two 0xRRGGBB
+    // colors, from `bottom` straight down to `top` straight up.
+    #[wasm_bindgen(js_name = setBackgroundGradient)]
+    pub fn set_background_gradient(&mut self, top: u32, bottom: u32) {
+        self.set_background(Background::Gradient {
+            top: unpack(top),
+            bottom: unpack(bottom),
+        });
+    }
+
+    // Fills the background with the colors that `callback` returns, as
+    // 0xRRGGBB numbers, when called with the x, y and z components of a unit
+    // direction. It's called once for each texel of an image baked up front,
+    // rather than for every ray. If it throws, or returns anything but a
+    // number, the background is left as it was.
+    #[wasm_bindgen(js_name = setBackgroundCallback)]
+    pub fn set_background_callback(&mut self, callback: &js_sys::Function) {
+        let baked = Background::bake(|d| {
+            let (x, y, z) = (d.x.into(), d.y.into(), d.z.into());
+            let color = callback.call3(&JsValue::NULL, &x, &y, &z).ok()?;
+            color.as_f64().map(|c| unpack(c as u32))
+        });
+        if let Some(background) = baked {
+            self.set_background(background);
+        }
+    }
+
+    // Goes back to the default background gradient.
+    #[wasm_bindgen(js_name = resetBackground)]
+    pub fn reset_background(&mut self) {
+        self.set_background(Background::Classic);
+    }
+}
+
+impl Scene {
+    fn set_background(&mut self, background: Background) {
+        self.backdrop = background;
+        self.forget_irradiance();
+        self.dirty = Some(Region::full());
+    }
+}
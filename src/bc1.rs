@@ -0,0 +1,159 @@
+use crate::texture::{blend, Decoder, MipLevel};
+use crate::RGB;
+
+// Block compression in the BC1 format, also known as DXT1, as GPUs use for
+// textures. Each block of 4 × 4 texels is stored in 8 bytes: two endpoint
+// colors in 16-bit 5:6:5, and a 2-bit index for each texel, picking one of
+// four colors along the line between them. That's half a byte per texel,
+// against three uncompressed, at the cost of some banding and blockiness.
+// Texels are compressed as they were uploaded, still encoded, and decoded
+// after they're looked up.
+pub struct Bc1Image {
+    width: usize,
+    height: usize,
+    // Row by row from the top, each block covering 4 × 4 texels.
+    blocks: Vec<Block>,
+}
+
+#[derive(Copy, Clone)]
+struct Block {
+    endpoints: [u16; 2],
+    // Two bits per texel, row by row, starting from the lowest bits.
+    indices: u32,
+}
+
+impl Bc1Image {
+    pub fn compress(image: &MipLevel<[u8; 3]>) -> Self {
+        let (width, height) = (image.width(), image.height());
+        let mut blocks = Vec::with_capacity(blocks_across(width) * blocks_across(height));
+
+        for by in 0..blocks_across(height) {
+            for bx in 0..blocks_across(width) {
+                // Blocks hanging over the edge of the image repeat the texels
+                // along it.
+                let texels = std::array::from_fn(|i| {
+                    let x = (4 * bx + i % 4).min(width - 1);
+                    let y = (4 * by + i / 4).min(height - 1);
+                    image.texel(x, y)
+                });
+                blocks.push(Block::encode(&texels));
+            }
+        }
+
+        Self {
+            width,
+            height,
+            blocks,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    fn texel(&self, x: usize, y: usize) -> [u8; 3] {
+        let block = &self.blocks[(y / 4) * blocks_across(self.width) + x / 4];
+        block.texel(4 * (y % 4) + x % 4)
+    }
+
+    // Blends the four texels nearest to (u, v), as `MipLevel::bilinear` does.
+    pub fn bilinear(&self, u: f64, v: f64, decoder: &Decoder) -> RGB {
+        blend(self.width, self.height, u, v, true, |x, y| {
+            decoder.decode(self.texel(x, y))
+        })
+    }
+}
+
+impl Block {
+    // Picks endpoints at opposite corners of the box bounding the texels'
+    // colors, along whichever diagonal the colors run, then the nearest of
+    // the four colors between them for each texel.
+    fn encode(texels: &[[u8; 3]; 16]) -> Self {
+        let mut lo = [u8::MAX; 3];
+        let mut hi = [0; 3];
+        let mut sum = [0i32; 3];
+        for texel in texels {
+            for c in 0..3 {
+                lo[c] = lo[c].min(texel[c]);
+                hi[c] = hi[c].max(texel[c]);
+                sum[c] += texel[c] as i32;
+            }
+        }
+
+        // Channels that fall as the widest one rises run from its high end to
+        // their low end.
+        let axis = (0..3).max_by_key(|&c| hi[c] - lo[c]).unwrap_or(0);
+        let (mut a, mut b) = (hi, lo);
+        for c in (0..3).filter(|&c| c != axis) {
+            let covariance: i32 = texels
+                .iter()
+                .map(|t| (16 * t[axis] as i32 - sum[axis]) * (16 * t[c] as i32 - sum[c]))
+                .sum();
+            if covariance < 0 {
+                std::mem::swap(&mut a[c], &mut b[c]);
+            }
+        }
+
+        // Four colors need the first endpoint to be the larger; equal ones
+        // only have three, but all texels take the first anyway.
+        let mut endpoints = [pack(a), pack(b)];
+        if endpoints[0] < endpoints[1] {
+            endpoints.swap(0, 1);
+        }
+
+        let palette = palette(endpoints);
+        let mut indices = 0;
+        for (i, texel) in texels.iter().enumerate() {
+            let nearest = (0..4)
+                .min_by_key(|&k| distance(&palette[k], texel))
+                .unwrap_or(0);
+            indices |= (nearest as u32) << (2 * i);
+        }
+
+        Self { endpoints, indices }
+    }
+
+    fn texel(&self, i: usize) -> [u8; 3] {
+        palette(self.endpoints)[(self.indices >> (2 * i)) as usize & 3]
+    }
+}
+
+fn blocks_across(texels: usize) -> usize {
+    texels.div_ceil(4)
+}
+
+// Rounds a color to 5:6:5 bits.
+fn pack([r, g, b]: [u8; 3]) -> u16 {
+    let round = |c: u8, max: u32| ((c as u32 * max + 127) / 255) as u16;
+    round(r, 31) << 11 | round(g, 63) << 5 | round(b, 31)
+}
+
+// Widens a 5:6:5 color back out to bytes, repeating the top bits in the
+// bottom ones so that the full range is covered.
+fn unpack(color: u16) -> [u8; 3] {
+    let (r, g, b) = (color >> 11 & 31, color >> 5 & 63, color & 31);
+    [
+        (r << 3 | r >> 2) as u8,
+        (g << 2 | g >> 4) as u8,
+        (b << 3 | b >> 2) as u8,
+    ]
+}
+
+// The colors that a block's indices pick from. A block whose first endpoint
+// isn't the larger has only three, and black.
+fn palette([c0, c1]: [u16; 2]) -> [[u8; 3]; 4] {
+    let (a, b) = (unpack(c0), unpack(c1));
+    let mix = |wa: u16, wb: u16| -> [u8; 3] {
+        std::array::from_fn(|c| ((wa * a[c] as u16 + wb * b[c] as u16) / (wa + wb)) as u8)
+    };
+
+    if c0 > c1 {
+        [a, b, mix(2, 1), mix(1, 2)]
+    } else {
+        [a, b, mix(1, 1), [0; 3]]
+    }
+}
+
+fn distance(a: &[u8; 3], b: &[u8; 3]) -> i32 {
+    (0..3).map(|c| (a[c] as i32 - b[c] as i32).pow(2)).sum()
+}
@@ -0,0 +1,89 @@
+use crate::material::Hit;
+use crate::texture::{holds_image, tangent_frame, ImageTexture, Texture, TextureEncoding};
+use crate::{Scene, Vec3};
+use std::f64::consts::PI;
+use wasm_bindgen::prelude::*;
+
+// Bump mapping tilts a surface's normal to follow the slopes of a height
+// texture, as if the surface had been displaced by it, without moving any
+// geometry. The slopes are found by finite differences, so any texture will
+// do, procedural or not; its height is the average of its channels.
+pub struct Bump {
+    height: Box<dyn Texture>,
+    // The distance, in world units, that a height of 1 displaces the surface.
+    strength: f64,
+    // How far apart, in world units, heights are compared to find slopes.
+    // Smaller distances pick up finer detail, but shouldn't go below the
+    // texture's own resolution.
+    delta: f64,
+}
+
+impl Bump {
+    pub fn new<T: Texture + 'static>(height: T, strength: f64, delta: f64) -> Self {
+        Self {
+            height: Box::new(height),
+            strength,
+            delta,
+        }
+    }
+
+    fn height_at(&self, hit: &Hit, offset: &Vec3) -> f64 {
+        let moved = Hit {
+            point: hit.point.add(offset),
+            normal: hit.normal,
+            local: hit.local.add(offset),
+            tangent: hit.tangent,
+            bitangent: hit.bitangent,
+            spread: hit.spread,
+        };
+        let c = self.height.value(&moved);
+        (c.red + c.green + c.blue) / 3.
+    }
+
+    // Tilts the normal at `hit` away from the slopes of the height texture.
+    pub fn perturb(&self, hit: &Hit) -> Vec3 {
+        let (tangent, bitangent) = tangent_frame(&hit.normal);
+
+        let slope = |axis: &Vec3| {
+            let step = axis.scale(self.delta);
+            let rise = self.height_at(hit, &step) - self.height_at(hit, &step.scale(-1.));
+            self.strength * rise / (2. * self.delta)
+        };
+
+        hit.normal
+            .subtract(&tangent.scale(slope(&tangent)))
+            .subtract(&bitangent.scale(slope(&bitangent)))
+            .unit()
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Wraps a height map, given as RGBA bytes, around the sphere at `index` in
+    // the same way as `setSphereTexture`, and bump maps the sphere with it.
+    // The strength is how far, in world units, the brightest parts of the map
+    // stand out from the darkest. Passing an empty image removes it.
+    #[wasm_bindgen(js_name = setSphereBumpMap)]
+    pub fn set_sphere_bump_map(
+        &mut self,
+        index: usize,
+        width: usize,
+        height: usize,
+        rgba: &[u8],
+        strength: f64,
+    ) {
+        let sphere = match self.spheres.get_mut(index) {
+            Some(sphere) => sphere,
+            None => return,
+        };
+        sphere.bump = if !holds_image(rgba.len(), width, height, 4) {
+            None
+        } else {
+            // Compare heights about one texel apart, around the equator.
+            let delta = 2. * PI * sphere.radius / width as f64;
+            let texture = ImageTexture::new(width, height, rgba, TextureEncoding::Linear);
+            Some(Bump::new(texture, strength, delta))
+        };
+        self.invalidate_lighting();
+    }
+}
@@ -0,0 +1,79 @@
+use crate::material::{Hit, ShadowCatcher};
+use crate::rng::Rng;
+use crate::stats::Counters;
+use crate::{Ray, Sampled, Scene, RGB};
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+impl Scene {
+    // Turns the sphere at `index` into a shadow catcher, which reflects the
+    // given fraction of light. It keeps its current color for when it shows
+    // up in reflections and bounces.
+    #[wasm_bindgen(js_name = setShadowCatcher)]
+    pub fn set_shadow_catcher(&mut self, index: usize, reflectance: f64) {
+        let sphere = match self.spheres.get_mut(index) {
+            Some(sphere) => sphere,
+            None => return,
+        };
+        let color = sphere.material.color(&sphere.hit(sphere.top(), 0., 0.));
+
+        sphere.material = Rc::new(ShadowCatcher::new(color, reflectance));
+        self.invalidate_lighting();
+    }
+}
+
+impl Scene {
+    // Shades a shadow catcher seen by the camera, returning the color and
+    // opacity to composite over the photograph behind it. Shadows darken the
+    // photograph in proportion to the light they block, and reflections of
+    // other objects are laid over it; the background isn't reflected, since
+    // the photograph already shows whatever lies beyond the scene.
+    pub(crate) fn catch_shadows(
+        &self,
+        ray: &Ray,
+        hit: &Hit,
+        reflectance: f64,
+        rng: &mut Rng,
+    ) -> (RGB, f64) {
+        let (mut lit, mut unshadowed) = (0., 0.);
+        for light in self.light_clusters.lights_for(&hit.point) {
+            let occluders = self.occluders_for(light);
+            let (_, lux) = light.illuminate(occluders, &hit.point, &hit.normal, &self.stats);
+            lit += lux * self.transmittance(&hit.point, light).average();
+            unshadowed += light.unshadowed(&hit.point, &hit.normal);
+        }
+        for sun in self.directional_lights() {
+            let (_, lux) = sun.illuminate(&self.occluders, &hit.point, &hit.normal, &self.stats);
+            let ray = sun.shadow_ray(&hit.point);
+            lit += lux * self.transmittance_along(&ray, f64::INFINITY).average();
+            unshadowed += sun.unshadowed(&hit.normal);
+        }
+
+        let shadow = if unshadowed > 0. {
+            1. - lit / unshadowed
+        } else {
+            0.
+        };
+
+        let mut reflection = RGB::black();
+        let mut coverage = 0.;
+        if reflectance > 0. {
+            let reflected = ray.reflect(&hit.point, &hit.normal);
+            Counters::bump(&self.stats.reflection_rays, 1);
+            if let Some(nearest) = self.nearest(&reflected) {
+                let sampled = Sampled::default();
+                reflection = self.shade(&reflected, Some(nearest), 2, reflectance, sampled, rng);
+                coverage = reflectance;
+            }
+        }
+
+        // Shadows are black, and reflections cover up the shadows beneath
+        // them. The color is returned unpremultiplied, as images store it.
+        let alpha = 1. - (1. - shadow) * (1. - coverage);
+        if alpha <= 0. {
+            return (RGB::black(), 0.);
+        }
+        (reflection.scale(coverage / alpha), alpha)
+    }
+}
@@ -0,0 +1,126 @@
+use crate::material::Hit;
+use crate::region::Region;
+use crate::rng::Rng;
+use crate::{Integrator, Scene, Vec3, RGB};
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use wasm_bindgen::prelude::*;
+
+// A caustic photon map, for adding caustics to every integrator that shades
+// surfaces with lights, rather than only PhotonMapping. Before the first
+// frame that needs it, photons are shot from the lights through the glass and
+// mirrors in the scene, as in progressive photon mapping, and stored where
+// they land. Then, whenever a diffuse surface is shaded, the photons around
+// the point are gathered into the light focused onto it. Unlike progressive
+// photon mapping, the map doesn't depend on the camera, so it lasts until the
+// lights or spheres change, and caustics show up in reflections and in every
+// render method. The price is that they never get any sharper than the
+// radius they are gathered over.
+pub struct Caustics {
+    photons: usize,
+    radius: f64,
+    // Traced on first use.
+    map: OnceCell<CausticMap>,
+}
+
+struct CausticMap {
+    // Photons indexed by the cell of a uniform grid, twice the gathering
+    // radius across, that they landed in.
+    grid: HashMap<(i64, i64, i64), Vec<Photon>>,
+}
+
+struct Photon {
+    position: Vec3,
+    normal: Vec3,
+    flux: RGB,
+}
+
+impl Caustics {
+    fn cell(&self, p: &Vec3) -> (i64, i64, i64) {
+        let size = 2. * self.radius;
+        (
+            (p.x / size).floor() as i64,
+            (p.y / size).floor() as i64,
+            (p.z / size).floor() as i64,
+        )
+    }
+}
+
+impl Scene {
+    // The light focused onto the diffuse surface at `hit` by glass and
+    // mirrors, as rendered color values, or black if caustics are off.
+    // PhotonMapping brings in caustics of its own.
+    pub(crate) fn caustic(&self, hit: &Hit) -> RGB {
+        let caustics = match &self.caustics {
+            Some(caustics) if self.integrator != Integrator::PhotonMapping => caustics,
+            _ => return RGB::black(),
+        };
+        let map = caustics.map.get_or_init(|| self.trace_caustics(caustics));
+
+        let r = caustics.radius;
+        let (x0, y0, z0) = caustics.cell(&hit.point.subtract(&Vec3::new(r, r, r)));
+        let (x1, y1, z1) = caustics.cell(&hit.point.add(&Vec3::new(r, r, r)));
+
+        let mut flux = RGB::black();
+        for cx in x0..=x1 {
+            for cy in y0..=y1 {
+                for cz in z0..=z1 {
+                    let photons = map.grid.get(&(cx, cy, cz)).into_iter().flatten();
+                    for photon in photons {
+                        if photon.normal.dot(&hit.normal) > 0.5
+                            && photon.position.subtract(&hit.point).length_sqr() <= r * r
+                        {
+                            flux = flux.add(&photon.flux);
+                        }
+                    }
+                }
+            }
+        }
+        flux.scale(1. / (PI * r * r))
+    }
+
+    fn trace_caustics(&self, caustics: &Caustics) -> CausticMap {
+        let mut grid: HashMap<_, Vec<Photon>> = HashMap::new();
+        let mut rng = Rng::new(0);
+        self.shoot_photons(caustics.photons, &mut rng, &mut |point, normal, flux| {
+            grid.entry(caustics.cell(point)).or_default().push(Photon {
+                position: *point,
+                normal: *normal,
+                flux: *flux,
+            })
+        });
+        CausticMap { grid }
+    }
+
+    // Throws away the photon map, to be traced again with the scene as it
+    // stands, once a frame needs it.
+    pub(crate) fn forget_caustics(&mut self) {
+        if let Some(caustics) = &mut self.caustics {
+            caustics.map = OnceCell::new();
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Adds caustics to the PathTracing and LitPreview integrators, from a
+    // photon map traced with the given number of photons per light for each
+    // glass or mirror sphere, and gathered over the given radius. More photons
+    // make for smoother caustics, and a smaller radius for sharper ones. Zero
+    // photons turns them off. Light passing through glass reaches its shadow
+    // as caustics instead of straight through.
+    #[wasm_bindgen(js_name = setCaustics)]
+    pub fn set_caustics(&mut self, photons: usize, radius: f64) {
+        self.caustics = if photons > 0 && radius > 0. {
+            Some(Caustics {
+                photons,
+                radius,
+                map: OnceCell::new(),
+            })
+        } else {
+            None
+        };
+        self.dirty = Some(Region::full());
+    }
+}
@@ -0,0 +1,120 @@
+use crate::spot::Beam;
+use crate::{Light, Vec3, RGB};
+
+// Scenes with many lights would need as many shadow rays for every point they
+// shade. Instead, lights are grouped into clusters by region, and points far
+// enough away from a cluster are lit by a single aggregate light standing in
+// for all of its members, at the cost of one shadow ray.
+
+// Lights are grouped by the cell of a grid of this size that they fall into.
+const CLUSTER_SIZE: f64 = 16.;
+
+// Points further than this many times a cluster's radius from its center are
+// lit by its aggregate light.
+const FAR_RATIO: f64 = 4.;
+
+// The coordinates of a cell of the grid.
+type Cell = (i64, i64, i64);
+
+pub struct LightClusters {
+    clusters: Vec<Cluster>,
+}
+
+struct Cluster {
+    members: Vec<Light>,
+    // A light with the combined power of all the members, placed at their
+    // power-weighted centroid.
+    aggregate: Light,
+    // The distance from the aggregate light to the furthest member.
+    radius: f64,
+}
+
+impl LightClusters {
+    pub fn new(lights: &[Light]) -> Self {
+        // Clusters are listed in the order of their first member, so that the
+        // contributions of lone lights are always added up in the same order.
+        // An aggregate light shines every way, unlike a spot light, and falls
+        // off physically, so spot lights and those that fall off differently
+        // are left in clusters of their own, outside of any cell, as are
+        // lights with links, which only light some of the spheres.
+        let mut cells: Vec<(Option<Cell>, Vec<Light>)> = vec![];
+        for light in lights {
+            let cell = match light.beam {
+                Beam::Omni if light.is_physical() && light.link.is_none() => Some((
+                    (light.pos.x / CLUSTER_SIZE).floor() as i64,
+                    (light.pos.y / CLUSTER_SIZE).floor() as i64,
+                    (light.pos.z / CLUSTER_SIZE).floor() as i64,
+                )),
+                _ => None,
+            };
+            match cells.iter_mut().find(|(c, _)| cell.is_some() && *c == cell) {
+                Some((_, members)) => members.push(*light),
+                None => cells.push((cell, vec![*light])),
+            }
+        }
+
+        let clusters = cells
+            .into_iter()
+            .map(|(_, members)| Cluster::new(members))
+            .collect();
+
+        Self { clusters }
+    }
+
+    // Returns the lights that shine on `point`: the aggregate light of every
+    // cluster far enough away, and the members of those nearby.
+    pub fn lights_for<'a>(&'a self, point: &'a Vec3) -> impl Iterator<Item = &'a Light> + 'a {
+        self.clusters.iter().flat_map(move |cluster| {
+            let dist_sqr = cluster.aggregate.pos.subtract(point).length_sqr();
+            if dist_sqr > (FAR_RATIO * cluster.radius).powi(2) {
+                std::slice::from_ref(&cluster.aggregate).iter()
+            } else {
+                cluster.members.iter()
+            }
+        })
+    }
+}
+
+impl Cluster {
+    fn new(members: Vec<Light>) -> Self {
+        if let [light] = members[..] {
+            return Self {
+                members,
+                aggregate: light,
+                radius: 0.,
+            };
+        }
+
+        // The aggregate sits at the members' center of power, with their
+        // average color weighted the same way. Members giving off no light
+        // at all are weighted evenly instead.
+        let power: f64 = members.iter().map(|l| l.power).sum();
+        let weight = |l: &Light| if power > 0. { l.power } else { 1. };
+        let total: f64 = members.iter().map(weight).sum();
+
+        let pos = members
+            .iter()
+            .fold(Vec3::new(0., 0., 0.), |acc, l| {
+                acc.add(&l.pos.scale(weight(l)))
+            })
+            .scale(1. / total);
+        let radius = members
+            .iter()
+            .map(|l| l.pos.subtract(&pos).length())
+            .fold(0., f64::max);
+
+        let color = members
+            .iter()
+            .fold(RGB::black(), |acc, l| acc.add(&l.color.scale(weight(l))))
+            .scale(1. / total);
+
+        Self {
+            members,
+            aggregate: Light {
+                color,
+                ..Light::new(pos, power)
+            },
+            radius,
+        }
+    }
+}
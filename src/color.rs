@@ -0,0 +1,69 @@
+use crate::{approx, RGB};
+use wasm_bindgen::prelude::*;
+
+// The color space in which an `Image` stores its pixels. This should match the
+// color space of the canvas the image is eventually drawn to.
+#[wasm_bindgen]
+#[derive(Copy, Clone, PartialEq)]
+pub enum ColorSpace {
+    Srgb,
+    DisplayP3,
+}
+
+// Converts linear sRGB to linear Display P3. Both share the D65 white point,
+// so this is a straight change of primaries.
+const SRGB_TO_P3: [[f64; 3]; 3] = [
+    [0.822_462_1, 0.177_538_0, 0.0],
+    [0.033_194_1, 0.966_805_8, 0.0],
+    [0.017_082_7, 0.072_397_4, 0.910_519_9],
+];
+
+// Converts CIE XYZ to linear sRGB.
+const XYZ_TO_SRGB: [[f64; 3]; 3] = [
+    [3.2406, -1.5372, -0.4986],
+    [-0.9689, 1.8758, 0.0415],
+    [0.0557, -0.2040, 1.0570],
+];
+
+// Converts a color given by its CIE xy chromaticity to linear sRGB, with a
+// luminance of 1. Colors outside the sRGB gamut are clipped to it.
+pub(crate) fn chromaticity_to_srgb(x: f64, y: f64) -> RGB {
+    let xyz = [x / y, 1., (1. - x - y) / y];
+    let rgb = XYZ_TO_SRGB.map(|m| (m[0] * xyz[0] + m[1] * xyz[1] + m[2] * xyz[2]).max(0.));
+    RGB::new(rgb[0], rgb[1], rgb[2])
+}
+
+impl ColorSpace {
+    // Converts a rendered color, whose components are linear sRGB, into this
+    // color space, applying its transfer function. Renders never leave the
+    // sRGB gamut, which is contained within that of Display P3, so no gamut
+    // compression is needed: the components are clipped to [0, 1] beforehand,
+    // as they would be on output.
+    pub(crate) fn encode(self, color: &RGB) -> RGB {
+        let c = [color.red, color.green, color.blue].map(|c| c.clamp(0., 1.));
+        match self {
+            ColorSpace::Srgb => RGB::new(srgb_encode(c[0]), srgb_encode(c[1]), srgb_encode(c[2])),
+            ColorSpace::DisplayP3 => {
+                let p3 = |m: &[f64; 3]| srgb_encode(m[0] * c[0] + m[1] * c[1] + m[2] * c[2]);
+                RGB::new(p3(&SRGB_TO_P3[0]), p3(&SRGB_TO_P3[1]), p3(&SRGB_TO_P3[2]))
+            }
+        }
+    }
+}
+
+// Display P3 uses the same transfer function as sRGB.
+pub(crate) fn srgb_decode(c: f64) -> f64 {
+    if c <= 0.040_45 {
+        c / 12.92
+    } else {
+        approx::powf((c + 0.055) / 1.055, 2.4)
+    }
+}
+
+pub(crate) fn srgb_encode(c: f64) -> f64 {
+    if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * approx::powf(c, 1. / 2.4) - 0.055
+    }
+}
@@ -0,0 +1,109 @@
+use crate::background::Background;
+use crate::region::Region;
+use crate::texture::{holds_image, Decoder, MipLevel, TextureEncoding};
+use crate::{Scene, Vec3, RGB};
+use wasm_bindgen::prelude::*;
+
+// A skybox: six square images on the faces of a cube around the scene, the
+// other common way of storing what surrounds it besides an equirectangular
+// map. Faces follow the usual cubemap layout, as exported by most tools and
+// expected by WebGL, with +Y up and +Z straight ahead of the default camera.
+// Each face's image runs row by row from the top, as seen from inside the
+// cube, except that the top and bottom faces have +Z at their bottom and top
+// edges respectively.
+#[derive(Default)]
+pub struct Cubemap {
+    // In the order of `CubeFace`. Faces that haven't been uploaded are black.
+    faces: [Option<Face>; 6],
+}
+
+// A face's texels, kept as bytes as they were uploaded, as image textures are.
+struct Face {
+    image: MipLevel<[u8; 3]>,
+    decoder: Decoder,
+}
+
+#[wasm_bindgen]
+#[derive(Copy, Clone)]
+pub enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl Cubemap {
+    // The color seen in the given direction, a unit vector.
+    pub fn radiance(&self, direction: &Vec3) -> RGB {
+        let (x, y, z) = (direction.x, direction.y, direction.z);
+        let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+
+        // The face is picked by the direction's largest component, and the
+        // other two, divided by it, say where on the face it lands.
+        let (face, s, t, major) = if ax >= ay && ax >= az {
+            if x > 0. {
+                (CubeFace::PositiveX, -z, -y, ax)
+            } else {
+                (CubeFace::NegativeX, z, -y, ax)
+            }
+        } else if ay >= az {
+            if y > 0. {
+                (CubeFace::PositiveY, x, z, ay)
+            } else {
+                (CubeFace::NegativeY, x, -z, ay)
+            }
+        } else if z > 0. {
+            (CubeFace::PositiveZ, x, -y, az)
+        } else {
+            (CubeFace::NegativeZ, -x, -y, az)
+        };
+
+        match &self.faces[face as usize] {
+            Some(face) if major > 0. => face.image.bilinear_clamped(
+                0.5 * (s / major + 1.),
+                0.5 * (t / major + 1.),
+                &face.decoder,
+            ),
+            _ => RGB::black(),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Sets one face of a skybox as the background, from a `width` × `height`
+    // image given as RGBA bytes in the given encoding, row by row from the
+    // top, as with `setSphereTexture`. The alpha channel is ignored. Faces not
+    // yet set are black, so set all six in a row. An empty image, or one with
+    // too few bytes, is ignored. Like any other background, an environment
+    // map or physical sky takes its place.
+    #[wasm_bindgen(js_name = setCubemapFace)]
+    pub fn set_cubemap_face(
+        &mut self,
+        face: CubeFace,
+        width: usize,
+        height: usize,
+        rgba: &[u8],
+        encoding: TextureEncoding,
+    ) {
+        if !holds_image(rgba.len(), width, height, 4) {
+            return;
+        }
+
+        let image = Face {
+            image: MipLevel::from_rgba(width, height, rgba),
+            decoder: Decoder::new(encoding),
+        };
+
+        if !matches!(self.backdrop, Background::Cubemap(_)) {
+            self.backdrop = Background::Cubemap(Box::default());
+        }
+        if let Background::Cubemap(cubemap) = &mut self.backdrop {
+            cubemap.faces[face as usize] = Some(image);
+        }
+        self.forget_irradiance();
+        self.dirty = Some(Region::full());
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{Image, RGB};
+use wasm_bindgen::prelude::*;
+
+// The built-in film response curves, which give renders a consistent look
+// without grading them afterwards. Curves reshape each component once it has
+// been clipped to [0, 1] for display, before it's converted to the image's
+// color space.
+#[wasm_bindgen]
+#[derive(Copy, Clone, PartialEq)]
+pub enum FilmCurve {
+    // Leaves colors as they are.
+    Neutral,
+    // An S-shaped curve, which deepens shadows and rolls off highlights in the
+    // manner of photographic film, adding contrast through the midtones.
+    Filmic,
+}
+
+// How strongly the filmic curve bends: 1 is a straight line, and larger values
+// add more contrast.
+const FILMIC_CONTRAST: f64 = 1.4;
+
+#[derive(Clone)]
+pub(crate) enum Curve {
+    Preset(FilmCurve),
+    // Samples of a custom curve, spaced evenly over [0, 1], with the first at
+    // 0 and the last at 1.
+    Lut(Vec<f64>),
+}
+
+impl Curve {
+    pub(crate) fn apply(&self, color: &RGB) -> RGB {
+        let map = |c: f64| {
+            let c = c.clamp(0., 1.);
+            match self {
+                Curve::Preset(FilmCurve::Neutral) => c,
+                Curve::Preset(FilmCurve::Filmic) => filmic(c),
+                Curve::Lut(samples) => lookup(samples, c),
+            }
+        };
+
+        match self {
+            Curve::Preset(FilmCurve::Neutral) => *color,
+            _ => RGB::new(map(color.red), map(color.green), map(color.blue)),
+        }
+    }
+}
+
+// A sigmoid that keeps 0, ½ and 1 where they are.
+fn filmic(c: f64) -> f64 {
+    let a = c.powf(FILMIC_CONTRAST);
+    let b = (1. - c).powf(FILMIC_CONTRAST);
+    a / (a + b)
+}
+
+// Interpolates linearly between the two samples either side of `c`.
+fn lookup(samples: &[f64], c: f64) -> f64 {
+    let x = c * (samples.len() - 1) as f64;
+    let i = (x as usize).min(samples.len() - 2);
+    let t = x - i as f64;
+    samples[i] * (1. - t) + samples[i + 1] * t
+}
+
+#[wasm_bindgen]
+impl Image {
+    // Sets the film response curve that subsequent renders are written with.
+    #[wasm_bindgen(js_name = setFilmCurve)]
+    pub fn set_film_curve(&mut self, curve: FilmCurve) {
+        self.curve = Curve::Preset(curve);
+    }
+
+    // Sets a custom film response curve, given as samples spaced evenly from
+    // input 0 to input 1, each the output for that input. Fewer than two
+    // samples can't describe a curve, and restore the neutral one instead.
+    #[wasm_bindgen(js_name = setFilmLut)]
+    pub fn set_film_lut(&mut self, samples: &[f32]) {
+        self.curve = if samples.len() < 2 {
+            Curve::Preset(FilmCurve::Neutral)
+        } else {
+            Curve::Lut(samples.iter().map(|&s| s as f64).collect())
+        };
+    }
+}
@@ -0,0 +1,138 @@
+use crate::raster::unpack;
+use crate::region::Region;
+use crate::sky::{sunlight, PhysicalSky};
+use crate::sun::DirectionalLight;
+use crate::temperature::kelvin_to_rgb;
+use crate::{Scene, Vec3, RGB};
+use std::f64::consts::PI;
+use wasm_bindgen::prelude::*;
+
+// A day and night cycle, driven by the time of day. The sun rises due east,
+// along +X, at 6am, climbs to its highest over -Z, behind the default camera,
+// at noon, and sets due west at 6pm. With a physical sky, its sun is the one
+// that moves, and the sky's colors follow from it. Otherwise, the time of day
+// drives a directional light of its own, and blends the default background
+// from night through twilight to day.
+
+// How high the sun climbs at noon, in degrees above the horizon.
+const NOON_ELEVATION: f64 = 60.;
+
+// The haziness of the air that sunlight passes through without a physical sky.
+const TURBIDITY: f64 = 3.;
+
+// The colors of the sky at the zenith and the horizon, as 0xRRGGBB colors, by
+// night, through twilight, and by day.
+const NIGHT: (u32, u32) = (0x02_04_0c, 0x06_08_14);
+const TWILIGHT: (u32, u32) = (0x24_34_6c, 0xe0_7c_48);
+const DAY: (u32, u32) = (0x3a_78_d8, 0xc8_dc_f0);
+
+pub struct TimeOfDay {
+    // The directional light that stands in for the sun without a physical
+    // sky, as a position in the scene's list, once one has been added.
+    sun: Option<usize>,
+    zenith: RGB,
+    horizon: RGB,
+}
+
+impl TimeOfDay {
+    // The color of the default background in the given direction, a unit
+    // vector. The ground below the horizon mirrors the sky above it.
+    pub fn radiance(&self, direction: &Vec3) -> RGB {
+        let t = direction.y.abs().min(1.);
+        self.horizon.scale(1. - t).add(&self.zenith.scale(t))
+    }
+}
+
+// A unit vector pointing towards the sun at the given hour.
+fn sun_direction(hours: f64) -> Vec3 {
+    let angle = (hours - 6.) / 12. * PI;
+    let tilt = NOON_ELEVATION.to_radians();
+    Vec3::new(
+        angle.cos(),
+        angle.sin() * tilt.sin(),
+        -angle.sin() * tilt.cos(),
+    )
+}
+
+// Rises smoothly from 0 at `from` to 1 at `to`.
+fn smoothstep(from: f64, to: f64, x: f64) -> f64 {
+    let t = ((x - from) / (to - from)).clamp(0., 1.);
+    t * t * (3. - 2. * t)
+}
+
+fn blend(a: RGB, b: RGB, t: f64) -> RGB {
+    a.scale(1. - t).add(&b.scale(t))
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Moves the sun to where it would be at the given hour of the day, from
+    // 0 to 24, and lights the scene to match. Sunlight comes in lux, so set
+    // a white point in the tens of thousands of lux for daylight. Call it
+    // every frame to animate a day and night cycle.
+    #[wasm_bindgen(js_name = setTimeOfDay)]
+    pub fn set_time_of_day(&mut self, hours: f64) {
+        if !hours.is_finite() {
+            return;
+        }
+
+        let towards = sun_direction(hours.rem_euclid(24.));
+        let elevation = towards.y.asin().to_degrees();
+
+        let night_to_twilight = smoothstep(-12., 0., elevation);
+        let twilight_to_day = smoothstep(0., 15., elevation);
+        let colors = |(zenith, horizon): (u32, u32)| (unpack(zenith), unpack(horizon));
+        let (night, twilight, day) = (colors(NIGHT), colors(TWILIGHT), colors(DAY));
+        let zenith = blend(
+            blend(night.0, twilight.0, night_to_twilight),
+            day.0,
+            twilight_to_day,
+        );
+        let horizon = blend(
+            blend(night.1, twilight.1, night_to_twilight),
+            day.1,
+            twilight_to_day,
+        );
+
+        let existing = self.time_of_day.as_ref().and_then(|day| day.sun);
+        let sun = match &self.sky {
+            Some(sky) => {
+                self.sky = Some(PhysicalSky::new(towards, sky.turbidity));
+
+                // The sky brings its own sun, so put out the stand-in.
+                if let Some(index) = existing {
+                    self.suns[index] = DirectionalLight::new(towards.scale(-1.), 0.);
+                }
+                existing
+            }
+            None => {
+                let index = existing.unwrap_or_else(|| {
+                    self.suns
+                        .push(DirectionalLight::new(towards.scale(-1.), 0.));
+                    self.suns.len() - 1
+                });
+
+                // Low in the sky, sunlight passes through more air, which
+                // dims it and scatters away its blue.
+                let lux = if towards.y > 0. {
+                    sunlight(towards.y, TURBIDITY)
+                } else {
+                    0.
+                };
+                let kelvin = 1900. + 3600. * smoothstep(0., 30., elevation);
+                let mut light = DirectionalLight::new(towards.scale(-1.), lux);
+                light.color = kelvin_to_rgb(kelvin);
+                self.suns[index] = light;
+                Some(index)
+            }
+        };
+
+        self.time_of_day = Some(TimeOfDay {
+            sun,
+            zenith,
+            horizon,
+        });
+        self.invalidate_lighting();
+        self.dirty = Some(Region::full());
+    }
+}
@@ -0,0 +1,126 @@
+use crate::material::{basis, Hit};
+use crate::rng::Rng;
+use crate::stats::Counters;
+use crate::{Ray, Scene, Sphere, RGB};
+use std::f64::consts::PI;
+
+// Next-event estimation for glowing spheres. Rays scattered off diffuse
+// surfaces only rarely happen to hit a small glowing sphere, which makes
+// whatever it lights speckled with noise. Instead, at every diffuse bounce,
+// one of them is picked, in proportion to how much light it could send the
+// point's way, and a shadow ray is cast towards a random point on it. The
+// ray scattered off the surface then leaves out any glow it finds, so that
+// it isn't counted twice.
+
+// The positions in the scene's list of spheres of those that glow, out of
+// those that scattered rays can hit.
+pub fn emitters(spheres: &[Sphere]) -> Vec<usize> {
+    spheres
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.resident && s.visibility.reflection && s.material.glows())
+        .map(|(i, _)| i)
+        .collect()
+}
+
+// A glowing sphere as seen from a point: the cone of directions in which it
+// lies, and how much light it could send along them.
+struct Sight {
+    index: usize,
+    cos_max: f64,
+    solid_angle: f64,
+    weight: f64,
+}
+
+impl Scene {
+    // Samples the light that glowing spheres shine on the diffuse surface at
+    // `hit`, as rendered color values, like those of scattered light.
+    pub(crate) fn sample_emitters(&self, hit: &Hit, rng: &mut Rng) -> RGB {
+        let time = self.shutter.time.get();
+        let sights: Vec<Sight> = self
+            .emitters
+            .iter()
+            .filter_map(|&index| {
+                let sphere = &self.spheres[index];
+                let dist_sqr = sphere.center_at(time).subtract(&hit.point).length_sqr();
+                let radius_sqr = sphere.radius * sphere.radius;
+
+                // From inside a sphere, it fills every direction, and can't be
+                // sampled as a cone.
+                if dist_sqr <= radius_sqr {
+                    return None;
+                }
+
+                let cos_max = (1. - radius_sqr / dist_sqr).sqrt();
+                let solid_angle = 2. * PI * (1. - cos_max);
+                let glow = sphere.material.emission(&sphere.hit(sphere.top(), 0., 0.));
+                Some(Sight {
+                    index,
+                    cos_max,
+                    solid_angle,
+                    weight: glow.average() * solid_angle,
+                })
+            })
+            .collect();
+
+        let total: f64 = sights.iter().map(|s| s.weight).sum();
+        if total <= 0. {
+            return RGB::black();
+        }
+
+        // Pick one of the spheres, in proportion to its weight.
+        let mut pick = rng.next_f64() * total;
+        let sight = sights
+            .iter()
+            .find(|s| {
+                pick -= s.weight;
+                pick < 0.
+            })
+            .unwrap_or(&sights[sights.len() - 1]);
+        let probability = sight.weight / total;
+
+        // Then a direction within its cone, uniformly.
+        let sphere = &self.spheres[sight.index];
+        let center = sphere.center_at(time);
+        let axis = center.subtract(&hit.point).unit();
+        let (tangent, bitangent) = basis(&axis);
+        let cos_theta = 1. - rng.next_f64() * (1. - sight.cos_max);
+        let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+        let phi = 2. * PI * rng.next_f64();
+        let direction = tangent
+            .scale(sin_theta * phi.cos())
+            .add(&bitangent.scale(sin_theta * phi.sin()))
+            .add(&axis.scale(cos_theta));
+
+        let cosine = hit.normal.dot(&direction);
+        if cosine <= 0. {
+            return RGB::black();
+        }
+
+        // Where the direction meets the sphere. Grazing the edge of the cone,
+        // rounding errors can make it miss by a hair.
+        let ray = Ray::new(hit.point, direction);
+        let oc = hit.point.subtract(&center);
+        let b = oc.dot(&direction);
+        let c = oc.length_sqr() - sphere.radius * sphere.radius;
+        let t = -b - (b * b - c).max(0.).sqrt();
+
+        // The sphere itself blocks shadow rays, so stop just short of it.
+        Counters::bump(&self.stats.shadow_rays, 1);
+        let max_t = t * (1. - 1e-9);
+        if self.occluders.occluded(&ray, max_t, &self.stats) {
+            return RGB::black();
+        }
+
+        // Rays scattered off a white diffuse surface would see the glow with
+        // the probability of a cosine-weighted direction, cos θ / π, rather
+        // than the probability of this one.
+        let glow = sphere
+            .material
+            .emission(&sphere.hit(ray.point_at(t), time, 0.));
+        let irradiance = cosine * sight.solid_angle / (PI * probability);
+        self.transmittance_along(&ray, max_t)
+            .tint(&glow)
+            .scale(irradiance * self.response())
+    }
+}
@@ -0,0 +1,93 @@
+use crate::background::Background;
+use crate::region::Region;
+use crate::texture::{holds_image, MipLevel};
+use crate::{Scene, Vec3, RGB};
+use std::f64::consts::PI;
+use wasm_bindgen::prelude::*;
+
+// An equirectangular image of the surroundings, which stands in for
+// everything beyond the spheres: it's seen in the background, and rays that
+// bounce off into it, whether reflected or scattered diffusely, bring back its
+// light. Bright parts of the image, like the sun, light the scene much as a
+// light of their own would, so values are high dynamic range.
+pub struct Environment {
+    image: MipLevel,
+    intensity: f64,
+    // How far the image is turned about the vertical axis, in radians.
+    rotation: f64,
+}
+
+impl Environment {
+    // The light arriving from the given direction, a unit vector. The middle
+    // of the image lies straight down the Z axis, with its top edge above.
+    pub fn radiance(&self, direction: &Vec3) -> RGB {
+        let azimuth = direction.x.atan2(direction.z) - self.rotation;
+        let u = (0.5 + azimuth / (2. * PI)).rem_euclid(1.);
+        let v = direction.y.clamp(-1., 1.).acos() / PI;
+        self.image.bilinear(u, v).scale(self.intensity)
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Surrounds the scene with an equirectangular `width` × `height` image,
+    // given as linear RGB floats row by row from the top, as decoded from an
+    // HDR or EXR file. It replaces the background gradient, and lights the
+    // scene through every ray that escapes into it, scaled by `intensity`. An
+    // empty image, or one with too few values, turns it off again.
+    #[wasm_bindgen(js_name = setEnvironmentMap)]
+    pub fn set_environment_map(
+        &mut self,
+        width: usize,
+        height: usize,
+        rgb: &[f32],
+        intensity: f64,
+    ) {
+        self.environment = if !holds_image(rgb.len(), width, height, 3) {
+            None
+        } else {
+            let texels = rgb
+                .chunks_exact(3)
+                .take(width * height)
+                .map(|p| RGB::new(p[0] as f64, p[1] as f64, p[2] as f64))
+                .collect();
+            let rotation = self.environment.as_ref().map_or(0., |env| env.rotation);
+            Some(Environment {
+                image: MipLevel::new(width, height, texels),
+                intensity: intensity.max(0.),
+                rotation,
+            })
+        };
+        self.forget_irradiance();
+        self.dirty = Some(Region::full());
+    }
+
+    // Turns the environment map about the vertical axis by the given angle
+    // in degrees, anticlockwise seen from above, to move its sun around.
+    #[wasm_bindgen(js_name = setEnvironmentRotation)]
+    pub fn set_environment_rotation(&mut self, degrees: f64) {
+        if let Some(env) = &mut self.environment {
+            env.rotation = degrees.to_radians();
+            self.forget_irradiance();
+            self.dirty = Some(Region::full());
+        }
+    }
+}
+
+impl Scene {
+    // The color seen along a ray that escapes the scene.
+    pub(crate) fn background(&self, direction: &Vec3) -> RGB {
+        if let Some(env) = &self.environment {
+            return env.radiance(direction);
+        }
+        if let Some(sky) = &self.sky {
+            return sky.radiance(direction).scale(self.response());
+        }
+
+        // The time of day only colors the default background.
+        match (&self.backdrop, &self.time_of_day) {
+            (Background::Classic, Some(day)) => day.radiance(direction),
+            _ => self.backdrop.radiance(direction),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use crate::units::nits_to_lux;
+use crate::Scene;
+use wasm_bindgen::prelude::*;
+
+// Photographic exposure. Rather than picking a white point by hand, the
+// camera can be set up the way a real one would be, with an aperture, a
+// shutter speed and a sensor sensitivity. Lights can then keep their real
+// ratings, and a setting that would expose a photograph of the same scene
+// well does the same here.
+//
+// This follows the saturation-based sensitivity of ISO 12232, as commonly
+// used in physically based renderers: the luminance at which the sensor
+// saturates is 78 / (S × q) × N² / t, with a lens and vignetting factor q
+// of 0.65, which comes to 1.2 × N² / t × 100 / S.
+
+// The ratio between the saturating luminance and N² / t × 100 / S.
+const SATURATION: f64 = 78. / (100. * 0.65);
+
+// The illuminance, in lux, at which a white diffuse surface saturates a
+// sensor with the given sensitivity, behind a lens with the given f-number,
+// when exposed for the given number of seconds.
+pub fn saturating_illuminance(f_number: f64, seconds: f64, iso: f64) -> f64 {
+    let luminance = SATURATION * f_number * f_number / seconds * 100. / iso;
+    nits_to_lux(luminance)
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Exposes the image as a camera with the given f-number, shutter speed in
+    // seconds and ISO sensitivity would, by setting the white point to match.
+    // For example, f/16 at 1/100s and ISO 100 suits a sunny day, and f/2 at
+    // 1/30s and ISO 800 a dim living room. Settings that aren't positive are
+    // ignored.
+    #[wasm_bindgen(js_name = setExposure)]
+    pub fn set_exposure(&mut self, f_number: f64, seconds: f64, iso: f64) {
+        let lux = saturating_illuminance(f_number, seconds, iso);
+        if f_number > 0. && seconds > 0. && iso > 0. && lux.is_finite() && lux > 0. {
+            self.set_white_point(lux);
+        }
+    }
+}
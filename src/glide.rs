@@ -0,0 +1,168 @@
+use crate::region::Region;
+use crate::{Camera, Scene, Vec3};
+use wasm_bindgen::prelude::*;
+
+// Smooth camera moves. Rather than jumping straight to a new pose, the camera
+// glides there over a given time, advanced by `tick` once a frame, slowing
+// down as it arrives. The camera is moved by how far the glide progressed
+// since the last tick, so anything else that nudges it in the meantime, such
+// as handheld shake, carries on working. The camera can also be set moving
+// at a steady velocity, which `tick` carries it along at too.
+#[derive(Copy, Clone)]
+pub struct Glide {
+    // How far the eye and the pivot move over the whole glide.
+    eye: Vec3,
+    pivot: Vec3,
+    // Whether the camera turns to keep facing its pivot as it goes.
+    turn: bool,
+    // In seconds.
+    duration: f64,
+    elapsed: f64,
+}
+
+// Eases out of the glide: quickly at first, then gently into place.
+fn ease(u: f64) -> f64 {
+    1. - (1. - u).powi(3)
+}
+
+impl Glide {
+    // The fraction of the way along the glide, eased.
+    fn progress(&self) -> f64 {
+        if self.duration > 0. {
+            ease((self.elapsed / self.duration).min(1.))
+        } else {
+            1.
+        }
+    }
+}
+
+impl Camera {
+    // Starts gliding from where the camera is to look at `target` from `eye`,
+    // over `duration` seconds.
+    pub(crate) fn glide_to(&mut self, eye: &Vec3, target: &Vec3, duration: f64) {
+        self.glide = Some(Glide {
+            eye: eye.subtract(&self.eye),
+            pivot: target.subtract(&self.pivot),
+            turn: true,
+            duration,
+            elapsed: 0.,
+        });
+    }
+
+    // Starts gliding by `offset`, without turning, over `duration` seconds.
+    // A glide already under way has what's left of it added on, so repeated
+    // steps build up rather than cutting each other short.
+    pub(crate) fn glide_by(&mut self, offset: &Vec3, duration: f64) {
+        let remaining = match &self.glide {
+            Some(glide) => glide.eye.scale(1. - glide.progress()),
+            None => Vec3::new(0., 0., 0.),
+        };
+        let eye = remaining.add(offset);
+        self.glide = Some(Glide {
+            eye,
+            pivot: eye,
+            turn: false,
+            duration,
+            elapsed: 0.,
+        });
+    }
+
+    // Carries the camera `dt` seconds further along its glide, and returns
+    // whether it moved.
+    pub(crate) fn advance(&mut self, dt: f64) -> bool {
+        let mut glide = match self.glide {
+            Some(glide) => glide,
+            None => return false,
+        };
+
+        let before = glide.progress();
+        glide.elapsed += dt;
+        let after = glide.progress();
+
+        let step = after - before;
+        self.eye = self.eye.add(&glide.eye.scale(step));
+        let pivot = self.pivot.add(&glide.pivot.scale(step));
+        if glide.turn {
+            self.look_at(&pivot);
+        }
+        self.pivot = pivot;
+
+        self.glide = if after < 1. { Some(glide) } else { None };
+        true
+    }
+
+    // Carries the camera along at its velocity for `dt` seconds, and returns
+    // whether it moved. Like the move methods, motion across and ahead
+    // follows the way the camera faces, while motion up and down stays
+    // vertical.
+    pub(crate) fn coast(&mut self, dt: f64) -> bool {
+        let v = self.velocity;
+        if v.x == 0. && v.y == 0. && v.z == 0. {
+            return false;
+        }
+
+        let offset = self
+            .right
+            .scale(v.x)
+            .add(&Vec3::new(0., v.y, 0.))
+            .add(&self.forward.scale(v.z));
+        self.translate(&offset.scale(dt));
+        true
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Glides the camera over `duration` seconds to look at `target` from
+    // `eye`, both given as [x, y, z] arrays. Call `tick` every frame to move
+    // it along.
+    #[wasm_bindgen(js_name = glideTo)]
+    pub fn glide_to(&mut self, eye: &[f64], target: &[f64], duration: f64) {
+        if eye.len() < 3 || target.len() < 3 || !duration.is_finite() || duration < 0. {
+            return;
+        }
+
+        let eye = Vec3::new(eye[0], eye[1], eye[2]);
+        let target = Vec3::new(target[0], target[1], target[2]);
+        self.camera.glide_to(&eye, &target, duration);
+    }
+
+    // Smooths out the steps taken by the move methods over the given number
+    // of seconds, rather than jumping a whole step at once. Zero, the
+    // default, jumps.
+    #[wasm_bindgen(js_name = setMoveSmoothing)]
+    pub fn set_move_smoothing(&mut self, seconds: f64) {
+        if seconds >= 0. && seconds.is_finite() {
+            self.camera.smoothing = seconds;
+        }
+    }
+
+    // Keeps the camera moving at the given speeds, in metres per second, to
+    // the right, up and forward, while `tick` is called. Hosts can set this
+    // while a key is held and clear it on release for motion that doesn't
+    // depend on the frame rate.
+    #[wasm_bindgen(js_name = setVelocity)]
+    pub fn set_velocity(&mut self, vx: f64, vy: f64, vz: f64) {
+        if vx.is_finite() && vy.is_finite() && vz.is_finite() {
+            self.camera.velocity = Vec3::new(vx, vy, vz);
+        }
+    }
+
+    // Moves the camera `dt` seconds further along, and returns whether it
+    // moved, so that the host knows to render another frame.
+    pub fn tick(&mut self, dt: f64) -> bool {
+        if !dt.is_finite() || dt <= 0. {
+            return false;
+        }
+
+        let glided = self.camera.advance(dt);
+        let coasted = self.camera.coast(dt);
+        if !glided && !coasted {
+            return false;
+        }
+
+        self.follow_camera();
+        self.invalidate(Region::full());
+        true
+    }
+}
@@ -0,0 +1,81 @@
+use crate::holdout::Footprint;
+use crate::rng::Rng;
+use crate::spheres::SphereSet;
+use crate::{Image, Integrator, Scene, RGB};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+impl Scene {
+    // Renders a reference for part of `frame`, to judge how far the
+    // interactive render strays from the truth: the `width` × `height` pixels
+    // with their top-left corner at (x, y), path traced with `samples` paths
+    // per pixel, spread over the whole area of each pixel. This ignores the
+    // chosen integrator, since every shortcut it takes is one more source of
+    // error. The result comes back as an image of its own, written with the
+    // frame's color space and grading so that the two can be compared as is.
+    #[wasm_bindgen(js_name = renderGroundTruth)]
+    pub fn render_ground_truth(
+        &mut self,
+        frame: &Image,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        samples: usize,
+    ) -> Image {
+        let mut img = frame.blank(width, height);
+        let samples = samples.max(1);
+
+        let integrator = self.integrator;
+        self.integrator = Integrator::PathTracing;
+        self.stats.reset();
+
+        let height_inv = 1. / frame.height as f64;
+        let width_inv = 1. / frame.width as f64;
+        let visible = self.visible_spheres();
+
+        for py in 0..height {
+            for px in 0..width {
+                let (color, alpha) =
+                    self.supersample(&visible, x + px, y + py, width_inv, height_inv, samples);
+                img.draw_alpha(px, py, &color, alpha);
+            }
+        }
+
+        self.integrator = integrator;
+        img
+    }
+}
+
+impl Scene {
+    // Traces `samples` paths through the pixel at (x, y), each through a random
+    // point within it, and returns their average color and opacity.
+    pub(crate) fn supersample(
+        &self,
+        visible: &SphereSet,
+        x: usize,
+        y: usize,
+        width_inv: f64,
+        height_inv: f64,
+        samples: usize,
+    ) -> (RGB, f64) {
+        let mut rng = Rng::for_pixel(x, y);
+        let mut color = RGB::black();
+        let mut alpha = 0.;
+
+        for _ in 0..samples {
+            let u = (x as f64 + rng.next_f64()) * width_inv;
+            let v = (y as f64 + rng.next_f64()) * height_inv;
+            let ray = self.primary_ray(visible, u, v, &mut rng);
+            let nearest = self.nearest_visible(visible, &ray);
+            let pixel = Footprint::new(x, y, width_inv, height_inv);
+
+            let (c, a) = self.shade_pixel(visible, pixel, &ray, nearest, &mut rng);
+            color = color.add(&c);
+            alpha += a;
+        }
+
+        let n = samples as f64;
+        (color.scale(1. / n), alpha / n)
+    }
+}
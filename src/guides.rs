@@ -0,0 +1,120 @@
+use crate::region::Region;
+use crate::{Image, Scene, Vec3};
+use wasm_bindgen::prelude::*;
+
+// Orientation guides, drawn over the rendered image with the rasterizer. They
+// are overlays rather than part of the scene, so spheres never hide them.
+
+// Grid lines are drawn one unit apart, out to this distance from the camera.
+const GRID_SPACING: f64 = 1.;
+const GRID_EXTENT: f64 = 50.;
+
+// Towards the horizon, lines running across the view get closer and closer
+// together. Those less than this many pixels below the last one are skipped,
+// rather than blurring into a solid band.
+const GRID_MIN_GAP: f64 = 4.;
+
+const GRID_COLOR: u32 = 0x80_80_80;
+
+// The axis gizmo is centered this many pixels in from the bottom-left corner
+// of the image, and each of its axes is drawn this many pixels long.
+const GIZMO_INSET: f64 = 32.;
+const GIZMO_SIZE: f64 = 20.;
+
+#[wasm_bindgen]
+impl Scene {
+    // Draws a grid on the horizontal plane at the given height after every
+    // render, or stops drawing it when passed `undefined`. The lines along the
+    // X and Z axes are picked out in red and blue respectively.
+    #[wasm_bindgen(js_name = setGroundGrid)]
+    pub fn set_ground_grid(&mut self, height: Option<f64>) {
+        self.ground_grid = height;
+        self.dirty = Some(Region::full());
+    }
+
+    // Draws a gizmo showing the directions of the X, Y and Z axes in the
+    // bottom-left corner of the image after every render.
+    #[wasm_bindgen(js_name = setAxisGizmo)]
+    pub fn set_axis_gizmo(&mut self, enabled: bool) {
+        self.axis_gizmo = enabled;
+        self.dirty = Some(Region::full());
+    }
+}
+
+impl Scene {
+    pub(crate) fn draw_guides(&self, img: &mut Image) {
+        if let Some(height) = self.ground_grid {
+            self.draw_ground_grid(img, height);
+        }
+
+        if self.axis_gizmo {
+            self.draw_axis_gizmo(img);
+        }
+
+        if let Some(hud) = &self.hud {
+            self.draw_hud(img, hud);
+        }
+    }
+
+    fn draw_ground_grid(&self, img: &mut Image, height: f64) {
+        let eye = &self.camera.eye;
+        let snap = |v: f64| (v / GRID_SPACING).round() * GRID_SPACING;
+        let (x0, x1) = (snap(eye.x - GRID_EXTENT), snap(eye.x + GRID_EXTENT));
+        let (z0, z1) = (snap(eye.z - GRID_EXTENT), snap(eye.z + GRID_EXTENT));
+        let color = |v: f64, axis: u32| if v == 0. { axis } else { GRID_COLOR };
+
+        // Lines running away from the camera, parallel to the Z axis.
+        let mut x = x0;
+        while x <= x1 {
+            let (from, to) = (Vec3::new(x, height, z0), Vec3::new(x, height, z1));
+            self.draw_segment(img, &from, &to, color(x, 0x00_00_FF));
+            x += GRID_SPACING;
+        }
+
+        // Lines running across the view, parallel to the X axis, from the
+        // nearest to the furthest.
+        let mut last_y = f64::INFINITY;
+        let mut z = z0;
+        while z <= z1 {
+            let center = Vec3::new(eye.x, height, z);
+            if let Some((_, y)) = self.camera.project(&center) {
+                let y = y * img.height as f64;
+                if (last_y - y).abs() >= GRID_MIN_GAP {
+                    let (from, to) = (Vec3::new(x0, height, z), Vec3::new(x1, height, z));
+                    self.draw_segment(img, &from, &to, color(z, 0xFF_00_00));
+                    last_y = y;
+                }
+            }
+            z += GRID_SPACING;
+        }
+    }
+
+    fn draw_axis_gizmo(&self, img: &mut Image) {
+        let (w, h) = (img.width as f64, img.height as f64);
+        let eye = &self.camera.eye;
+        let film = &self.camera.film;
+
+        // Anchor the gizmo a fixed distance past the film, along the ray
+        // through its corner of the image, then size its axes to match.
+        let depth = 4.;
+        let corner = film.project(GIZMO_INSET / w, 1. - GIZMO_INSET / h);
+        let anchor = eye.add(&self.camera.world_direction(&corner).scale(depth));
+        let length = GIZMO_SIZE * film.width / w * depth;
+
+        let axes = [
+            (Vec3::new(length, 0., 0.), 0xFF_00_00, "X"),
+            (Vec3::new(0., length, 0.), 0x00_FF_00, "Y"),
+            (Vec3::new(0., 0., length), 0x00_00_FF, "Z"),
+        ];
+
+        for (axis, color, label) in &axes {
+            let tip = anchor.add(axis);
+            self.draw_segment(img, &anchor, &tip, *color);
+
+            if let Some((x, y)) = self.camera.project(&tip) {
+                let (x, y) = ((x * w).round() as i32, (y * h).round() as i32);
+                img.draw_text(x + 2, y - 2, label, 1, *color);
+            }
+        }
+    }
+}
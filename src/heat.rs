@@ -0,0 +1,62 @@
+use crate::holdout::Footprint;
+use crate::rng::Rng;
+use crate::texture::holds_image;
+use crate::Scene;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+impl Scene {
+    // Writes a path heat AOV for a `width` × `height` frame into `out`, as
+    // interleaved (bounces, length) pairs, to show where mirrors and glass
+    // make the tracer work hardest. Bounces counts the secondary rays traced
+    // for the pixel, and length the total distance, in world units, that its
+    // rays travelled before hitting something. Russian roulette makes both
+    // vary from one render to the next, so each is averaged over `samples`
+    // renders of the pixel. Buffers too small to hold the whole frame are
+    // left untouched, and the statistics of the last frame are kept.
+    #[wasm_bindgen(js_name = renderPathHeat)]
+    pub fn render_path_heat(
+        &mut self,
+        width: usize,
+        height: usize,
+        samples: usize,
+        out: &mut [f32],
+    ) {
+        if !holds_image(out.len(), width, height, 2) {
+            return;
+        }
+        self.fit_film(width, height);
+        let stats = self.stats.clone();
+
+        let samples = samples.max(1);
+        let height_inv = 1. / height as f64;
+        let width_inv = 1. / width as f64;
+        let visible = self.visible_spheres();
+
+        for y in 0..height {
+            for x in 0..width {
+                let mut rng = Rng::for_pixel(x, y);
+                let pixel = Footprint::new(x, y, width_inv, height_inv);
+                let ray = self
+                    .camera
+                    .cast(x as f64 * width_inv, y as f64 * height_inv);
+                let nearest = self.nearest_visible(&visible, &ray);
+
+                let bounces = self.stats.reflection_rays.get();
+                let length = self.stats.path_length.get();
+                for _ in 0..samples {
+                    self.shade_pixel(&visible, pixel, &ray, nearest, &mut rng);
+                }
+
+                let bounces = self.stats.reflection_rays.get() - bounces;
+                let length = self.stats.path_length.get() - length;
+
+                let idx = 2 * (y * width + x);
+                out[idx] = (bounces as f64 / samples as f64) as f32;
+                out[idx + 1] = (length / samples as f64) as f32;
+            }
+        }
+
+        self.stats.restore(&stats);
+    }
+}
@@ -0,0 +1,109 @@
+use crate::rng::Rng;
+use crate::spheres::SphereSet;
+use crate::{Ray, Scene, RGB};
+use wasm_bindgen::prelude::*;
+
+// Holdouts cut holes in the image where the camera sees them, leaving pixels
+// transparent so that another layer can show through when compositing. They
+// still show up in reflections and cast shadows as usual.
+//
+// A holdout's edge rarely lines up with the edge of a pixel, so the fraction
+// of each pixel it covers is measured with a grid of this many samples along
+// each side. This only costs intersection tests, not shading, and is skipped
+// entirely in scenes without holdouts.
+pub const COVERAGE_SAMPLES: usize = 4;
+
+// The patch of film that a pixel covers, with its top-left corner at (u, v),
+// as fractions of the film's width and height.
+#[derive(Copy, Clone)]
+pub struct Footprint {
+    u: f64,
+    v: f64,
+    du: f64,
+    dv: f64,
+}
+
+impl Footprint {
+    pub fn new(x: usize, y: usize, width_inv: f64, height_inv: f64) -> Self {
+        Self {
+            u: x as f64 * width_inv,
+            v: y as f64 * height_inv,
+            du: width_inv,
+            dv: height_inv,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Makes the sphere at `index` a holdout, or an ordinary sphere again.
+    #[wasm_bindgen(js_name = setSphereHoldout)]
+    pub fn set_sphere_holdout(&mut self, index: usize, holdout: bool) {
+        let sphere = match self.spheres.get_mut(index) {
+            Some(sphere) => sphere,
+            None => return,
+        };
+        sphere.holdout = holdout;
+        let region = self.sphere_bounds(&self.spheres[index]);
+
+        self.holdouts = self.spheres.iter().any(|s| s.holdout);
+        self.invalidate(region);
+    }
+}
+
+impl Scene {
+    // Computes the color and opacity of a pixel, given the primary ray through
+    // its corner and the nearest sphere that ray hits, out of those visible to
+    // the camera.
+    pub(crate) fn shade_pixel(
+        &self,
+        visible: &SphereSet,
+        pixel: Footprint,
+        ray: &Ray,
+        nearest: Option<(usize, f64)>,
+        rng: &mut Rng,
+    ) -> (RGB, f64) {
+        self.pixel_spread.set(self.camera.spread(pixel.du));
+
+        if !self.holdouts {
+            return self.shade_primary(ray, nearest, rng);
+        }
+
+        let n = COVERAGE_SAMPLES;
+        let mut uncovered = 0;
+        let mut uncovered_ray = None;
+
+        for i in 0..n {
+            for j in 0..n {
+                let u = pixel.u + (i as f64 + 0.5) / n as f64 * pixel.du;
+                let v = pixel.v + (j as f64 + 0.5) / n as f64 * pixel.dv;
+                let sample = self.camera.cast(u, v);
+
+                match self.nearest_visible(visible, &sample) {
+                    Some((i, _)) if self.spheres[i].holdout => {}
+                    _ => {
+                        uncovered += 1;
+                        uncovered_ray.get_or_insert(sample);
+                    }
+                }
+            }
+        }
+
+        let coverage = uncovered as f64 / (n * n) as f64;
+
+        // Pixels whose corner lies on a holdout take their color from a part
+        // of the pixel that doesn't.
+        let (color, alpha) = match nearest {
+            Some((i, _)) if self.spheres[i].holdout => match uncovered_ray {
+                Some(ray) => {
+                    let nearest = self.nearest_visible(visible, &ray);
+                    self.shade_primary(&ray, nearest, rng)
+                }
+                None => (RGB::black(), 0.),
+            },
+            _ => self.shade_primary(ray, nearest, rng),
+        };
+
+        (color, alpha * coverage)
+    }
+}
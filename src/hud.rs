@@ -0,0 +1,120 @@
+use crate::accel::Accelerator;
+use crate::raster::text_size;
+use crate::region::Region;
+use crate::{utils, Image, Integrator, Scene};
+use std::cell::Cell;
+use wasm_bindgen::prelude::*;
+
+// A heads-up display of the tracer's statistics and settings, drawn over the
+// top-left corner of every frame with the rasterizer, so that demos don't
+// need a UI of their own to show how fast they're going.
+#[derive(Default)]
+pub struct Hud {
+    // When the last frame was finished, in milliseconds, and a running
+    // average of the frame rate, if there's been a frame to measure it by.
+    last_frame: Cell<Option<f64>>,
+    fps: Cell<Option<f64>>,
+}
+
+// How much each frame counts towards the average frame rate. Smaller weights
+// steady the number, but make it slower to catch up with changes.
+const FPS_WEIGHT: f64 = 0.1;
+
+const HUD_SCALE: i32 = 2;
+const HUD_PADDING: i32 = 4;
+const HUD_COLOR: u32 = 0xFF_FF_FF;
+const HUD_BACKGROUND: u32 = 0x20_20_20;
+
+#[wasm_bindgen]
+impl Scene {
+    // Draws the frame rate, the rays traced per second, the samples per pixel,
+    // the memory in use and the main render settings over the top-left corner
+    // of the image after every render, or stops drawing them.
+    #[wasm_bindgen(js_name = setStatsHud)]
+    pub fn set_stats_hud(&mut self, enabled: bool) {
+        self.hud = if enabled { Some(Hud::default()) } else { None };
+        self.dirty = Some(Region::full());
+    }
+}
+
+impl Scene {
+    pub(crate) fn draw_hud(&self, img: &mut Image, hud: &Hud) {
+        let now = utils::now();
+        if let Some(last) = hud.last_frame.replace(Some(now)) {
+            if now > last {
+                let fps = 1000. / (now - last);
+                let average = hud
+                    .fps
+                    .get()
+                    .map_or(fps, |avg| avg + (fps - avg) * FPS_WEIGHT);
+                hud.fps.set(Some(average));
+            }
+        }
+
+        let stats = &self.stats;
+        let rays = stats.primary_rays.get() + stats.shadow_rays.get() + stats.reflection_rays.get();
+        let elapsed = (now - stats.started.get()) / 1000.;
+        let pixels = (img.width * img.height).max(1);
+
+        let mut lines = vec![
+            match hud.fps.get() {
+                Some(fps) => format!("FPS {:.1}", fps),
+                None => "FPS -".to_string(),
+            },
+            if elapsed > 0. {
+                format!("RAYS/S {:.0}", rays as f64 / elapsed)
+            } else {
+                "RAYS/S -".to_string()
+            },
+            format!("SPP {:.2}", stats.primary_rays.get() as f64 / pixels as f64),
+        ];
+
+        if let Some(map) = &self.photon_map {
+            if self.integrator == Integrator::PhotonMapping {
+                lines.push(format!("PASSES {}", map.passes()));
+            }
+        }
+        if let Some(bytes) = utils::memory_bytes() {
+            lines.push(format!("MEM {:.1} MB", bytes as f64 / (1024. * 1024.)));
+        }
+        lines.push(self.settings_summary());
+
+        let line_height = text_size("", HUD_SCALE).1 + HUD_SCALE;
+        let width = lines
+            .iter()
+            .map(|l| text_size(l, HUD_SCALE).0)
+            .max()
+            .unwrap_or(0);
+        let height = lines.len() as i32 * line_height - HUD_SCALE;
+
+        // The background is drawn solid, rather than blended, so that it also
+        // covers whatever the HUD said last time in parts of the image that
+        // weren't rendered again.
+        img.fill_rect(
+            0,
+            0,
+            width + 2 * HUD_PADDING,
+            height + 2 * HUD_PADDING,
+            HUD_BACKGROUND,
+        );
+        for (i, line) in lines.iter().enumerate() {
+            let y = HUD_PADDING + i as i32 * line_height;
+            img.draw_text(HUD_PADDING, y, line, HUD_SCALE, HUD_COLOR);
+        }
+    }
+
+    fn settings_summary(&self) -> String {
+        let accel = match self.accel.kind {
+            Accelerator::BruteForce => "BRUTE",
+            Accelerator::Bvh => "BVH",
+            Accelerator::Grid => "GRID",
+            Accelerator::KdTree => "KD",
+        };
+
+        let mut summary = format!("{} {}", self.integrator.name(), accel);
+        if self.packet_tracing {
+            summary.push_str(" PACKETS");
+        }
+        summary
+    }
+}
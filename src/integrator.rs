@@ -0,0 +1,38 @@
+use wasm_bindgen::prelude::*;
+
+// The method used to work out the light that reaches each pixel.
+#[wasm_bindgen]
+#[derive(Copy, Clone, PartialEq)]
+pub enum Integrator {
+    // Follows the rays scattered by every surface, as its material dictates,
+    // through reflections and refractions. Diffuse surfaces also sample the
+    // glow of emissive spheres directly, rather than waiting for scattered
+    // rays to find them.
+    PathTracing,
+    // Traces a single primary ray per pixel, plus a shadow ray per light, and
+    // nothing else: surfaces show their own color, lit by the lights, with no
+    // reflections. This is the fastest mode, meant for interactive use with
+    // large scenes.
+    LitPreview,
+    // Path tracing, plus caustics found by progressive photon mapping, which
+    // converge over successive calls to `render`. The other render methods
+    // leave caustics out, and trace paths as PathTracing does.
+    PhotonMapping,
+    // Renders each surface in shades of grey by how open it is to the sky,
+    // with the settings of `setAmbientOcclusion`, or its defaults if it's off.
+    // This shows the contact shadows that ambient occlusion adds, and nothing
+    // else.
+    AmbientOcclusion,
+}
+
+impl Integrator {
+    // The name of the integrator, as it's known to JS.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Integrator::PathTracing => "PathTracing",
+            Integrator::LitPreview => "LitPreview",
+            Integrator::PhotonMapping => "PhotonMapping",
+            Integrator::AmbientOcclusion => "AmbientOcclusion",
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use crate::material::{basis, Hit};
+use crate::region::Region;
+use crate::rng::Rng;
+use crate::stats::Counters;
+use crate::{Ray, Sampled, Scene, Vec3, RGB};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use wasm_bindgen::prelude::*;
+
+// Irradiance caching, after Ward, Rubinstein and Clear (1988). Light bounced
+// off other surfaces changes slowly across most diffuse surfaces, so rather
+// than scattering a single noisy ray at every pixel, many rays are traced
+// over the whole hemisphere at a sparse set of points, and the light they
+// gather is interpolated in between. Each record is valid for a distance that
+// grows with how far away the surfaces it saw were, so points are packed
+// tightly into corners and creases, and spread far apart in the open. The
+// cache only stands in for light bounced onto surfaces seen by the camera;
+// deeper bounces are path traced as usual.
+pub struct IrradianceCache {
+    // The number of rays traced over the hemisphere for each record.
+    samples: usize,
+    // How far from a record its light may be reused, relative to its radius,
+    // which also caps how different the surface may be facing.
+    accuracy: f64,
+    // Filled in as frames are rendered.
+    records: RefCell<Records>,
+}
+
+#[derive(Default)]
+struct Records {
+    list: Vec<Record>,
+    // The positions in `list` of the records whose reach overlaps each cell
+    // of a uniform grid.
+    grid: HashMap<(i64, i64, i64), Vec<usize>>,
+}
+
+struct Record {
+    position: Vec3,
+    normal: Vec3,
+    // The light arriving at the point, averaged over the hemisphere with a
+    // cosine weighting, in rendered color values.
+    irradiance: RGB,
+    // The harmonic mean of the distances to the surfaces it saw.
+    radius: f64,
+}
+
+// The range that records' radii are clamped to, in metres. The smallest keeps
+// records from piling up where spheres touch, and the largest keeps them from
+// spreading too far over open ground.
+const MIN_RADIUS: f64 = 0.1;
+const MAX_RADIUS: f64 = 10.;
+
+// The size of the grid's cells, in metres.
+const CELL_SIZE: f64 = 1.;
+
+fn cell(p: &Vec3) -> (i64, i64, i64) {
+    (
+        (p.x / CELL_SIZE).floor() as i64,
+        (p.y / CELL_SIZE).floor() as i64,
+        (p.z / CELL_SIZE).floor() as i64,
+    )
+}
+
+impl IrradianceCache {
+    // Interpolates the light arriving at a point from the records around it,
+    // weighted by how close they are and how alike they face, or returns
+    // `None` if none of them reach it.
+    fn lookup(&self, point: &Vec3, normal: &Vec3) -> Option<RGB> {
+        let records = self.records.borrow();
+        let nearby = records.grid.get(&cell(point))?;
+
+        let mut sum = RGB::black();
+        let mut total = 0.;
+        for &i in nearby {
+            let record = &records.list[i];
+            let offset = point.subtract(&record.position);
+            let error =
+                offset.length() / record.radius + (1. - normal.dot(&record.normal)).max(0.).sqrt();
+            if error >= self.accuracy {
+                continue;
+            }
+
+            // A record in front of the point may see light that the point
+            // itself can't, such as the inside of a crease.
+            if offset.dot(&normal.add(&record.normal)) < -0.1 * record.radius {
+                continue;
+            }
+
+            let weight = 1. / error.max(1e-6);
+            sum = sum.add(&record.irradiance.scale(weight));
+            total += weight;
+        }
+
+        if total > 0. {
+            Some(sum.scale(1. / total))
+        } else {
+            None
+        }
+    }
+
+    fn insert(&self, record: Record) {
+        let mut records = self.records.borrow_mut();
+        let index = records.list.len();
+
+        let reach = self.accuracy * record.radius;
+        let r = Vec3::new(reach, reach, reach);
+        let (x0, y0, z0) = cell(&record.position.subtract(&r));
+        let (x1, y1, z1) = cell(&record.position.add(&r));
+        for x in x0..=x1 {
+            for y in y0..=y1 {
+                for z in z0..=z1 {
+                    records.grid.entry((x, y, z)).or_default().push(index);
+                }
+            }
+        }
+        records.list.push(record);
+    }
+}
+
+impl Scene {
+    // The light arriving at the diffuse surface at `hit`, seen by a camera
+    // ray, from the irradiance cache, or `None` if caching is off. Where no
+    // record reaches, a new one is traced.
+    pub(crate) fn cached_irradiance(
+        &self,
+        hit: &Hit,
+        throughput: f64,
+        rng: &mut Rng,
+    ) -> Option<RGB> {
+        let cache = self.irradiance_cache.as_ref()?;
+        if let Some(irradiance) = cache.lookup(&hit.point, &hit.normal) {
+            return Some(irradiance);
+        }
+
+        // Stratify the hemisphere into a grid of cells of equal probability
+        // under a cosine weighting, and trace a ray through each of them.
+        let (tangent, bitangent) = basis(&hit.normal);
+        let side = (cache.samples as f64).sqrt().ceil() as usize;
+        let count = side * side;
+        let mut sum = RGB::black();
+        let mut inverse_distances = 0.;
+        for i in 0..count {
+            let u = ((i / side) as f64 + rng.next_f64()) / side as f64;
+            let v = ((i % side) as f64 + rng.next_f64()) / side as f64;
+            let (sin_theta, cos_theta) = (u.sqrt(), (1. - u).sqrt());
+            let phi = 2. * PI * v;
+            let direction = tangent
+                .scale(sin_theta * phi.cos())
+                .add(&bitangent.scale(sin_theta * phi.sin()))
+                .add(&hit.normal.scale(cos_theta));
+
+            let ray = Ray::new(hit.point, direction);
+            Counters::bump(&self.stats.reflection_rays, 1);
+            let nearest = self.nearest(&ray);
+            if let Some((_, t)) = nearest {
+                inverse_distances += 1. / t;
+            }
+            let sampled = Sampled::default();
+            sum = sum.add(&self.shade(&ray, nearest, 2, throughput, sampled, rng));
+        }
+
+        let irradiance = sum.scale(1. / count as f64);
+        let radius = if inverse_distances > 0. {
+            count as f64 / inverse_distances
+        } else {
+            MAX_RADIUS
+        };
+        cache.insert(Record {
+            position: hit.point,
+            normal: hit.normal,
+            irradiance,
+            radius: radius.clamp(MIN_RADIUS, MAX_RADIUS),
+        });
+        Some(irradiance)
+    }
+
+    // Throws away every record, to be traced again with the scene as it
+    // stands.
+    pub(crate) fn forget_irradiance(&mut self) {
+        if let Some(cache) = &mut self.irradiance_cache {
+            cache.records = RefCell::default();
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Caches the light bounced onto diffuse surfaces seen by the camera, for
+    // the PathTracing integrator, tracing the given number of rays for each
+    // record. The accuracy, between 0 and 1, is how far records are reused:
+    // smaller values place them more densely, for smoother but slower
+    // results. Records last until the lights, spheres or surroundings change,
+    // so moving the camera reuses them. Zero samples turns caching off.
+    #[wasm_bindgen(js_name = setIrradianceCache)]
+    pub fn set_irradiance_cache(&mut self, samples: usize, accuracy: f64) {
+        self.irradiance_cache = if samples > 0 && accuracy > 0. {
+            Some(IrradianceCache {
+                samples,
+                accuracy: accuracy.min(1.),
+                records: RefCell::default(),
+            })
+        } else {
+            None
+        };
+        self.dirty = Some(Region::full());
+    }
+}
@@ -0,0 +1,177 @@
+use crate::region::Region;
+use crate::{Scene, Vec3};
+use wasm_bindgen::prelude::*;
+
+// Camera paths for fly-throughs. Keyframes pin down where the camera is, what
+// it looks at and how wide it sees at given times, and the camera is moved
+// between them for any time in between, one frame at a time.
+
+#[wasm_bindgen]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PathInterpolation {
+    // Straight from one keyframe to the next, changing direction abruptly at
+    // each of them.
+    Linear,
+    // Along a Catmull-Rom spline, which passes through every keyframe and
+    // curves smoothly around them.
+    CatmullRom,
+}
+
+#[derive(Copy, Clone)]
+struct Keyframe {
+    time: f64,
+    eye: Vec3,
+    target: Vec3,
+    // The horizontal field of view, in degrees.
+    fov: f64,
+}
+
+pub struct CameraPath {
+    // In order of time, no two at the same time.
+    keys: Vec<Keyframe>,
+    interpolation: PathInterpolation,
+}
+
+impl Default for CameraPath {
+    fn default() -> Self {
+        Self {
+            keys: vec![],
+            interpolation: PathInterpolation::CatmullRom,
+        }
+    }
+}
+
+impl CameraPath {
+    fn insert(&mut self, key: Keyframe) {
+        match self.keys.iter().position(|k| k.time >= key.time) {
+            Some(i) if self.keys[i].time == key.time => self.keys[i] = key,
+            Some(i) => self.keys.insert(i, key),
+            None => self.keys.push(key),
+        }
+    }
+
+    // Where the camera is at time `t`. Before the first keyframe and after
+    // the last, it holds still.
+    fn at(&self, t: f64) -> Option<Keyframe> {
+        let last = self.keys.len().checked_sub(1)?;
+        let i = self.keys.iter().rposition(|k| k.time <= t).unwrap_or(0);
+        if i == last || t <= self.keys[0].time {
+            return Some(self.keys[i]);
+        }
+
+        let (a, b) = (&self.keys[i], &self.keys[i + 1]);
+        let u = (t - a.time) / (b.time - a.time);
+        Some(match self.interpolation {
+            PathInterpolation::Linear => Keyframe {
+                time: t,
+                eye: lerp(&a.eye, &b.eye, u),
+                target: lerp(&a.target, &b.target, u),
+                fov: a.fov + (b.fov - a.fov) * u,
+            },
+            PathInterpolation::CatmullRom => {
+                // The ends of the path stand in for the keyframes beyond them.
+                let keys = [
+                    &self.keys[i.saturating_sub(1)],
+                    a,
+                    b,
+                    &self.keys[(i + 2).min(last)],
+                ];
+                let w = catmull_rom(u);
+                let blend = |f: fn(&Keyframe) -> Vec3| {
+                    (0..4).fold(Vec3::new(0., 0., 0.), |sum, j| {
+                        sum.add(&f(keys[j]).scale(w[j]))
+                    })
+                };
+                Keyframe {
+                    time: t,
+                    eye: blend(|k| k.eye),
+                    target: blend(|k| k.target),
+                    fov: (0..4).map(|j| keys[j].fov * w[j]).sum(),
+                }
+            }
+        })
+    }
+}
+
+fn lerp(a: &Vec3, b: &Vec3, u: f64) -> Vec3 {
+    a.add(&b.subtract(a).scale(u))
+}
+
+// The weights of the four points around a segment of a uniform Catmull-Rom
+// spline, for the point a fraction `u` of the way along it.
+fn catmull_rom(u: f64) -> [f64; 4] {
+    let (u2, u3) = (u * u, u * u * u);
+    [
+        0.5 * (-u3 + 2. * u2 - u),
+        0.5 * (3. * u3 - 5. * u2 + 2.),
+        0.5 * (-3. * u3 + 4. * u2 + u),
+        0.5 * (u3 - u2),
+    ]
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Adds a keyframe to the camera's path, at `time` in any unit, with the
+    // eye and the point it looks at given as [x, y, z] arrays, and the
+    // horizontal field of view in degrees. A keyframe already at that time
+    // is replaced.
+    #[wasm_bindgen(js_name = addKeyframe)]
+    pub fn add_keyframe(&mut self, time: f64, eye: &[f64], target: &[f64], fov: f64) {
+        if !time.is_finite() || eye.len() < 3 || target.len() < 3 {
+            return;
+        }
+
+        self.camera_path.insert(Keyframe {
+            time,
+            eye: Vec3::new(eye[0], eye[1], eye[2]),
+            target: Vec3::new(target[0], target[1], target[2]),
+            fov,
+        });
+    }
+
+    // Adds a keyframe at `time` where the camera is now, looking at its
+    // pivot, with its current field of view.
+    #[wasm_bindgen(js_name = recordKeyframe)]
+    pub fn record_keyframe(&mut self, time: f64) {
+        if !time.is_finite() {
+            return;
+        }
+
+        let fov = self.field_of_view();
+        self.camera_path.insert(Keyframe {
+            time,
+            eye: self.camera.eye,
+            target: self.camera.pivot,
+            fov,
+        });
+    }
+
+    #[wasm_bindgen(js_name = clearKeyframes)]
+    pub fn clear_keyframes(&mut self) {
+        self.camera_path.keys.clear();
+    }
+
+    // Sets how the camera moves between keyframes. The default is along a
+    // Catmull-Rom spline.
+    #[wasm_bindgen(js_name = setPathInterpolation)]
+    pub fn set_path_interpolation(&mut self, interpolation: PathInterpolation) {
+        self.camera_path.interpolation = interpolation;
+    }
+
+    // Moves the camera to where its path has it at time `t`. Without any
+    // keyframes, this does nothing.
+    #[wasm_bindgen(js_name = setAnimationTime)]
+    pub fn set_animation_time(&mut self, t: f64) {
+        if !t.is_finite() {
+            return;
+        }
+
+        if let Some(key) = self.camera_path.at(t) {
+            self.camera.eye = key.eye;
+            self.camera.look_at(&key.target);
+            self.set_field_of_view(key.fov);
+            self.follow_camera();
+            self.invalidate(Region::full());
+        }
+    }
+}
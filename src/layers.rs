@@ -0,0 +1,95 @@
+use crate::holdout::Footprint;
+use crate::rng::Rng;
+use crate::stats::Counters;
+use crate::{ColorSpace, Image, Scene, RGB};
+use wasm_bindgen::prelude::*;
+
+// A set of images of the same size, one for each render layer. Every sphere
+// belongs to one layer, and the background to the first. Each image holds
+// only what the camera sees of its own layer's spheres, and is transparent
+// everywhere else, so that the layers can be graded separately and then
+// stacked back up. Spheres on other layers still cast shadows onto a layer's
+// spheres, and show up in their reflections.
+#[wasm_bindgen]
+pub struct RenderLayers {
+    images: Vec<Image>,
+}
+
+#[wasm_bindgen]
+impl RenderLayers {
+    #[wasm_bindgen(constructor)]
+    pub fn new(count: usize, width: usize, height: usize) -> Self {
+        Self {
+            images: (0..count.max(1))
+                .map(|_| Image::new(width, height))
+                .collect(),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.images.len()
+    }
+
+    // The pixels of the given layer, laid out in the same way as an image's,
+    // or a null pointer for a layer past the last one.
+    pub fn pixels(&self, layer: usize) -> *const u8 {
+        self.images
+            .get(layer)
+            .map_or(std::ptr::null(), |img| img.pixels())
+    }
+
+    #[wasm_bindgen(js_name = setColorSpace)]
+    pub fn set_color_space(&mut self, color_space: ColorSpace) {
+        for img in &mut self.images {
+            img.set_color_space(color_space);
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Moves the sphere at `index` onto the given render layer. Spheres beyond
+    // the last layer of a `RenderLayers` are left out of all of its images.
+    #[wasm_bindgen(js_name = setSphereLayer)]
+    pub fn set_sphere_layer(&mut self, index: usize, layer: usize) {
+        if let Some(sphere) = self.spheres.get_mut(index) {
+            sphere.layer = layer;
+        }
+    }
+
+    // Renders every layer at once, tracing a single ray for each pixel and
+    // writing its color into the image of the layer that the ray hit.
+    #[wasm_bindgen(js_name = renderLayers)]
+    pub fn render_layers(&mut self, layers: &mut RenderLayers) {
+        let (width, height) = (layers.images[0].width, layers.images[0].height);
+        self.fit_film(width, height);
+        self.stats.reset();
+
+        let height_inv = 1. / height as f64;
+        let width_inv = 1. / width as f64;
+        let visible = self.visible_spheres();
+
+        for y in 0..height {
+            for x in 0..width {
+                let mut rng = Rng::for_pixel(x, y);
+                let pixel = Footprint::new(x, y, width_inv, height_inv);
+                let ray = self
+                    .camera
+                    .cast(x as f64 * width_inv, y as f64 * height_inv);
+                let nearest = self.nearest_visible(&visible, &ray);
+                Counters::bump(&self.stats.primary_rays, 1);
+
+                let layer = nearest.map_or(0, |(i, _)| self.spheres[i].layer);
+                let (color, alpha) = self.shade_pixel(&visible, pixel, &ray, nearest, &mut rng);
+
+                for (i, img) in layers.images.iter_mut().enumerate() {
+                    if i == layer {
+                        img.draw_alpha(x, y, &color, alpha);
+                    } else {
+                        img.draw_alpha(x, y, &RGB::black(), 0.);
+                    }
+                }
+            }
+        }
+    }
+}
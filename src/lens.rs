@@ -0,0 +1,75 @@
+use crate::region::Region;
+use crate::rng::Rng;
+use crate::{Camera, CameraModel, Ray, Scene, Vec3};
+use std::f64::consts::PI;
+use wasm_bindgen::prelude::*;
+
+// Depth of field, with a thin lens in place of the pinhole at the eye. Rays
+// leave from random points across the lens, all bent towards the point on the
+// plane of focus that the pinhole ray through the same spot on the film would
+// reach. Whatever lies on that plane stays sharp, and the further anything is
+// from it, the more the rays through a pixel spread out around it.
+#[derive(Copy, Clone)]
+pub struct Lens {
+    // The radius of the lens, in metres.
+    aperture: f64,
+    // The distance from the eye to the plane of focus, along the direction the
+    // camera faces, in metres.
+    focus: f64,
+}
+
+impl Camera {
+    // Like `cast`, but through a random point on the lens, if there is one.
+    // Without a lens, `rng` is left untouched.
+    pub(crate) fn cast_through_lens(&self, x: f64, y: f64, rng: &mut Rng) -> Ray {
+        let lens = match &self.lens {
+            Some(lens) if self.model == CameraModel::Perspective => lens,
+            _ => return self.cast(x, y),
+        };
+
+        let through = self.film.project(x, y);
+        let focus = through.scale(lens.focus / through.z);
+
+        let r = lens.aperture * rng.next_f64().sqrt();
+        let theta = 2. * PI * rng.next_f64();
+        let origin = Vec3::new(r * theta.cos(), r * theta.sin(), 0.);
+
+        Ray::new(
+            self.eye.add(&self.world_direction(&origin)),
+            self.world_direction(&focus.subtract(&origin)),
+        )
+    }
+
+    // How far a ray through the lens can stray from the pinhole ray through
+    // the same spot on the film, at the depths that a sphere of the given
+    // radius around `center` spans. At a depth z, the two are at most
+    // aperture × |1 - z / focus| apart, so a sphere grown by this much is hit
+    // by the pinhole rays of every pixel that its blur can reach.
+    pub(crate) fn defocus_margin(&self, center: &Vec3, radius: f64) -> f64 {
+        let lens = match &self.lens {
+            Some(lens) if self.model == CameraModel::Perspective => lens,
+            _ => return 0.,
+        };
+
+        let depth = self.local_direction(&center.subtract(&self.eye)).z;
+        let spread = |z: f64| (1. - z / lens.focus).abs();
+        lens.aperture * spread(depth - radius).max(spread(depth + radius))
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Blurs whatever lies away from the plane of focus, the given number of
+    // metres in front of the camera, through a lens of the given radius in
+    // metres. Wider apertures blur more. An aperture of zero goes back to a
+    // pinhole camera, with everything in focus.
+    #[wasm_bindgen(js_name = setDepthOfField)]
+    pub fn set_depth_of_field(&mut self, aperture: f64, focus: f64) {
+        self.camera.lens = if aperture > 0. && focus > 0. && aperture.is_finite() {
+            Some(Lens { aperture, focus })
+        } else {
+            None
+        };
+        self.invalidate(Region::full());
+    }
+}
@@ -1,10 +1,135 @@
 extern crate cfg_if;
+extern crate js_sys;
 extern crate wasm_bindgen;
 
+mod accel;
+mod ambient;
+mod approx;
+mod area;
+mod attenuation;
+mod background;
+mod bc1;
+mod bump;
+mod catcher;
+mod caustics;
+mod clusters;
+mod color;
+mod cubemap;
+mod curves;
+mod daytime;
+mod emitters;
+mod environment;
+mod exposure;
+mod glide;
+mod ground_truth;
+mod guides;
+mod heat;
+mod holdout;
+mod hud;
+mod integrator;
+mod irradiance;
+mod keyframes;
+mod layers;
+mod lens;
+mod library;
+mod linking;
+mod lint;
+mod lut;
+mod material;
+mod mattes;
+mod motion;
+mod noise;
+mod occlusion;
+mod orientation;
+mod packet;
+mod panorama;
+mod photons;
+mod png;
+mod portals;
+pub mod prelude;
+mod projection;
+mod raster;
+mod region;
+mod rng;
+mod shake;
+mod shutter;
+mod sky;
+mod spheres;
+mod spot;
+mod starfield;
+mod stats;
+mod stereo;
+mod still;
+mod streaming;
+mod sun;
+mod temperature;
+mod texture;
+mod tiles;
+mod transparency;
+mod units;
 mod utils;
-
+mod visibility;
+
+use accel::AccelSettings;
+pub use accel::Accelerator;
+use ambient::AmbientGradient;
+use area::Shape;
+pub use attenuation::Attenuation;
+use background::Background;
+use bump::Bump;
+use caustics::Caustics;
 use cfg_if::cfg_if;
-use std::{f64, u8};
+use clusters::LightClusters;
+pub use color::ColorSpace;
+pub use cubemap::CubeFace;
+use curves::Curve;
+pub use curves::FilmCurve;
+use daytime::TimeOfDay;
+use environment::Environment;
+use glide::Glide;
+use holdout::Footprint;
+use hud::Hud;
+pub use integrator::Integrator;
+use irradiance::IrradianceCache;
+use keyframes::CameraPath;
+pub use keyframes::PathInterpolation;
+pub use layers::RenderLayers;
+use lens::Lens;
+use linking::LightLinks;
+pub use linking::LinkMode;
+pub use lint::{Lint, LintKind};
+use lut::Lut3d;
+use material::{Dielectric, Emissive, Glossy, Hit, Lambertian, Material, Metal, Pbr, Translucency};
+pub use mattes::MatteKind;
+use motion::MotionReference;
+use occlusion::Occlusion;
+use packet::{RayPacket, PACKET_SIZE};
+pub use panorama::CameraModel;
+use photons::PhotonMap;
+use portals::Portal;
+use region::Region;
+use rng::Rng;
+use shake::CameraShake;
+use shutter::Shutter;
+use sky::PhysicalSky;
+use spheres::SphereSet;
+use spot::Beam;
+use stats::Counters;
+pub use stats::RenderStats;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::ops::Range;
+use std::rc::Rc;
+pub use still::Still;
+use streaming::Streaming;
+pub use streaming::StreamingChanges;
+use sun::DirectionalLight;
+use texture::{Checker, ImageTexture, Space, Texture};
+pub use texture::{Pattern, TextureEncoding};
+use tiles::tiles;
+pub use tiles::RenderOrder;
+use visibility::Visibility;
 use wasm_bindgen::prelude::*;
 
 cfg_if! {
@@ -27,84 +152,105 @@ impl Square for f64 {
     }
 }
 
-#[derive(Copy, Clone)]
-struct Vec3 {
-    x: f64,
-    y: f64,
-    z: f64,
+/// A point or direction in world space. The X axis points right, the Y axis
+/// up and the Z axis away from the viewer, into the screen.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Vec3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
 }
 
 impl Vec3 {
-    fn new(x: f64, y: f64, z: f64) -> Self {
+    pub fn new(x: f64, y: f64, z: f64) -> Self {
         Self { x, y, z }
     }
 
-    fn unit(&self) -> Vec3 {
+    /// Returns a vector of length 1 pointing the same way. The result is NaN
+    /// for the zero vector, which has no direction.
+    pub fn unit(&self) -> Vec3 {
         // It is faster to multiply than divide two numbers, so we turn the division
         // operations into multiplications by the inverse of the vector's length.
         let inv = 1. / self.length();
         Vec3::new(self.x * inv, self.y * inv, self.z * inv)
     }
 
-    fn length(&self) -> f64 {
-        (self.x.sqr() + self.y.sqr() + self.z.sqr()).sqrt()
+    pub fn length(&self) -> f64 {
+        self.length_sqr().sqrt()
     }
 
-    fn add(&self, other: &Vec3) -> Vec3 {
+    pub fn length_sqr(&self) -> f64 {
+        self.dot(self)
+    }
+
+    pub fn add(&self, other: &Vec3) -> Vec3 {
         Vec3::new(self.x + other.x, self.y + other.y, self.z + other.z)
     }
 
-    fn subtract(&self, other: &Vec3) -> Vec3 {
+    pub fn subtract(&self, other: &Vec3) -> Vec3 {
         Vec3::new(self.x - other.x, self.y - other.y, self.z - other.z)
     }
 
-    fn scale(&self, f: f64) -> Vec3 {
+    pub fn scale(&self, f: f64) -> Vec3 {
         Vec3::new(self.x * f, self.y * f, self.z * f)
     }
 
-    fn dot(&self, other: &Vec3) -> f64 {
+    pub fn dot(&self, other: &Vec3) -> f64 {
         self.x * other.x + self.y * other.y + self.z * other.z
     }
+
+    pub fn cross(&self, other: &Vec3) -> Vec3 {
+        Vec3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
 }
 
-#[derive(Copy, Clone)]
-struct RGB {
-    red: f64,
-    green: f64,
-    blue: f64,
+/// A color, or an amount of light, as linear sRGB components. Components are
+/// nominally between 0 and 1, but may exceed 1 for bright light; they are
+/// only clamped when written out to an image.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RGB {
+    pub red: f64,
+    pub green: f64,
+    pub blue: f64,
+}
+
+impl std::iter::Sum for RGB {
+    fn sum<I: Iterator<Item = RGB>>(iter: I) -> RGB {
+        iter.fold(RGB::black(), |acc, c| acc.add(&c))
+    }
 }
 
 impl RGB {
-    #[allow(dead_code)]
-    fn red() -> Self {
+    pub fn red() -> Self {
         Self::new(1., 0., 0.)
     }
 
-    #[allow(dead_code)]
-    fn green() -> Self {
+    pub fn green() -> Self {
         Self::new(0., 1., 0.)
     }
 
-    #[allow(dead_code)]
-    fn blue() -> Self {
+    pub fn blue() -> Self {
         Self::new(0., 0., 1.)
     }
 
-    #[allow(dead_code)]
-    fn black() -> Self {
+    pub fn black() -> Self {
         Self::new(0., 0., 0.)
     }
 
-    #[allow(dead_code)]
-    fn white() -> Self {
+    pub fn white() -> Self {
         Self::new(1., 1., 1.)
     }
 
-    fn new(red: f64, green: f64, blue: f64) -> Self {
+    pub fn new(red: f64, green: f64, blue: f64) -> Self {
         Self { red, green, blue }
     }
 
-    fn add(&self, other: &RGB) -> RGB {
+    pub fn add(&self, other: &RGB) -> RGB {
         RGB::new(
             self.red + other.red,
             self.green + other.green,
@@ -112,17 +258,85 @@ impl RGB {
         )
     }
 
-    fn shade(&self, f: f64) -> RGB {
+    pub fn subtract(&self, other: &RGB) -> RGB {
+        RGB::new(
+            self.red - other.red,
+            self.green - other.green,
+            self.blue - other.blue,
+        )
+    }
+
+    pub fn scale(&self, f: f64) -> RGB {
+        RGB::new(self.red * f, self.green * f, self.blue * f)
+    }
+
+    /// Multiplies each component by the matching one of `other`, as when light
+    /// of one color is reflected by a surface of another.
+    pub fn tint(&self, other: &RGB) -> RGB {
+        RGB::new(
+            self.red * other.red,
+            self.green * other.green,
+            self.blue * other.blue,
+        )
+    }
+
+    /// Scales the color by `f`, clamped between 0 and 1, as when a surface of
+    /// this color receives that fraction of full light.
+    pub fn shade(&self, f: f64) -> RGB {
         if f <= 0. {
             RGB::black()
         } else if f >= 1. {
-            self.clone()
+            *self
         } else {
             RGB::new(self.red * f, self.green * f, self.blue * f)
         }
     }
 
-    fn write(&self, pixels: &mut [u8]) {
+    /// Like `shade`, for light that isn't white: each component is scaled by
+    /// the matching one of `light`, clamped between 0 and 1.
+    pub fn shade_by(&self, light: &RGB) -> RGB {
+        let channel = |c: f64, f: f64| {
+            if f <= 0. {
+                0.
+            } else if f >= 1. {
+                c
+            } else {
+                c * f
+            }
+        };
+        RGB::new(
+            channel(self.red, light.red),
+            channel(self.green, light.green),
+            channel(self.blue, light.blue),
+        )
+    }
+
+    /// A shade of grey, or white light, with every component equal to `v`.
+    pub fn grey(v: f64) -> RGB {
+        RGB::new(v, v, v)
+    }
+
+    /// Whether no component is above zero, as for light that has been
+    /// blocked entirely.
+    pub fn is_black(&self) -> bool {
+        self.red <= 0. && self.green <= 0. && self.blue <= 0.
+    }
+
+    /// Raises any component below `floor` up to it.
+    pub fn max(&self, floor: f64) -> RGB {
+        RGB::new(
+            self.red.max(floor),
+            self.green.max(floor),
+            self.blue.max(floor),
+        )
+    }
+
+    /// The mean of the components.
+    pub fn average(&self) -> f64 {
+        (self.red + self.green + self.blue) / 3.
+    }
+
+    fn write(&self, pixels: &mut [u8], alpha: f64) {
         let max = u8::MAX as f64;
         let red = max * f64::min(self.red, 1.0);
         let green = max * f64::min(self.green, 1.0);
@@ -131,110 +345,344 @@ impl RGB {
         pixels[0] = red.round() as u8;
         pixels[1] = green.round() as u8;
         pixels[2] = blue.round() as u8;
-        pixels[3] = u8::MAX;
+        pixels[3] = (max * alpha.clamp(0., 1.)).round() as u8;
     }
 }
 
-struct Ray {
+/// A half-line starting at an origin, along a direction of unit length. The
+/// direction is normalised on construction, and can't be changed afterwards.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ray {
     origin: Vec3,
     direction: Vec3,
 }
 
 impl Ray {
-    fn cast(from: &Vec3, to: &Vec3) -> Self {
-        let direction = to.subtract(from);
-        Ray::new(from.clone(), direction)
+    /// Creates a ray pointing from `from` towards `to`, returning it alongside
+    /// the distance between the two points.
+    pub fn cast(from: &Vec3, to: &Vec3) -> (Self, f64) {
+        let delta = to.subtract(from);
+        let len_sqr = delta.length_sqr();
+
+        // This is where shadow rays get built, so it's worth approximating the
+        // normalisation when fast math is on. Rays that go on to find a hit
+        // point can't do the same: points computed along a slightly shorter or
+        // longer direction end up inside the surface, and then shadow themselves.
+        let inv = approx::inv_sqrt(len_sqr);
+        let len = len_sqr * inv;
+        let direction = delta.scale(inv);
+        (
+            Ray {
+                origin: *from,
+                direction,
+            },
+            len,
+        )
     }
 
-    fn new(origin: Vec3, direction: Vec3) -> Self {
-        Self { origin, direction }
+    /// Rays always carry a unit direction vector, so that intersection tests can
+    /// skip normalising it on every call. The direction is normalised here, once.
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Self {
+            origin,
+            direction: direction.unit(),
+        }
     }
 
-    fn length(&self) -> f64 {
-        self.direction.length()
+    pub fn origin(&self) -> Vec3 {
+        self.origin
     }
 
-    fn unit(&self) -> Ray {
-        Ray::new(self.origin, self.direction.unit())
+    pub fn direction(&self) -> Vec3 {
+        self.direction
     }
 
-    fn point_at(&self, t: f64) -> Vec3 {
+    /// Returns the point at distance `t` along the ray.
+    pub fn point_at(&self, t: f64) -> Vec3 {
         self.origin.add(&self.direction.scale(t))
     }
 
-    fn reflect(&self, point: &Vec3, normal: &Vec3) -> Ray {
-        let cosine = self.direction.dot(&normal);
+    /// Reflects the ray about the unit-length `normal`, starting from `point`.
+    pub fn reflect(&self, point: &Vec3, normal: &Vec3) -> Ray {
+        // Reflecting a unit vector about a unit normal yields another unit vector,
+        // so there is no need to go through `Ray::new`.
+        let cosine = self.direction.dot(normal);
         let reflection = self.direction.subtract(&normal.scale(2. * cosine));
-        Ray::new(point.clone(), reflection)
+        Ray {
+            origin: *point,
+            direction: reflection,
+        }
     }
 }
 
+// Intersections closer than this to a ray's origin are ignored, so that rays
+// leaving a surface don't immediately hit that same surface.
+const EPSILON: f64 = 1e-10;
+
 struct Sphere {
     center: Vec3,
     radius: f64,
-    color: RGB,
-    glossiness: f64,
+    // Materials from the library are shared between every sphere using them.
+    material: Rc<dyn Material>,
+    visibility: Visibility,
+    holdout: bool,
+    normal_map: Option<ImageTexture>,
+    bump: Option<Bump>,
+    layer: usize,
+    // The fraction of light that passes straight through the sphere.
+    transparency: f64,
+    // Whether the sphere is paged in for tracing. Only streaming pages
+    // spheres out.
+    resident: bool,
+    // How far the sphere moves over a frame, for motion blur.
+    velocity: Vec3,
 }
 
 impl Sphere {
-    fn new(center: Vec3, radius: f64, color: RGB, glossiness: f64) -> Self {
+    fn new<M: Material + 'static>(center: Vec3, radius: f64, material: M) -> Self {
         Self {
             center,
             radius,
-            color,
-            glossiness,
+            material: Rc::new(material),
+            visibility: Visibility::default(),
+            holdout: false,
+            normal_map: None,
+            bump: None,
+            layer: 0,
+            transparency: 0.,
+            resident: true,
+            velocity: Vec3::new(0., 0., 0.),
         }
     }
 
-    fn intersect(&self, ray: &Ray) -> Option<f64> {
-        let oc = ray.origin.subtract(&self.center);
-        let dot = ray.direction.unit().dot(&oc);
-        let sqrt_term = dot.sqr() - (oc.length().sqr() - self.radius.sqr());
+    /// Returns where the sphere's center is at the given time, as a fraction
+    /// of the frame.
+    fn center_at(&self, time: f64) -> Vec3 {
+        self.center.add(&self.velocity.scale(time))
+    }
+
+    /// Returns the highest point on the sphere.
+    fn top(&self) -> Vec3 {
+        self.center.add(&Vec3::new(0., self.radius, 0.))
+    }
 
-        if sqrt_term < 0. {
-            None
-        } else {
-            let sqrt = sqrt_term.sqrt();
-            vec![-dot - sqrt, -dot + sqrt]
-                .iter()
-                .cloned()
-                .find(|&t| t >= 1e-10)
+    /// Describes the surface at `point`, including its unit-length normal,
+    /// for the sphere's material, where the sphere is at `time`.
+    fn hit(&self, point: Vec3, time: f64, spread: f64) -> Hit {
+        let local = point.subtract(&self.center_at(time));
+        let normal = local.scale(1. / self.radius);
+        let (tangent, bitangent) = texture::tangent_frame(&normal);
+        let mut hit = Hit {
+            point,
+            normal,
+            local,
+            tangent,
+            bitangent,
+            spread,
+        };
+
+        if let Some(map) = &self.normal_map {
+            hit.normal = texture::perturb(&hit.normal, &map.value(&hit));
+        }
+        if let Some(bump) = &self.bump {
+            hit.normal = bump.perturb(&hit);
+        }
+        if self.normal_map.is_some() || self.bump.is_some() {
+            let (tangent, bitangent) = texture::tangent_frame(&hit.normal);
+            hit.tangent = tangent;
+            hit.bitangent = bitangent;
         }
+        hit
     }
 
-    fn surface_normal(&self, point: &Vec3) -> Vec3 {
-        point.subtract(&self.center)
+    /// Describes the surface where `ray` hits it, `t` along, as `hit` does.
+    /// Rays that start inside an opaque sphere, such as from a camera flown
+    /// into it, see its inner surface, so the normal is turned around to
+    /// face them and the interior is lit and shaded like any other surface.
+    /// Spheres that let light through keep the outward normal, which tells
+    /// them whether a ray is entering or leaving.
+    fn hit_along(&self, ray: &Ray, t: f64, time: f64, spread: f64) -> Hit {
+        let mut hit = self.hit(ray.point_at(t), time, spread);
+        if self.material.transmission().is_none() && ray.direction.dot(&hit.normal) > 0. {
+            hit.normal = hit.normal.scale(-1.);
+            hit.tangent = hit.tangent.scale(-1.);
+        }
+        hit
     }
 }
 
+#[derive(Copy, Clone)]
 struct Light {
     pos: Vec3,
+    // The luminous flux, in lumens.
     power: f64,
+    // The color of the light, with a luminance of 1.
+    color: RGB,
+    beam: Beam,
+    shape: Shape,
+    // The number of shadow rays cast towards the light, if it has an area.
+    samples: usize,
+    attenuation: Attenuation,
+    // The distance beyond which the light reaches nothing.
+    range: f64,
+    // The position of the light's links in the scene's list of them, if it
+    // has any.
+    link: Option<usize>,
 }
 
 impl Light {
     fn new(pos: Vec3, power: f64) -> Self {
-        Self { pos, power }
+        Self {
+            pos,
+            power,
+            color: RGB::white(),
+            beam: Beam::Omni,
+            shape: Shape::Point,
+            samples: area::DEFAULT_SAMPLES,
+            attenuation: Attenuation::InverseSquare,
+            range: f64::INFINITY,
+            link: None,
+        }
     }
 
-    fn illuminate(&self, spheres: &[Sphere], point: &Vec3, surface_normal: &Vec3) -> f64 {
-        let ray = Ray::cast(point, &self.pos);
-        let len = ray.length();
-        let unit_ray = ray.unit();
+    // The fraction of the light's strength that reaches a point in the
+    // direction of `ray`, which points from the point towards the light.
+    fn falloff(&self, ray: &Ray) -> f64 {
+        self.beam.falloff(&ray.direction.scale(-1.))
+    }
 
-        for sphere in spheres {
-            if let Some(t) = sphere.intersect(&unit_ray) {
-                if t < len {
-                    return 0.;
-                }
-            }
+    // The illuminance in lux that `point` would receive from the light, if
+    // nothing stood in the way.
+    fn unshadowed(&self, point: &Vec3, surface_normal: &Vec3) -> f64 {
+        let unshadowed = |light: &Light| {
+            (
+                Vec3::new(0., 0., 0.),
+                light.unshadowed_point(point, surface_normal),
+            )
+        };
+        self.over_area(point, unshadowed).1
+    }
+
+    fn unshadowed_point(&self, point: &Vec3, surface_normal: &Vec3) -> f64 {
+        let (ray, len) = Ray::cast(point, &self.pos);
+        let cosine = surface_normal.dot(&ray.direction).max(0.) * self.falloff(&ray);
+        self.illuminance(cosine, len)
+    }
+
+    // Returns the direction from `point` towards the light, and the
+    // illuminance in lux that it receives from it.
+    fn illuminate(
+        &self,
+        spheres: &SphereSet,
+        point: &Vec3,
+        surface_normal: &Vec3,
+        stats: &Counters,
+    ) -> (Vec3, f64) {
+        self.over_area(point, |light| {
+            light.illuminate_point(spheres, point, surface_normal, stats)
+        })
+    }
+
+    fn illuminate_point(
+        &self,
+        spheres: &SphereSet,
+        point: &Vec3,
+        surface_normal: &Vec3,
+        stats: &Counters,
+    ) -> (Vec3, f64) {
+        let (ray, len) = Ray::cast(point, &self.pos);
+
+        // Surfaces facing away from the light can't be lit by it. Checking for
+        // this up front saves a shadow ray, and spheres that cast no shadows
+        // can't be relied on to shadow their own far side.
+        // The same goes for points outside a spot light's cone.
+        let cosine = surface_normal.dot(&ray.direction);
+        if cosine <= 0. {
+            return (ray.direction, 0.);
+        }
+
+        let falloff = self.falloff(&ray);
+        if falloff <= 0. {
+            return (ray.direction, 0.);
+        }
+
+        Counters::bump(&stats.shadow_rays, 1);
+        if spheres.occluded(&ray, len, stats) {
+            return (ray.direction, 0.);
+        }
+
+        let cosine = cosine * falloff;
+        (ray.direction, self.illuminance(cosine, len))
+    }
+
+    // Like `illuminate`, for a point on a translucent sphere.
+    fn illuminate_translucent(
+        &self,
+        spheres: &SphereSet,
+        hit: &Hit,
+        translucency: Translucency,
+        stats: &Counters,
+    ) -> (Vec3, f64) {
+        self.over_area(&hit.point, |light| {
+            light.illuminate_translucent_point(spheres, hit, translucency, stats)
+        })
+    }
+
+    fn illuminate_translucent_point(
+        &self,
+        spheres: &SphereSet,
+        hit: &Hit,
+        translucency: Translucency,
+        stats: &Counters,
+    ) -> (Vec3, f64) {
+        let (ray, len) = Ray::cast(&hit.point, &self.pos);
+        let falloff = self.falloff(&ray);
+        if falloff <= 0. {
+            return (ray.direction, 0.);
         }
 
-        let cosine = surface_normal.dot(&unit_ray.direction) / surface_normal.length();
-        (self.power * cosine) / (4. * f64::consts::PI * len.sqr())
+        let strength = translucent_strength(spheres, hit, &ray, len, translucency, stats) * falloff;
+        (ray.direction, self.illuminance(strength, len))
     }
 }
 
+// The fraction of a light's strength that reaches a point on a translucent
+// sphere, from a light `len` away along `ray`. Wrap lighting replaces the
+// cosine, and light on the far side of the sphere shines through it, so the
+// shadow ray starts where the ray towards the light leaves the sphere, rather
+// than at the point itself.
+fn translucent_strength(
+    spheres: &SphereSet,
+    hit: &Hit,
+    ray: &Ray,
+    len: f64,
+    translucency: Translucency,
+    stats: &Counters,
+) -> f64 {
+    let cosine = hit.normal.dot(&ray.direction);
+    let wrap = translucency.wrap;
+    let wrapped = ((cosine + wrap) / (1. + wrap)).max(0.);
+
+    // The chord the ray cuts through the sphere, which is empty wherever the
+    // light is in front of the surface.
+    let thickness = (-2. * hit.local.dot(&ray.direction)).max(0.);
+    let through = (-cosine).max(0.) * (-thickness / translucency.depth).exp();
+
+    let strength = wrapped + through;
+    if strength <= 0. || thickness >= len {
+        return 0.;
+    }
+
+    let exit = Ray::new(ray.point_at(thickness), ray.direction);
+    Counters::bump(&stats.shadow_rays, 1);
+    if spheres.occluded(&exit, len - thickness, stats) {
+        return 0.;
+    }
+
+    strength
+}
+
+#[derive(Copy, Clone)]
 struct Film {
     origin: Vec3,
     width: f64,
@@ -268,77 +716,353 @@ enum Move {
     Back,
 }
 
+// How far in front of the default camera its pivot lies, about the middle of
+// the default scene.
+const DEFAULT_PIVOT_DISTANCE: f64 = 16.;
+
+#[derive(Copy, Clone)]
 struct Camera {
     eye: Vec3,
+    // The directions in which the film runs across and up, and in which the
+    // camera faces: unit vectors at right angles to each other.
+    right: Vec3,
+    up: Vec3,
+    forward: Vec3,
+    // The point that the camera orbits around and dollies towards.
+    pivot: Vec3,
+    // How far the camera moves with each step, in metres.
+    step: f64,
+    // How long each step takes, in seconds, or zero to jump straight there.
+    smoothing: f64,
+    // The glide that the camera is partway through, if any.
+    glide: Option<Glide>,
+    // How fast the camera is moving, in metres per second, to the right, up
+    // and forward.
+    velocity: Vec3,
+    model: CameraModel,
+    // A thin lens for depth of field, or `None` for a pinhole camera.
+    lens: Option<Lens>,
+    // The film, placed relative to the eye in the camera's own frame of
+    // reference, with X to the right, Y up and Z forward.
     film: Film,
 }
 
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new(
+            Vec3::new(0., 0., -6.),
+            Film::new(Vec3::new(-4., -3., 6.), 8., 4.5),
+        )
+    }
+}
+
 impl Camera {
     fn new(eye: Vec3, film: Film) -> Self {
-        Self { eye, film }
+        Self {
+            eye,
+            right: Vec3::new(1., 0., 0.),
+            up: Vec3::new(0., 1., 0.),
+            forward: Vec3::new(0., 0., 1.),
+            pivot: eye.add(&Vec3::new(0., 0., DEFAULT_PIVOT_DISTANCE)),
+            step: 1.,
+            smoothing: 0.,
+            glide: None,
+            velocity: Vec3::new(0., 0., 0.),
+            model: CameraModel::Perspective,
+            lens: None,
+            film,
+        }
     }
 
     fn cast(&self, x: f64, y: f64) -> Ray {
-        let origin = self.eye;
-        let direction = self.film.project(x, y).subtract(&origin).unit();
-        Ray::new(origin, direction)
+        let direction = self.world_direction(&self.direction_through(x, y));
+        Ray::new(self.eye, direction)
     }
 
-    fn move_one(&mut self, mov: Move) {
-        match mov {
-            Move::Left => {
-                self.eye.x -= 1.;
-                self.film.origin.x -= 1.;
-            }
-            Move::Right => {
-                self.eye.x += 1.;
-                self.film.origin.x += 1.;
-            }
-            Move::Up => {
-                self.eye.y += 1.;
-                self.film.origin.y += 1.;
-            }
-            Move::Down => {
-                self.eye.y -= 1.;
-                self.film.origin.y -= 1.;
-            }
-            Move::Forward => {
-                self.eye.z += 1.;
-                self.film.origin.z += 1.;
+    // Turns a vector in the camera's frame of reference into world space.
+    fn world_direction(&self, v: &Vec3) -> Vec3 {
+        self.right
+            .scale(v.x)
+            .add(&self.up.scale(v.y))
+            .add(&self.forward.scale(v.z))
+    }
+
+    // Turns a vector in world space into the camera's frame of reference.
+    fn local_direction(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(v.dot(&self.right), v.dot(&self.up), v.dot(&self.forward))
+    }
+
+    // How wide a pixel that covers `du` of the film's width is, at a distance
+    // of one world unit from the eye. This holds at the center of the frame,
+    // and slightly overestimates it towards the edges.
+    fn spread(&self, du: f64) -> f64 {
+        match self.model {
+            CameraModel::Perspective => self.film.width * du / self.film.origin.z,
+            CameraModel::Fisheye => PI * du * self.fisheye_extent().0,
+            CameraModel::Equirectangular => 2. * PI * du,
+        }
+    }
+
+    // Maps a point in world space to the film coordinates it appears at, or
+    // returns `None` if the point lies behind the film.
+    fn project(&self, point: &Vec3) -> Option<(f64, f64)> {
+        self.project_local(&self.local_direction(&point.subtract(&self.eye)))
+    }
+
+    // Like `project`, for a point in the camera's frame of reference.
+    fn project_local(&self, local: &Vec3) -> Option<(f64, f64)> {
+        let film_depth = self.film.origin.z;
+        if self.model != CameraModel::Perspective || local.z < film_depth {
+            return None;
+        }
+
+        let s = film_depth / local.z;
+        let x = s * local.x;
+        let y = s * local.y;
+
+        Some((
+            (x - self.film.origin.x) / self.film.width,
+            (self.film.origin.y + self.film.height - y) / self.film.height,
+        ))
+    }
+
+    // Projects the segment between two points in world space onto the film,
+    // clipping away any part of it that lies behind the film.
+    fn project_segment(&self, a: &Vec3, b: &Vec3) -> Option<((f64, f64), (f64, f64))> {
+        let film_z = self.film.origin.z;
+        let a = self.local_direction(&a.subtract(&self.eye));
+        let b = self.local_direction(&b.subtract(&self.eye));
+
+        let (a, b) = match (a.z >= film_z, b.z >= film_z) {
+            (true, true) => (a, b),
+            (false, false) => return None,
+            (true, false) | (false, true) => {
+                let t = (film_z - a.z) / (b.z - a.z);
+                let cut = a.add(&b.subtract(&a).scale(t));
+                if a.z >= film_z {
+                    (a, cut)
+                } else {
+                    (cut, b)
+                }
             }
-            Move::Back => {
-                self.eye.z -= 1.;
-                self.film.origin.z -= 1.;
+        };
+
+        Some((self.project_local(&a)?, self.project_local(&b)?))
+    }
+
+    // Returns the region of the frame covered by the given sphere, computed by
+    // projecting the corners of its bounding box. Spheres reaching behind the
+    // film are conservatively assumed to cover the entire frame, and spheres
+    // out of focus are grown to cover their blur. `travel` is how far the eye
+    // moves while the shutter is open.
+    fn bounds(&self, sphere: &Sphere, travel: &Vec3) -> Region {
+        let mut corners = Vec::with_capacity(32);
+
+        // Moving spheres cover the whole of their path. A moving eye sees
+        // them slide the opposite way along its own.
+        let path = [sphere.center, sphere.center_at(1.)];
+        for center in path.iter().flat_map(|c| [*c, c.subtract(travel)]) {
+            let r = sphere.radius + self.defocus_margin(&center, sphere.radius);
+
+            for &dx in &[-r, r] {
+                for &dy in &[-r, r] {
+                    for &dz in &[-r, r] {
+                        let corner = center.add(&Vec3::new(dx, dy, dz));
+                        match self.project(&corner) {
+                            Some(p) => corners.push(p),
+                            None => return Region::full(),
+                        }
+                    }
+                }
             }
         }
+
+        Region::around(corners)
+    }
+
+    fn translate(&mut self, offset: &Vec3) {
+        self.eye = self.eye.add(offset);
+        self.pivot = self.pivot.add(offset);
+    }
+
+    // Scales the film about its center, widening the field of view by the
+    // given factor, or narrowing it for factors below 1.
+    fn widen(&mut self, factor: f64) {
+        self.resize(self.film.width * factor, self.film.height * factor);
+    }
+
+    // Resizes the film about its center.
+    fn resize(&mut self, width: f64, height: f64) {
+        self.film.origin.x -= (width - self.film.width) / 2.;
+        self.film.origin.y -= (height - self.film.height) / 2.;
+        self.film.width = width;
+        self.film.height = height;
+    }
+
+    fn move_one(&mut self, mov: Move) {
+        // Steps across and ahead follow the way the camera faces, while
+        // steps up and down stay vertical.
+        let direction = match mov {
+            Move::Left => self.right.scale(-1.),
+            Move::Right => self.right,
+            Move::Up => Vec3::new(0., 1., 0.),
+            Move::Down => Vec3::new(0., -1., 0.),
+            Move::Forward => self.forward,
+            Move::Back => self.forward.scale(-1.),
+        };
+        let offset = direction.scale(self.step);
+        if self.smoothing > 0. {
+            self.glide_by(&offset, self.smoothing);
+        } else {
+            self.translate(&offset);
+        }
     }
 }
 
+// Tracing only ever borrows the scene immutably. Whatever the tracer needs to
+// keep track of as it renders a frame, such as counters, the time a pixel is
+// traced at, or caches that are filled in as they are used, is therefore kept
+// in cells.
 #[wasm_bindgen]
 pub struct Scene {
     camera: Camera,
     spheres: Vec<Sphere>,
+    // The spheres that secondary rays can hit, and those that cast shadows.
+    geometry: SphereSet,
+    occluders: SphereSet,
+    // The spheres that let some light through to their shadows.
+    ghosts: SphereSet,
+    // The positions of the glowing spheres that secondary rays can hit.
+    emitters: Vec<usize>,
+    portals: Vec<Portal>,
+    // Whether any of the spheres is a holdout.
+    holdouts: bool,
     lights: Vec<Light>,
+    light_clusters: LightClusters,
+    light_links: Vec<LightLinks>,
+    suns: Vec<DirectionalLight>,
+    dirty: Option<Region>,
+    packet_tracing: bool,
+    texture_compression: bool,
+    render_order: RenderOrder,
+    integrator: Integrator,
+    field: usize,
+    stale_field: bool,
+    stats: Counters,
+    ground_grid: Option<f64>,
+    white_point: f64,
+    ambient: Option<AmbientGradient>,
+    axis_gizmo: bool,
+    motion_reference: Option<MotionReference>,
+    photon_map: Option<PhotonMap>,
+    caustics: Option<Caustics>,
+    irradiance_cache: Option<IrradianceCache>,
+    // How wide the pixel being shaded is at a distance of one world unit from
+    // the eye.
+    pixel_spread: Cell<f64>,
+    // Named materials, which any number of spheres can share.
+    materials: HashMap<String, Rc<dyn Material>>,
+    shake: CameraShake,
+    shutter: Shutter,
+    camera_path: CameraPath,
+    accel: AccelSettings,
+    // The spheres visible in the frame being rendered, or the last one, found
+    // once at the start of the frame. The next frame reuses their index where
+    // it can.
+    visible: SphereSet,
+    // Whether the spheres or the camera have changed since `visible` was
+    // found, so that a frame rendered over several calls picks them up.
+    stale_visible: bool,
+    streaming: Option<Streaming>,
+    occlusion: Option<Occlusion>,
+    hud: Option<Hud>,
+    environment: Option<Environment>,
+    sky: Option<PhysicalSky>,
+    backdrop: Background,
+    time_of_day: Option<TimeOfDay>,
 }
 
+// When an object moves, its reflections and shadows may land outside its own
+// screen-space bounds. Rather than tracing every secondary ray back to the
+// object, dirty regions are padded by this fraction of the frame.
+const REFLECTION_MARGIN: f64 = 0.1;
+
+// Previews trace one ray for every PREVIEW_SCALE × PREVIEW_SCALE block of
+// pixels, i.e. a quarter of the rays of a full-resolution render.
+const PREVIEW_SCALE: usize = 2;
+
+// Reflections are always traced up to this depth, after which they are subject
+// to Russian roulette. Even paths that lose no energy at all get terminated
+// with probability 1 - MAX_SURVIVAL per bounce, e.g. between parallel mirrors.
+const ROULETTE_DEPTH: u8 = 3;
+const MAX_SURVIVAL: f64 = 0.95;
+
 #[wasm_bindgen]
 impl Scene {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Self {
-        let camera = Camera::new(
-            Vec3::new(0., 0., -6.),
-            Film::new(Vec3::new(-4., -3., 0.), 8., 4.5),
-        );
+        utils::set_panic_hook();
+
+        let camera = Camera::default();
 
         let spheres = vec![
-            Sphere::new(Vec3::new(-1., 4., 15.), 2., RGB::red(), 1.),
-            Sphere::new(Vec3::new(2., 2., 20.), 5., RGB::green(), 1.),
-            Sphere::new(Vec3::new(10., -1., 25.), 3., RGB::new(0.5, 0., 0.5), 0.7),
-            Sphere::new(Vec3::new(12., 4., 24.), 2., RGB::new(1., 1., 0.), 0.5),
-            Sphere::new(Vec3::new(-5., -2., 12.), 3., RGB::blue(), 0.7),
-            Sphere::new(Vec3::new(-1., -1., 11.), 1., RGB::new(1., 0.5, 0.7), 0.2),
-            Sphere::new(Vec3::new(-11., 6., 12.), 4., RGB::white(), 1.),
-            Sphere::new(Vec3::new(6., -9., 12.), 5., RGB::black(), 1.),
+            Sphere::new(
+                Vec3::new(-1., 4., 15.),
+                2.,
+                Glossy::new(RGB::red(), RGB::white()),
+            ),
+            Sphere::new(
+                Vec3::new(2., 2., 20.),
+                5.,
+                Glossy::new(RGB::green(), RGB::white()),
+            ),
+            Sphere::new(
+                Vec3::new(10., -1., 25.),
+                3.,
+                Glossy::new(RGB::new(0.5, 0., 0.5), RGB::new(0.7, 0.7, 0.7)),
+            ),
+            Sphere::new(
+                Vec3::new(12., 4., 24.),
+                2.,
+                Pbr::new(RGB::new(1., 0.78, 0.34), 1., 0.2),
+            ),
+            Sphere::new(
+                Vec3::new(-5., -2., 12.),
+                3.,
+                Glossy::new(RGB::blue(), RGB::new(0.7, 0.7, 0.7)),
+            ),
+            Sphere::new(
+                Vec3::new(-1., -1., 11.),
+                1.,
+                Lambertian::new(Checker::new(
+                    RGB::new(1., 0.5, 0.7),
+                    RGB::white(),
+                    0.4,
+                    Space::Object,
+                )),
+            ),
+            Sphere::new(
+                Vec3::new(-11., 6., 12.),
+                4.,
+                Glossy::new(RGB::white(), RGB::white()),
+            ),
+            Sphere::new(Vec3::new(6., -9., 12.), 5., Metal::new(RGB::black(), 0.15)),
+            Sphere::new(Vec3::new(2.5, -1.5, 8.), 1.5, Dielectric::new(1.5)),
+            Sphere::new(
+                Vec3::new(0., -1014., 0.),
+                1000.,
+                Lambertian::new(Checker::new(
+                    RGB::new(0.8, 0.8, 0.8),
+                    RGB::new(0.3, 0.3, 0.3),
+                    2.,
+                    Space::World,
+                )),
+            ),
+            Sphere::new(
+                Vec3::new(-1.8, -2.2, 10.2),
+                0.5,
+                Emissive::new(RGB::new(1., 0.6, 0.2), 0.64),
+            ),
         ];
 
         let lights = vec![
@@ -347,104 +1071,719 @@ impl Scene {
             Light::new(Vec3::new(-5., 8., 30.), 2500.),
         ];
 
-        Self {
-            camera,
-            spheres,
-            lights,
+        Self::with_world(camera, spheres, lights)
+    }
+
+    // Creates a scene with nothing in it, seen through the default camera,
+    // for hosts to fill with spheres and lights of their own.
+    pub fn empty() -> Self {
+        utils::set_panic_hook();
+        Self::with_world(Camera::default(), vec![], vec![])
+    }
+
+    // Creates a scene with nothing in it, seen from the given eye looking at
+    // the given target, with the given horizontal field of view in degrees.
+    #[wasm_bindgen(js_name = withCamera)]
+    pub fn with_camera(ex: f64, ey: f64, ez: f64, tx: f64, ty: f64, tz: f64, fov: f64) -> Self {
+        let mut scene = Self::empty();
+        scene.camera.eye = Vec3::new(ex, ey, ez);
+        scene.camera.look_at(&Vec3::new(tx, ty, tz));
+        scene.set_field_of_view(fov);
+        scene
+    }
+
+    // Traces primary rays in packets of four neighbouring pixels, rather than
+    // one at a time. Secondary rays are always traced individually.
+    #[wasm_bindgen(js_name = setPacketTracing)]
+    pub fn set_packet_tracing(&mut self, enabled: bool) {
+        self.packet_tracing = enabled;
+    }
+
+    // Sets the order in which `renderBudgeted` works through the frame's tiles.
+    // This takes effect from the next frame, not in the middle of one.
+    #[wasm_bindgen(js_name = setRenderOrder)]
+    pub fn set_render_order(&mut self, order: RenderOrder) {
+        self.render_order = order;
+    }
+
+    // Sets the integrator used to render subsequent frames.
+    #[wasm_bindgen(js_name = setIntegrator)]
+    pub fn set_integrator(&mut self, integrator: Integrator) {
+        self.integrator = integrator;
+        self.dirty = Some(Region::full());
+    }
+
+    // Returns statistics about the work done to render the most recent frame.
+    pub fn stats(&self) -> RenderStats {
+        self.stats.snapshot()
+    }
+
+    pub fn render(&mut self, img: &mut Image) {
+        self.fit_film(img.width, img.height);
+        self.stats.reset();
+        if self.integrator == Integrator::PhotonMapping {
+            self.render_photon_pass(img);
+        } else {
+            self.render_region(img, &Region::full(), 1);
         }
+        self.draw_guides(img);
+        self.dirty = None;
+        self.stale_field = false;
     }
 
-    pub fn render(&self, img: &mut Image) {
+    // Renders half of the pixels in the frame, in a checkerboard pattern, and
+    // alternates between the two halves (or fields) from one call to the next.
+    // If the scene changed since the last call, the pixels of the other field
+    // are interpolated from their neighbours; otherwise, they still hold the
+    // previous frame, so two calls in a row produce a complete, exact image.
+    #[wasm_bindgen(js_name = renderInterlaced)]
+    pub fn render_interlaced(&mut self, img: &mut Image) {
+        self.fit_film(img.width, img.height);
+        let changed = self.dirty.is_some();
+        if !changed && !self.stale_field {
+            return;
+        }
+
+        self.stats.reset();
+
         let height_inv = 1. / img.height as f64;
         let width_inv = 1. / img.width as f64;
+        let visible = self.visible_spheres();
 
         for y in 0..img.height {
             let y_offset = y as f64 * height_inv;
 
-            for x in 0..img.width {
-                let x_offset = x as f64 * width_inv;
-                let ray = self.camera.cast(x_offset, y_offset);
+            for x in ((y + self.field) % 2..img.width).step_by(2) {
+                let mut rng = Rng::for_pixel(x, y);
+                let ray = self.primary_ray(&visible, x as f64 * width_inv, y_offset, &mut rng);
+                let nearest = self.nearest_visible(&visible, &ray);
+                Counters::bump(&self.stats.primary_rays, 1);
+                let pixel = Footprint::new(x, y, width_inv, height_inv);
+                let (color, alpha) = self.shade_pixel(&visible, pixel, &ray, nearest, &mut rng);
+                img.draw_alpha(x, y, &color, alpha);
+            }
+        }
+
+        if changed {
+            for y in 0..img.height {
+                for x in ((y + self.field + 1) % 2..img.width).step_by(2) {
+                    img.interpolate(x, y);
+                }
+            }
+        }
+
+        self.draw_guides(img);
+        self.field ^= 1;
+        self.dirty = None;
+        self.stale_field = changed;
+    }
+
+    // Renders as many tiles as fit within the given time budget, starting from
+    // where the previous call left off, and returns a cursor to resume from.
+    // Passing no cursor starts a new frame. This keeps the main thread
+    // responsive while rendering frames that take longer than a few ms.
+    #[wasm_bindgen(js_name = renderBudgeted)]
+    pub fn render_budgeted(
+        &mut self,
+        img: &mut Image,
+        budget_ms: f64,
+        cursor: Option<RenderCursor>,
+    ) -> RenderCursor {
+        let deadline = utils::now() + budget_ms;
+        let order = cursor.as_ref().map_or(self.render_order, |c| c.order);
+        let tiles = tiles(img.width, img.height, order);
+        let mut next = cursor.map_or(0, |c| c.next);
+
+        if next == 0 {
+            self.fit_film(img.width, img.height);
+            self.stats.reset();
+        }
+        if next == 0 || self.stale_visible {
+            self.update_visible();
+        }
+
+        // Always render at least one tile, so that every call makes progress.
+        while next < tiles.len() {
+            let tile = &tiles[next];
+            self.render_pixels(img, tile.xs.clone(), tile.ys.clone(), 1);
+            next += 1;
 
-                let color = self.light(&ray, 1);
-                img.draw(x, y, &color);
+            if utils::now() >= deadline {
+                break;
             }
         }
+
+        if next == tiles.len() {
+            self.draw_guides(img);
+            self.dirty = None;
+        }
+
+        RenderCursor {
+            next,
+            total: tiles.len(),
+            order,
+        }
+    }
+
+    // Quickly renders the frame at a quarter of the resolution, upsampled to
+    // fill the whole image. This is meant for use while the camera is moving:
+    // the entire frame is left dirty so that a later call to `renderDirty`
+    // refines it at full resolution once the input stops.
+    #[wasm_bindgen(js_name = renderPreview)]
+    pub fn render_preview(&mut self, img: &mut Image) {
+        self.fit_film(img.width, img.height);
+        self.stats.reset();
+        self.render_region(img, &Region::full(), PREVIEW_SCALE);
+        self.draw_guides(img);
+        self.dirty = Some(Region::full());
+    }
+
+    // Re-renders only the parts of the frame that have changed since the last
+    // render, assuming `img` still holds the result of that render.
+    #[wasm_bindgen(js_name = renderDirty)]
+    pub fn render_dirty(&mut self, img: &mut Image) {
+        self.fit_film(img.width, img.height);
+        if let Some(region) = self.dirty.take() {
+            self.stats.reset();
+            self.render_region(img, &region, 1);
+            self.draw_guides(img);
+        }
+    }
+
+    // Adds a sphere with a plain grey matte surface, and returns its index.
+    // Give it a material from the library with `setSphereMaterial`.
+    #[wasm_bindgen(js_name = addSphere)]
+    pub fn add_sphere(&mut self, x: f64, y: f64, z: f64, radius: f64) -> usize {
+        let grey = Lambertian::new(RGB::new(0.5, 0.5, 0.5));
+        self.spheres
+            .push(Sphere::new(Vec3::new(x, y, z), radius, grey));
+        self.rebuild_geometry();
+
+        let index = self.spheres.len() - 1;
+        let region = self.sphere_bounds(&self.spheres[index]);
+        self.invalidate(region.expand(REFLECTION_MARGIN));
+        index
+    }
+
+    #[wasm_bindgen(js_name = setSphereCenter)]
+    pub fn set_sphere_center(&mut self, index: usize, x: f64, y: f64, z: f64) {
+        let before = match self.spheres.get(index) {
+            Some(sphere) => self.sphere_bounds(sphere),
+            None => return,
+        };
+        self.spheres[index].center = Vec3::new(x, y, z);
+        self.rebuild_geometry();
+        let after = self.sphere_bounds(&self.spheres[index]);
+
+        self.invalidate(before.union(&after).expand(REFLECTION_MARGIN));
+    }
+
+    #[wasm_bindgen(js_name = setLightPosition)]
+    pub fn set_light_position(&mut self, index: usize, x: f64, y: f64, z: f64) {
+        match self.lights.get_mut(index) {
+            Some(light) => light.pos = Vec3::new(x, y, z),
+            None => return,
+        }
+        self.light_clusters = LightClusters::new(&self.lights);
+        self.invalidate_lighting();
+    }
+
+    // Adds a point light with the given luminous flux, in lumens, and returns its
+    // index. Lights far from the point being shaded are clustered together,
+    // so scenes can hold many of them without multiplying the shadow rays.
+    #[wasm_bindgen(js_name = addLight)]
+    pub fn add_light(&mut self, x: f64, y: f64, z: f64, power: f64) -> usize {
+        self.lights.push(Light::new(Vec3::new(x, y, z), power));
+        self.light_clusters = LightClusters::new(&self.lights);
+        self.invalidate_lighting();
+        self.lights.len() - 1
+    }
+
+    // Sets how far each of the move methods takes the camera, in metres. The
+    // default is one metre. Steps that aren't positive are ignored.
+    #[wasm_bindgen(js_name = setMoveStep)]
+    pub fn set_move_step(&mut self, step: f64) {
+        if step > 0. && step.is_finite() {
+            self.camera.step = step;
+        }
     }
 
     #[wasm_bindgen(js_name = moveLeft)]
     pub fn move_left(&mut self) {
-        self.camera.move_one(Move::Left);
+        self.move_camera(Move::Left);
     }
 
     #[wasm_bindgen(js_name = moveRight)]
     pub fn move_right(&mut self) {
-        self.camera.move_one(Move::Right);
+        self.move_camera(Move::Right);
     }
 
     #[wasm_bindgen(js_name = moveUp)]
     pub fn move_up(&mut self) {
-        self.camera.move_one(Move::Up);
+        self.move_camera(Move::Up);
     }
 
     #[wasm_bindgen(js_name = moveDown)]
     pub fn move_down(&mut self) {
-        self.camera.move_one(Move::Down);
+        self.move_camera(Move::Down);
     }
 
     #[wasm_bindgen(js_name = moveForward)]
     pub fn move_forward(&mut self) {
-        self.camera.move_one(Move::Forward);
+        self.move_camera(Move::Forward);
     }
 
     #[wasm_bindgen(js_name = moveBack)]
     pub fn move_back(&mut self) {
-        self.camera.move_one(Move::Back);
+        self.move_camera(Move::Back);
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl Scene {
-    fn light(&self, ray: &Ray, depth: u8) -> RGB {
-        let nearest =
-            self.spheres
-                .iter()
-                .fold((None, f64::INFINITY), |min, s| match s.intersect(ray) {
-                    Some(t) if t < min.1 => (Some(s), t),
-                    _ => min,
-                });
+    // Creates a scene of the given spheres and lights, with everything else
+    // at its defaults.
+    fn with_world(camera: Camera, spheres: Vec<Sphere>, lights: Vec<Light>) -> Self {
+        let geometry = visibility::reflected(&spheres);
+        let occluders = visibility::occluders(&spheres);
+        let ghosts = transparency::ghosts(&spheres);
+        let emitters = emitters::emitters(&spheres);
+        let light_clusters = LightClusters::new(&lights);
 
-        match nearest {
-            (Some(sphere), t) => {
-                let point = ray.point_at(t);
-                let normal = sphere.surface_normal(&point);
+        Self {
+            camera,
+            spheres,
+            geometry,
+            occluders,
+            ghosts,
+            emitters,
+            portals: vec![],
+            holdouts: false,
+            lights,
+            light_clusters,
+            light_links: vec![],
+            suns: vec![],
+            dirty: Some(Region::full()),
+            packet_tracing: false,
+            texture_compression: false,
+            render_order: RenderOrder::Scanline,
+            integrator: Integrator::PathTracing,
+            field: 0,
+            stale_field: false,
+            stats: Counters::default(),
+            ground_grid: None,
+            white_point: 1.,
+            ambient: None,
+            axis_gizmo: false,
+            motion_reference: None,
+            photon_map: None,
+            caustics: None,
+            irradiance_cache: None,
+            pixel_spread: Cell::new(0.),
+            materials: HashMap::new(),
+            shake: CameraShake::default(),
+            shutter: Shutter::default(),
+            camera_path: CameraPath::default(),
+            accel: AccelSettings::default(),
+            visible: SphereSet::new(vec![]),
+            stale_visible: true,
+            streaming: None,
+            occlusion: None,
+            hud: None,
+            environment: None,
+            sky: None,
+            backdrop: Background::Classic,
+            time_of_day: None,
+        }
+    }
+
+    fn move_camera(&mut self, mov: Move) {
+        self.camera.move_one(mov);
+        self.follow_camera();
+        self.invalidate(Region::full());
+    }
+
+    fn invalidate(&mut self, region: Region) {
+        self.stale_visible = true;
+        self.dirty = Some(match self.dirty {
+            Some(dirty) => dirty.union(&region),
+            None => region,
+        });
+    }
+
+    // The region of the frame covered by the given sphere, over the whole
+    // time that the shutter is open.
+    fn sphere_bounds(&self, sphere: &Sphere) -> Region {
+        self.camera.bounds(sphere, &self.shutter.camera_travel())
+    }
+
+    fn invalidate_lighting(&mut self) {
+        self.forget_caustics();
+        self.forget_irradiance();
+
+        // Lights only affect the shading of geometry, never the background, so
+        // the region covered by all spheres contains every pixel that changed.
+        let spheres = self.spheres.iter().map(|s| self.sphere_bounds(s));
+        if let Some(region) = spheres.reduce(|a, b| a.union(&b)) {
+            self.invalidate(region);
+        }
+    }
+
+    // Renders the given region of the frame, tracing one ray for every
+    // `step` × `step` block of pixels and filling the whole block with it.
+    fn render_region(&mut self, img: &mut Image, region: &Region, step: usize) {
+        self.update_visible();
+        let (xs, ys) = region.pixels(img.width, img.height);
+        self.render_pixels(img, xs, ys, step);
+    }
+
+    // Finds the spheres visible in the frame about to be rendered.
+    fn update_visible(&mut self) {
+        self.visible = self.visible_spheres();
+        self.stale_visible = false;
+    }
+
+    // Spheres hidden from the camera, or lying entirely outside the view
+    // frustum, can't be hit by any primary ray, although they may still cast
+    // shadows onto the visible spheres or show up in their reflections.
+    fn visible_spheres(&self) -> SphereSet {
+        let visible = SphereSet::new(self.spheres.iter().enumerate().filter(|(_, s)| {
+            s.resident && s.visibility.camera && self.sphere_bounds(s).overlaps(&Region::full())
+        }));
+
+        self.accelerate(visible, Some(&self.visible), &self.stats.build_time)
+    }
 
-                let radiance = self
-                    .lights
-                    .iter()
-                    .map(|light| light.illuminate(&self.spheres, &point, &normal))
-                    .sum();
+    // Renders the given pixels, as in `render_region`, out of the spheres
+    // found to be visible at the start of the frame.
+    fn render_pixels(&self, img: &mut Image, xs: Range<usize>, ys: Range<usize>, step: usize) {
+        let height_inv = 1. / img.height as f64;
+        let width_inv = 1. / img.width as f64;
+
+        let visible = &self.visible;
+
+        for y in ys.step_by(step) {
+            let y_offset = y as f64 * height_inv;
+            let cast = |x: usize, rng: &mut Rng| {
+                self.camera
+                    .cast_through_lens(x as f64 * width_inv, y_offset, rng)
+            };
+
+            // Packets trace their rays together, at a single time.
+            if self.packet_tracing && !self.shutter.is_open() {
+                let columns: Vec<usize> = xs.clone().step_by(step).collect();
+
+                for chunk in columns.chunks(PACKET_SIZE) {
+                    // Rows whose width isn't a multiple of the packet size end
+                    // with a partial chunk, whose spare lanes repeat its first ray.
+                    let mut rngs: Vec<_> = chunk.iter().map(|&x| Rng::for_pixel(x, y)).collect();
+                    let mut rays = [cast(chunk[0], &mut rngs[0]); PACKET_SIZE];
+                    for (i, &x) in chunk.iter().enumerate().skip(1) {
+                        rays[i] = cast(x, &mut rngs[i]);
+                    }
+
+                    let hits = RayPacket::new(&rays).nearest(visible, &self.stats);
+                    Counters::bump(&self.stats.primary_rays, chunk.len() as u64);
+
+                    for (i, (&x, rng)) in chunk.iter().zip(&mut rngs).enumerate() {
+                        let nearest = hits[i];
+                        let pixel = Footprint::new(x, y, width_inv, height_inv);
+                        let (color, alpha) =
+                            self.shade_pixel(visible, pixel, &rays[i], nearest, rng);
+                        img.fill(x, y, step, &color, alpha);
+                    }
+                }
+            } else {
+                for x in xs.clone().step_by(step) {
+                    let mut rng = Rng::for_pixel(x, y);
+                    let ray = self.primary_ray(visible, x as f64 * width_inv, y_offset, &mut rng);
+                    let nearest = self.nearest_visible(visible, &ray);
+                    Counters::bump(&self.stats.primary_rays, 1);
+                    let pixel = Footprint::new(x, y, width_inv, height_inv);
+                    let (color, alpha) = self.shade_pixel(visible, pixel, &ray, nearest, &mut rng);
+                    img.fill(x, y, step, &color, alpha);
+                }
+            }
+        }
+    }
+
+    fn light(&self, ray: &Ray, depth: u8, throughput: f64, sampled: Sampled, rng: &mut Rng) -> RGB {
+        self.shade(ray, self.nearest(ray), depth, throughput, sampled, rng)
+    }
+
+    fn nearest(&self, ray: &Ray) -> Option<(usize, f64)> {
+        self.nearest_visible(&self.geometry, ray)
+    }
 
-                let mut color = sphere.color;
+    // Finds the index of the nearest sphere hit by a ray, out of the given
+    // subset of spheres, and the distance to it.
+    fn nearest_visible(&self, spheres: &SphereSet, ray: &Ray) -> Option<(usize, f64)> {
+        spheres.nearest(ray, &self.stats)
+    }
+
+    // Computes the color and opacity seen along a primary ray, given the
+    // nearest sphere it hits. Pixels are opaque, except where shadow catchers
+    // let the background show through.
+    fn shade_primary(&self, ray: &Ray, nearest: Option<(usize, f64)>, rng: &mut Rng) -> (RGB, f64) {
+        if let Some((index, t)) = nearest {
+            let sphere = &self.spheres[index];
+            if let Some(reflectance) = sphere.material.shadow_catcher() {
+                let time = self.shutter.time.get();
+                let hit = sphere.hit_along(ray, t, time, t * self.pixel_spread.get());
+                return self.catch_shadows(ray, &hit, reflectance, rng);
+            }
+        }
 
-                if sphere.glossiness > 0. && depth < 100 {
-                    let reflection = ray.reflect(&point, &normal.unit());
-                    let reflection_color =
-                        self.light(&reflection, depth + 1).shade(sphere.glossiness);
+        (self.shade(ray, nearest, 1, 1., Sampled::default(), rng), 1.)
+    }
 
-                    color = color.add(&reflection_color)
+    // Computes the color seen along `ray`, given the nearest sphere it hits
+    // (if any) and the distance to it. The throughput is the fraction of this
+    // color that will make it back to the pixel, after all previous bounces,
+    // and `sampled` the light already sampled directly at the surface that
+    // the ray was scattered off, which it leaves out.
+    fn shade(
+        &self,
+        ray: &Ray,
+        nearest: Option<(usize, f64)>,
+        depth: u8,
+        throughput: f64,
+        sampled: Sampled,
+        rng: &mut Rng,
+    ) -> RGB {
+        match nearest {
+            Some((index, t)) => {
+                let sphere = &self.spheres[index];
+                self.stats.travel(t);
+                let color = self.shade_hit(ray, (index, t), depth, throughput, sampled, rng);
+                if sphere.transparency == 0. {
+                    return color;
                 }
 
-                color.shade(radiance)
+                // Sampled light was seen through transparent spheres too.
+                let behind = self.see_through(ray, (sphere, t), depth, throughput, sampled, rng);
+                color
+                    .scale(1. - sphere.transparency)
+                    .add(&behind.scale(sphere.transparency))
             }
-            (None, _) => {
-                let y = 0.7 - ray.direction.y.abs();
-                let mut x = ray.direction.x / 2.0;
-                if x < y {
-                    x = y
+            None => {
+                if sampled.portals && self.through_portal(ray) {
+                    return RGB::black();
                 }
-                RGB::new(x, y, x)
+                self.background(&ray.direction)
             }
         }
     }
+
+    // Computes the color of the surface of the sphere at `index` that `ray`
+    // hits, at the given distance, as in `shade`.
+    fn shade_hit(
+        &self,
+        ray: &Ray,
+        (index, t): (usize, f64),
+        depth: u8,
+        throughput: f64,
+        sampled: Sampled,
+        rng: &mut Rng,
+    ) -> RGB {
+        let sphere = &self.spheres[index];
+        let time = self.shutter.time.get();
+        let hit = sphere.hit_along(ray, t, time, t * self.pixel_spread.get());
+
+        // Shadow rays are only cast if the material asks for them.
+        let response = self.response();
+        let translucency = sphere.material.translucency();
+        let points = self
+            .light_clusters
+            .lights_for(&hit.point)
+            .filter(|light| self.illuminates(light, index))
+            .map(|light| {
+                let occluders = self.occluders_for(light);
+                let (direction, lux) = match translucency {
+                    Some(translucency) => {
+                        light.illuminate_translucent(occluders, &hit, translucency, &self.stats)
+                    }
+                    None => light.illuminate(occluders, &hit.point, &hit.normal, &self.stats),
+                };
+                let transmittance = self.transmittance(&hit.point, light);
+                let color = transmittance.tint(&light.color);
+                (direction, color.scale(lux).scale(response))
+            });
+        let suns = self.directional_lights().map(|sun| {
+            let (direction, lux) = match translucency {
+                Some(translucency) => {
+                    sun.illuminate_translucent(&self.occluders, &hit, translucency, &self.stats)
+                }
+                None => sun.illuminate(&self.occluders, &hit.point, &hit.normal, &self.stats),
+            };
+            let ray = sun.shadow_ray(&hit.point);
+            let transmittance = self.transmittance_along(&ray, f64::INFINITY);
+            let color = transmittance.tint(&sun.color);
+            (direction, color.scale(lux).scale(response))
+        });
+        let mut lights = points.chain(suns);
+
+        let emission = if sampled.glow {
+            RGB::black()
+        } else {
+            sphere.material.emission(&hit).scale(response)
+        };
+
+        if self.integrator == Integrator::AmbientOcclusion {
+            let open = self.openness(&hit, &self.occlusion.unwrap_or_default(), rng);
+            return RGB::new(open, open, open);
+        }
+
+        // Ambient light only reaches surfaces that reflect diffusely.
+        let ambient = match (&self.ambient, sphere.material.diffuse(&hit)) {
+            (Some(ambient), Some(albedo)) => {
+                let open = match &self.occlusion {
+                    Some(occlusion) => self.openness(&hit, occlusion, rng),
+                    None => 1.,
+                };
+                albedo.tint(&ambient.irradiance(&hit.normal).scale(response * open))
+            }
+            _ => RGB::black(),
+        };
+
+        // Caustics, too, only land on surfaces that reflect diffusely.
+        let caustic = match &self.caustics {
+            Some(_) => match sphere.material.diffuse(&hit) {
+                Some(albedo) => albedo.tint(&self.caustic(&hit)),
+                None => RGB::black(),
+            },
+            None => RGB::black(),
+        };
+
+        if self.integrator == Integrator::LitPreview {
+            let direct: RGB = lights.map(|(_, e)| e).sum();
+            return sphere
+                .material
+                .color(&hit)
+                .shade_by(&direct)
+                .add(&emission)
+                .add(&ambient)
+                .add(&caustic);
+        }
+
+        let mut scattered_light = RGB::black();
+        let mut sampled_light = RGB::black();
+
+        if depth < 100 {
+            let scattered = sphere.material.scatter(ray, &hit, rng);
+            let diffuse = scattered.iter().flatten().any(|scatter| scatter.diffuse);
+
+            // Surfaces seen by the camera may take the light bounced onto them
+            // from the irradiance cache, in place of their diffuse lobes.
+            let cached = if depth == 1 && diffuse && self.irradiance_cache.is_some() {
+                sphere.material.diffuse(&hit).and_then(|albedo| {
+                    let irradiance = self.cached_irradiance(&hit, throughput, rng)?;
+                    Some(albedo.tint(&irradiance))
+                })
+            } else {
+                None
+            };
+
+            // Light from glowing spheres, and from the environment through
+            // portals, is sampled directly, once for all of the surface's
+            // diffuse lobes, and left out of what they scatter.
+            let albedo = if self.emitters.is_empty() && self.portals.is_empty() {
+                None
+            } else if diffuse && cached.is_none() {
+                sphere.material.diffuse(&hit)
+            } else {
+                None
+            };
+            let mut sampled_here = Sampled::default();
+            if let Some(albedo) = &albedo {
+                let mut light = RGB::black();
+                if !self.emitters.is_empty() {
+                    light = light.add(&self.sample_emitters(&hit, rng));
+                    sampled_here.glow = true;
+                }
+                if !self.portals.is_empty() {
+                    light = light.add(&self.sample_portals(&hit, rng));
+                    sampled_here.portals = true;
+                }
+                sampled_light = albedo.tint(&light);
+            }
+            if let Some(cached) = cached {
+                sampled_light = cached;
+            }
+
+            for scatter in scattered.iter().flatten() {
+                if scatter.diffuse && cached.is_some() {
+                    continue;
+                }
+                let throughput = throughput * scatter.attenuation;
+
+                // Past the first few bounces, play Russian roulette:
+                // only follow the scattered ray with a probability
+                // proportional to how much it can still contribute, and
+                // scale up the ones that survive so that the image stays
+                // unbiased on average.
+                let survival = if depth < ROULETTE_DEPTH {
+                    1.
+                } else {
+                    throughput.min(MAX_SURVIVAL)
+                };
+
+                if rng.next_f64() < survival {
+                    Counters::bump(&self.stats.reflection_rays, 1);
+                    let sampled = if scatter.diffuse {
+                        sampled_here
+                    } else {
+                        Sampled::default()
+                    };
+                    let scattered_color = self
+                        .light(&scatter.ray, depth + 1, throughput, sampled, rng)
+                        .shade(scatter.attenuation)
+                        .tint(&scatter.tint)
+                        .scale(1. / survival);
+
+                    scattered_light = scattered_light.add(&scattered_color)
+                }
+            }
+        }
+
+        sphere
+            .material
+            .combine(ray, &hit, &mut lights, &scattered_light)
+            .add(&sampled_light)
+            .add(&emission)
+            .add(&ambient)
+            .add(&caustic)
+    }
+}
+
+// The kinds of light that a diffuse surface sampled directly, which the rays
+// scattered off it leave out.
+#[derive(Copy, Clone, Default)]
+struct Sampled {
+    // The glow of emissive spheres.
+    glow: bool,
+    // The environment, seen through portals.
+    portals: bool,
+}
+
+// Tracks how far into a frame `Scene::render_budgeted` has progressed.
+#[wasm_bindgen]
+pub struct RenderCursor {
+    next: usize,
+    total: usize,
+    order: RenderOrder,
+}
+
+#[wasm_bindgen]
+impl RenderCursor {
+    pub fn done(&self) -> bool {
+        self.next >= self.total
+    }
 }
 
 #[wasm_bindgen]
@@ -452,6 +1791,9 @@ pub struct Image {
     width: usize,
     height: usize,
     pixels: Vec<u8>,
+    color_space: ColorSpace,
+    curve: Curve,
+    lut: Option<Lut3d>,
 }
 
 #[wasm_bindgen]
@@ -465,17 +1807,84 @@ impl Image {
             width,
             height,
             pixels,
+            color_space: ColorSpace::Srgb,
+            curve: Curve::Preset(FilmCurve::Neutral),
+            lut: None,
         }
     }
 
     pub fn pixels(&self) -> *const u8 {
         self.pixels.as_ptr()
     }
+
+    // Sets the color space that pixels are written in by subsequent renders.
+    #[wasm_bindgen(js_name = setColorSpace)]
+    pub fn set_color_space(&mut self, color_space: ColorSpace) {
+        self.color_space = color_space;
+    }
 }
 
 impl Image {
+    // A blank image of the given size, which writes its pixels in the same
+    // way as this one.
+    fn blank(&self, width: usize, height: usize) -> Self {
+        let mut img = Self::new(width, height);
+        img.color_space = self.color_space;
+        img.curve = self.curve.clone();
+        img.lut = self.lut.clone();
+        img
+    }
+
     fn draw(&mut self, x: usize, y: usize, color: &RGB) {
+        self.draw_alpha(x, y, color, 1.);
+    }
+
+    // Draws a pixel with the given opacity, which is stored alongside it
+    // rather than blended with what was there before.
+    fn draw_alpha(&mut self, x: usize, y: usize, color: &RGB, alpha: f64) {
         let idx = (x + y * self.width) << 2;
-        color.write(&mut self.pixels[idx..idx + 4]);
+        let mut color = self.curve.apply(color);
+        if let Some(lut) = &self.lut {
+            color = lut.apply(&color);
+        }
+        let color = self.color_space.encode(&color);
+        color.write(&mut self.pixels[idx..idx + 4], alpha);
+    }
+
+    // Replaces the pixel at (x, y) with the average of its horizontal and
+    // vertical neighbours.
+    fn interpolate(&mut self, x: usize, y: usize) {
+        let neighbours = [
+            (x > 0, x.wrapping_sub(1), y),
+            (x + 1 < self.width, x + 1, y),
+            (y > 0, x, y.wrapping_sub(1)),
+            (y + 1 < self.height, x, y + 1),
+        ];
+
+        let mut sum = [0usize; 4];
+        let mut count = 0;
+
+        for &(_, nx, ny) in neighbours.iter().filter(|n| n.0) {
+            let idx = (nx + ny * self.width) << 2;
+            for (channel, sum) in sum.iter_mut().enumerate() {
+                *sum += self.pixels[idx + channel] as usize;
+            }
+            count += 1;
+        }
+
+        let idx = (x + y * self.width) << 2;
+        for (channel, sum) in sum.iter().enumerate() {
+            self.pixels[idx + channel] = (sum / usize::max(count, 1)) as u8;
+        }
+    }
+
+    // Draws a square block of `size` × `size` pixels with its top-left corner
+    // at (x, y), clipped to the edges of the image.
+    fn fill(&mut self, x: usize, y: usize, size: usize, color: &RGB, alpha: f64) {
+        for yy in y..usize::min(y + size, self.height) {
+            for xx in x..usize::min(x + size, self.width) {
+                self.draw_alpha(xx, yy, color, alpha);
+            }
+        }
     }
 }
@@ -0,0 +1,127 @@
+use crate::material::{
+    Anisotropic, Dielectric, Emissive, Glossy, Lambertian, Material, Metal, Pbr, Subsurface,
+};
+use crate::raster::unpack;
+use crate::Scene;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+
+// The material library lets a material be defined once, under a name, and
+// then shared by any number of spheres. Defining a material again under the
+// same name edits it: every sphere using it picks up the new definition on
+// the next render. Colors are given as 0xRRGGBB.
+#[wasm_bindgen]
+impl Scene {
+    // A matte material, which scatters light equally in all directions.
+    #[wasm_bindgen(js_name = defineMatte)]
+    pub fn define_matte(&mut self, name: &str, color: u32) {
+        self.define(name, Rc::new(Lambertian::new(unpack(color))));
+    }
+
+    // A glossy material, with a colored diffuse base under a clear coat that
+    // reflects the given color.
+    #[wasm_bindgen(js_name = defineGlossy)]
+    pub fn define_glossy(&mut self, name: &str, color: u32, reflectance: u32) {
+        self.define(
+            name,
+            Rc::new(Glossy::new(unpack(color), unpack(reflectance))),
+        );
+    }
+
+    // A metal, whose reflections blur as the roughness goes from 0 to 1.
+    #[wasm_bindgen(js_name = defineMetal)]
+    pub fn define_metal(&mut self, name: &str, color: u32, roughness: f64) {
+        self.define(name, Rc::new(Metal::new(unpack(color), roughness)));
+    }
+
+    // A brushed metal, with separate roughnesses along the sphere's lines of
+    // latitude and longitude, which stretch highlights across the brushing.
+    #[wasm_bindgen(js_name = defineBrushedMetal)]
+    pub fn define_brushed_metal(
+        &mut self,
+        name: &str,
+        color: u32,
+        roughness_along: f64,
+        roughness_across: f64,
+    ) {
+        let material = Anisotropic::new(unpack(color), roughness_along, roughness_across);
+        self.define(name, Rc::new(material));
+    }
+
+    // A clear glass with the given index of refraction.
+    #[wasm_bindgen(js_name = defineGlass)]
+    pub fn define_glass(&mut self, name: &str, ior: f64) {
+        self.define(name, Rc::new(Dielectric::new(ior)));
+    }
+
+    // A colored glass, which tints the light passing through it, and the
+    // shadows it casts.
+    #[wasm_bindgen(js_name = defineTintedGlass)]
+    pub fn define_tinted_glass(&mut self, name: &str, ior: f64, color: u32) {
+        self.define(name, Rc::new(Dielectric::tinted(ior, unpack(color))));
+    }
+
+    // A physically based material, from fully dielectric at a metallic value
+    // of 0 to fully metallic at 1.
+    #[wasm_bindgen(js_name = definePbr)]
+    pub fn define_pbr(&mut self, name: &str, color: u32, metallic: f64, roughness: f64) {
+        self.define(name, Rc::new(Pbr::new(unpack(color), metallic, roughness)));
+    }
+
+    // A translucent material, such as wax, skin or jade. The wrap, from 0 to 1,
+    // is how far light creeps past the terminator, and the depth is how far,
+    // in world units, light shines through the object before fading to 1/e.
+    #[wasm_bindgen(js_name = defineSubsurface)]
+    pub fn define_subsurface(&mut self, name: &str, color: u32, wrap: f64, depth: f64) {
+        self.define(name, Rc::new(Subsurface::new(unpack(color), wrap, depth)));
+    }
+
+    // A material that glows with the given luminance, in nits.
+    #[wasm_bindgen(js_name = defineEmissive)]
+    pub fn define_emissive(&mut self, name: &str, color: u32, luminance: f64) {
+        self.define(name, Rc::new(Emissive::new(unpack(color), luminance)));
+    }
+
+    // Gives the sphere at `index` the library material with the given name.
+    // Returns false, leaving the sphere as it was, if there isn't one, or no
+    // sphere at `index`.
+    #[wasm_bindgen(js_name = setSphereMaterial)]
+    pub fn set_sphere_material(&mut self, index: usize, name: &str) -> bool {
+        match (self.materials.get(name), self.spheres.get_mut(index)) {
+            (Some(material), Some(sphere)) => {
+                sphere.material = Rc::clone(material);
+                // Whether the sphere lets light through to its shadow may
+                // have changed along with its material.
+                self.rebuild_geometry();
+                self.invalidate_lighting();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Scene {
+    // Adds a material to the library, replacing any already defined under the
+    // same name, including on the spheres that use it.
+    fn define(&mut self, name: &str, material: Rc<dyn Material>) {
+        let previous = self
+            .materials
+            .insert(name.to_string(), Rc::clone(&material));
+
+        if let Some(previous) = previous {
+            let mut used = false;
+            for sphere in &mut self.spheres {
+                if Rc::ptr_eq(&sphere.material, &previous) {
+                    sphere.material = Rc::clone(&material);
+                    used = true;
+                }
+            }
+
+            if used {
+                self.rebuild_geometry();
+                self.invalidate_lighting();
+            }
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use crate::clusters::LightClusters;
+use crate::spheres::SphereSet;
+use crate::visibility::occluders;
+use crate::{Light, Scene};
+use wasm_bindgen::prelude::*;
+
+// Light linking, the art director's way of cheating: a light can be made to
+// light only some of the spheres, or all but some, and likewise to have only
+// some of them cast its shadows. A rim light can pick out the hero without
+// washing over the set, or a fill light can be kept from casting a second,
+// distracting shadow. Photons, which make up caustics, ignore links.
+
+// Which spheres a link applies to.
+#[wasm_bindgen]
+#[derive(Copy, Clone, PartialEq)]
+pub enum LinkMode {
+    // Every sphere, whatever the list says.
+    All,
+    // The spheres in the list, and no others.
+    Only,
+    // Every sphere but those in the list.
+    Except,
+}
+
+pub struct Links {
+    mode: LinkMode,
+    spheres: Vec<usize>,
+}
+
+impl Links {
+    fn all() -> Self {
+        Self {
+            mode: LinkMode::All,
+            spheres: vec![],
+        }
+    }
+
+    fn includes(&self, sphere: usize) -> bool {
+        match self.mode {
+            LinkMode::All => true,
+            LinkMode::Only => self.spheres.contains(&sphere),
+            LinkMode::Except => !self.spheres.contains(&sphere),
+        }
+    }
+}
+
+// The links of a single light.
+pub struct LightLinks {
+    // The spheres that the light lights.
+    illuminates: Links,
+    // The spheres that cast the light's shadows, and those of them that block
+    // shadow rays outright.
+    shadows: Links,
+    occluders: SphereSet,
+}
+
+impl Scene {
+    // Whether `light` lights the sphere at `index`.
+    pub(crate) fn illuminates(&self, light: &Light, index: usize) -> bool {
+        match light.link {
+            Some(link) => self.light_links[link].illuminates.includes(index),
+            None => true,
+        }
+    }
+
+    // Whether the sphere at `index` casts a shadow from `light`.
+    pub(crate) fn shadows(&self, light: &Light, index: usize) -> bool {
+        match light.link {
+            Some(link) => self.light_links[link].shadows.includes(index),
+            None => true,
+        }
+    }
+
+    // The spheres that block shadow rays cast towards `light`.
+    pub(crate) fn occluders_for(&self, light: &Light) -> &SphereSet {
+        match light.link {
+            Some(link) => &self.light_links[link].occluders,
+            None => &self.occluders,
+        }
+    }
+
+    // Brings the occluders of every linked light up to date with the spheres.
+    pub(crate) fn rebuild_light_links(&mut self) {
+        let mut links = std::mem::take(&mut self.light_links);
+        let clock = &self.stats.geometry_build_time;
+        let all = occluders(&self.spheres);
+        for link in &mut links {
+            let linked = SphereSet::new(
+                all.index
+                    .iter()
+                    .filter(|&&i| link.shadows.includes(i))
+                    .map(|&i| (i, &self.spheres[i])),
+            );
+            link.occluders = self.accelerate(linked, Some(&link.occluders), clock);
+        }
+        self.light_links = links;
+    }
+
+    // The links of the light at `index`, which start out linking it to every
+    // sphere, or `None` if there's no such light.
+    fn links_mut(&mut self, index: usize) -> Option<&mut LightLinks> {
+        let link = match self.lights.get(index)?.link {
+            Some(link) => link,
+            None => {
+                self.light_links.push(LightLinks {
+                    illuminates: Links::all(),
+                    shadows: Links::all(),
+                    occluders: occluders(&self.spheres),
+                });
+                self.lights[index].link = Some(self.light_links.len() - 1);
+                self.light_links.len() - 1
+            }
+        };
+        Some(&mut self.light_links[link])
+    }
+
+    fn relink(&mut self) {
+        self.light_clusters = LightClusters::new(&self.lights);
+        self.rebuild_light_links();
+        self.invalidate_lighting();
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Sets which spheres the light at `index` lights, as positions in the
+    // scene's list of spheres. The rest are left as if it were off.
+    #[wasm_bindgen(js_name = setLightIlluminationLinks)]
+    pub fn set_light_illumination_links(&mut self, index: usize, mode: LinkMode, spheres: &[u32]) {
+        let links = match self.links_mut(index) {
+            Some(links) => links,
+            None => return,
+        };
+        links.illuminates = Links {
+            mode,
+            spheres: spheres.iter().map(|&i| i as usize).collect(),
+        };
+        self.relink();
+    }
+
+    // Sets which spheres cast shadows from the light at `index`, as positions
+    // in the scene's list of spheres. Its light passes straight through the
+    // rest.
+    #[wasm_bindgen(js_name = setLightShadowLinks)]
+    pub fn set_light_shadow_links(&mut self, index: usize, mode: LinkMode, spheres: &[u32]) {
+        let links = match self.links_mut(index) {
+            Some(links) => links,
+            None => return,
+        };
+        links.shadows = Links {
+            mode,
+            spheres: spheres.iter().map(|&i| i as usize).collect(),
+        };
+        self.relink();
+    }
+}
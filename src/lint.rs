@@ -0,0 +1,124 @@
+use crate::{Scene, EPSILON};
+use wasm_bindgen::prelude::*;
+
+// The kinds of problem that `Scene::lint` looks for.
+#[wasm_bindgen]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum LintKind {
+    // Two spheres with the same center and radius, which fight over which of
+    // them each ray hits. The subject and the other are both spheres.
+    OverlappingSpheres,
+    // A light inside a sphere that casts shadows, which it can't shine out of.
+    // The subject is the light, and the other the sphere.
+    LightInsideGeometry,
+    // A light that gives off no light at all. The subject is the light.
+    ZeroPowerLight,
+    // A sphere whose material has parameters that can't happen physically.
+    // The subject is the sphere.
+    MaterialOutOfRange,
+}
+
+struct Warning {
+    kind: LintKind,
+    subject: usize,
+    other: Option<usize>,
+    message: String,
+}
+
+// The problems found in a scene, for the host to list. Each warning has a
+// kind, the index of the sphere or light it concerns, and a message to show.
+// Asking about a warning past the last one gives nothing back.
+#[wasm_bindgen]
+pub struct Lint {
+    warnings: Vec<Warning>,
+}
+
+#[wasm_bindgen]
+impl Lint {
+    pub fn count(&self) -> usize {
+        self.warnings.len()
+    }
+
+    pub fn kind(&self, i: usize) -> Option<LintKind> {
+        Some(self.warnings.get(i)?.kind)
+    }
+
+    pub fn subject(&self, i: usize) -> Option<usize> {
+        Some(self.warnings.get(i)?.subject)
+    }
+
+    // The second sphere or light involved in the warning, if there is one.
+    pub fn other(&self, i: usize) -> Option<usize> {
+        self.warnings.get(i)?.other
+    }
+
+    pub fn message(&self, i: usize) -> Option<String> {
+        Some(self.warnings.get(i)?.message.clone())
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Looks through the scene for common mistakes that make renders look
+    // wrong, or waste time, without being errors as such.
+    pub fn lint(&self) -> Lint {
+        let mut warnings = vec![];
+
+        for (i, a) in self.spheres.iter().enumerate() {
+            for (j, b) in self.spheres.iter().enumerate().skip(i + 1) {
+                let same_center = a.center.subtract(&b.center).length_sqr() < EPSILON;
+                if same_center && (a.radius - b.radius).abs() < EPSILON {
+                    warnings.push(Warning {
+                        kind: LintKind::OverlappingSpheres,
+                        subject: i,
+                        other: Some(j),
+                        message: format!("spheres {} and {} are identical", i, j),
+                    });
+                }
+            }
+        }
+
+        for (i, light) in self.lights.iter().enumerate() {
+            if light.power <= 0. {
+                warnings.push(Warning {
+                    kind: LintKind::ZeroPowerLight,
+                    subject: i,
+                    other: None,
+                    message: format!("light {} gives off no light", i),
+                });
+            }
+
+            let inside = self.spheres.iter().position(|s| {
+                s.visibility.shadow
+                    && s.transparency < 1.
+                    && light.pos.subtract(&s.center).length_sqr() < s.radius * s.radius
+            });
+            if let Some(j) = inside {
+                warnings.push(Warning {
+                    kind: LintKind::LightInsideGeometry,
+                    subject: i,
+                    other: Some(j),
+                    message: format!("light {} is inside sphere {}", i, j),
+                });
+            }
+        }
+
+        for (i, sphere) in self.spheres.iter().enumerate() {
+            if let Some(problem) = sphere.material.check() {
+                warnings.push(Warning {
+                    kind: LintKind::MaterialOutOfRange,
+                    subject: i,
+                    other: None,
+                    message: format!(
+                        "sphere {}'s {} material: {}",
+                        i,
+                        sphere.material.name(),
+                        problem
+                    ),
+                });
+            }
+        }
+
+        Lint { warnings }
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{Image, RGB};
+use wasm_bindgen::prelude::*;
+
+// The largest lattice the .cube format allows along each axis.
+const MAX_SIZE: usize = 256;
+
+// A 3D lookup table, which grades colors by mapping every point of a lattice
+// spanning the RGB cube to a new color, and interpolating in between. These
+// are usually exported from grading tools in the .cube format, which lets a
+// render be graded exactly like the rest of a production's footage.
+#[derive(Clone)]
+pub struct Lut3d {
+    size: usize,
+    domain_min: [f64; 3],
+    domain_max: [f64; 3],
+    // The lattice's colors, with red varying fastest, then green, then blue.
+    table: Vec<RGB>,
+}
+
+impl Lut3d {
+    // Parses the text of a .cube file, or returns `None` if it isn't a valid
+    // 3D LUT. Keywords that don't affect the lattice, such as TITLE, are
+    // skipped.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut size = None;
+        let mut domain_min = [0.; 3];
+        let mut domain_max = [1.; 3];
+        let mut table = vec![];
+
+        for line in text.lines() {
+            let mut words = line.split_whitespace();
+            let keyword = match words.next() {
+                Some(word) if !word.starts_with('#') => word,
+                _ => continue,
+            };
+
+            match keyword {
+                "LUT_3D_SIZE" => size = Some(words.next()?.parse::<usize>().ok()?),
+                "DOMAIN_MIN" => domain_min = triple(words)?,
+                "DOMAIN_MAX" => domain_max = triple(words)?,
+                // Only 3D LUTs are supported.
+                "LUT_1D_SIZE" => return None,
+                _ if keyword.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.') => {
+                    let [red, green, blue] = triple(line.split_whitespace())?;
+                    table.push(RGB::new(red, green, blue));
+                }
+                _ => {}
+            }
+        }
+
+        let size = size.filter(|&n| (2..=MAX_SIZE).contains(&n))?;
+        let points = size.checked_mul(size)?.checked_mul(size)?;
+        if table.len() != points || (0..3).any(|i| domain_max[i] <= domain_min[i]) {
+            return None;
+        }
+
+        Some(Self {
+            size,
+            domain_min,
+            domain_max,
+            table,
+        })
+    }
+
+    // Looks up a color, interpolating trilinearly between the eight nearest
+    // points of the lattice. Colors outside the domain are clamped to it.
+    pub fn apply(&self, color: &RGB) -> RGB {
+        let last = (self.size - 1) as f64;
+        let coord = |c: f64, i: usize| {
+            let t = (c - self.domain_min[i]) / (self.domain_max[i] - self.domain_min[i]);
+            let x = t.clamp(0., 1.) * last;
+            let lo = (x as usize).min(self.size - 2);
+            (lo, x - lo as f64)
+        };
+
+        let (r, fr) = coord(color.red, 0);
+        let (g, fg) = coord(color.green, 1);
+        let (b, fb) = coord(color.blue, 2);
+
+        let at = |dr: usize, dg: usize, db: usize| {
+            self.table[(r + dr) + self.size * ((g + dg) + self.size * (b + db))]
+        };
+        let mix = |a: RGB, b: RGB, t: f64| a.scale(1. - t).add(&b.scale(t));
+
+        let blue = |db: usize| {
+            mix(
+                mix(at(0, 0, db), at(1, 0, db), fr),
+                mix(at(0, 1, db), at(1, 1, db), fr),
+                fg,
+            )
+        };
+        mix(blue(0), blue(1), fb)
+    }
+}
+
+fn triple<'a>(mut words: impl Iterator<Item = &'a str>) -> Option<[f64; 3]> {
+    let mut next = || words.next()?.parse::<f64>().ok();
+    Some([next()?, next()?, next()?])
+}
+
+#[wasm_bindgen]
+impl Image {
+    // Grades subsequent renders with a 3D LUT, given as the text of a .cube
+    // file. This is the last step before pixels are converted to the image's
+    // color space, after the film response curve. The neutral curve leaves
+    // colors unclipped, so a LUT whose domain extends past 1 can grade the
+    // highlights of the render as well. Returns whether the LUT could be
+    // parsed; if not, the image is left ungraded. Passing an empty string
+    // removes it.
+    #[wasm_bindgen(js_name = setCubeLut)]
+    pub fn set_cube_lut(&mut self, text: &str) -> bool {
+        if text.trim().is_empty() {
+            self.lut = None;
+            return true;
+        }
+
+        self.lut = Lut3d::parse(text);
+        self.lut.is_some()
+    }
+}
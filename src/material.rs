@@ -0,0 +1,960 @@
+use crate::rng::Rng;
+use crate::texture::Texture;
+use crate::units::nits_to_lux;
+use crate::{Ray, Vec3, EPSILON, RGB};
+use std::f64::consts::PI;
+
+// Describes how a surface responds to light. Shapes only know their geometry;
+// everything about their appearance lives behind this trait, so that new
+// kinds of surface can be added without touching the tracer itself.
+pub trait Material {
+    // A short name for the kind of material, which identifies it in mattes.
+    fn name(&self) -> &'static str;
+
+    // The color of the surface at `hit`, before any lighting is applied.
+    fn color(&self, _hit: &Hit) -> RGB;
+
+    // The light given off by the surface itself at `hit`, which is seen
+    // whether or not anything lights it up. It is measured by the illuminance,
+    // in lux, that a white diffuse surface would need to look as bright.
+    fn emission(&self, _hit: &Hit) -> RGB {
+        RGB::black()
+    }
+
+    // Whether the surface gives off any light at all, so that the tracer can
+    // aim rays at it.
+    fn glows(&self) -> bool {
+        false
+    }
+
+    // The fraction of light falling on the surface at `hit` that it reflects
+    // diffusely, in every direction at once, or `None` for purely specular
+    // surfaces like glass and mirrors, which only reflect or refract light in
+    // a few directions.
+    fn diffuse(&self, hit: &Hit) -> Option<RGB> {
+        Some(self.color(hit))
+    }
+
+    // The fraction of light that a shadow catcher reflects, or `None` for any
+    // other material. Camera rays treat shadow catchers specially.
+    fn shadow_catcher(&self) -> Option<f64> {
+        None
+    }
+
+    // The color that light passing straight through the sphere is filtered
+    // by on its way to whatever lies in its shadow, for materials that let
+    // light through, or `None` for those that block it.
+    fn transmission(&self) -> Option<RGB> {
+        None
+    }
+
+    // How light passes through the surface, for translucent materials, or
+    // `None` for opaque ones. The tracer lights translucent surfaces from
+    // behind, and past the terminator, as well as from the front.
+    fn translucency(&self) -> Option<Translucency> {
+        None
+    }
+
+    // Describes what's wrong with the material's parameters, if anything:
+    // values that can't happen physically, and make renders misbehave.
+    fn check(&self) -> Option<&'static str> {
+        None
+    }
+
+    // Combines the light falling directly on the surface at `hit`, where it
+    // was hit by `ray`, with the light it scatters towards the viewer from
+    // elsewhere, into the color the viewer sees. By default, the scattered
+    // light adds to the surface color, and the sum is shaded by the lights.
+    fn combine(&self, _ray: &Ray, hit: &Hit, lights: &mut DirectLight, scattered: &RGB) -> RGB {
+        self.color(hit)
+            .add(scattered)
+            .shade_by(&lights.map(|(_, e)| e).sum())
+    }
+
+    // Scatters `ray`, which hit the surface at `hit`, returning the rays that
+    // leave the surface along with the fraction of light each carries back.
+    fn scatter(&self, ray: &Ray, hit: &Hit, rng: &mut Rng) -> Scattered;
+}
+
+// Where a ray hit a surface.
+pub struct Hit {
+    pub point: Vec3,
+    // The unit-length surface normal, facing outwards.
+    pub normal: Vec3,
+    // The point relative to the object it lies on, so that textures can stay
+    // attached to objects as they move around.
+    pub local: Vec3,
+    // Unit vectors along the surface, at right angles to the normal and each
+    // other, for materials that look different in different directions. The
+    // tangent runs around the object's equator, and the bitangent up it.
+    pub tangent: Vec3,
+    pub bitangent: Vec3,
+    // Roughly how wide, in world units, the patch of surface seen through a
+    // single pixel is, so that textures can filter out detail finer than it.
+    // Zero asks for a point sample.
+    pub spread: f64,
+}
+
+// The light reaching a surface straight from each of the scene's lights, as
+// the direction towards the light and the irradiance it delivers, which is
+// colored by any glass or transparent spheres it passed through on the way.
+// Each item costs a shadow ray, so materials that don't need them shouldn't
+// ask.
+pub type DirectLight<'a> = dyn Iterator<Item = (Vec3, RGB)> + 'a;
+
+// Surfaces scatter light into at most two rays, for reflection and refraction.
+// Keeping them in a fixed-size array avoids an allocation on every hit.
+pub type Scattered = [Option<Scatter>; 2];
+
+pub struct Scatter {
+    pub ray: Ray,
+    pub attenuation: f64,
+    // The color that the surface filters the scattered light through, on top
+    // of attenuating it.
+    pub tint: RGB,
+    // Whether the ray samples a diffuse lobe, whose light from glowing
+    // spheres is sampled directly instead.
+    pub diffuse: bool,
+}
+
+// A solid color with a mirror-like reflection on top. The strength of the
+// reflection is given per channel by its reflectance when viewed head-on, so
+// that it can tint what it reflects, and rises towards full strength at
+// grazing angles, as with a polished floor or a stretch of water. A black
+// reflectance means no reflection at all.
+pub struct Glossy {
+    color: RGB,
+    reflectance: RGB,
+}
+
+impl Glossy {
+    pub fn new(color: RGB, reflectance: RGB) -> Self {
+        Self { color, reflectance }
+    }
+}
+
+impl Material for Glossy {
+    fn name(&self) -> &'static str {
+        "glossy"
+    }
+
+    fn color(&self, _hit: &Hit) -> RGB {
+        self.color
+    }
+
+    fn check(&self) -> Option<&'static str> {
+        if !in_unit_range(&self.color) {
+            Some("color is outside [0, 1]")
+        } else if !in_unit_range(&self.reflectance) {
+            Some("reflectance is outside [0, 1]")
+        } else {
+            None
+        }
+    }
+
+    fn scatter(&self, ray: &Ray, hit: &Hit, _rng: &mut Rng) -> Scattered {
+        let f0 = &self.reflectance;
+        if f0.red <= 0. && f0.green <= 0. && f0.blue <= 0. {
+            return [None, None];
+        }
+
+        let cosine = -ray.direction.dot(&hit.normal);
+        let fresnel = RGB::new(
+            schlick(f0.red, cosine),
+            schlick(f0.green, cosine),
+            schlick(f0.blue, cosine),
+        );
+
+        // The strongest channel sets how much light the reflection carries,
+        // and the tint how it is split between the channels.
+        let strength = fresnel.red.max(fresnel.green).max(fresnel.blue);
+        let reflection = Scatter {
+            ray: ray.reflect(&hit.point, &hit.normal),
+            attenuation: strength,
+            tint: fresnel.scale(1. / strength),
+            diffuse: false,
+        };
+        [Some(reflection), None]
+    }
+}
+
+// A surface that glows with a color of its own, and reflects no light. Other
+// surfaces pick up its glow wherever they scatter rays towards it, although
+// it still blocks shadow rays from the scene's lights like any other object.
+pub struct Emissive {
+    emission: RGB,
+}
+
+impl Emissive {
+    // The color is scaled by the luminance, in nits.
+    pub fn new(color: RGB, luminance: f64) -> Self {
+        Self {
+            emission: color.scale(nits_to_lux(luminance)),
+        }
+    }
+}
+
+impl Material for Emissive {
+    fn name(&self) -> &'static str {
+        "emissive"
+    }
+
+    fn color(&self, _hit: &Hit) -> RGB {
+        RGB::black()
+    }
+
+    fn emission(&self, _hit: &Hit) -> RGB {
+        self.emission
+    }
+
+    fn glows(&self) -> bool {
+        !self.emission.is_black()
+    }
+
+    fn check(&self) -> Option<&'static str> {
+        let e = &self.emission;
+        if e.red < 0. || e.green < 0. || e.blue < 0. {
+            Some("emission is negative")
+        } else {
+            None
+        }
+    }
+
+    fn combine(&self, _ray: &Ray, _hit: &Hit, _lights: &mut DirectLight, _scattered: &RGB) -> RGB {
+        RGB::black()
+    }
+
+    fn scatter(&self, _ray: &Ray, _hit: &Hit, _rng: &mut Rng) -> Scattered {
+        [None, None]
+    }
+}
+
+// A stand-in for the ground (or any other surface) in a photograph that the
+// render will be composited over. Seen by the camera, it is transparent apart
+// from the shadows and reflections it receives, so that the photograph shows
+// through. Seen indirectly, it acts as a plain matte surface of the given
+// color, which should roughly match the photograph's.
+pub struct ShadowCatcher {
+    color: RGB,
+    reflectance: f64,
+}
+
+impl ShadowCatcher {
+    pub fn new(color: RGB, reflectance: f64) -> Self {
+        Self {
+            color,
+            reflectance: reflectance.clamp(0., 1.),
+        }
+    }
+}
+
+impl Material for ShadowCatcher {
+    fn name(&self) -> &'static str {
+        "shadow catcher"
+    }
+
+    fn color(&self, _hit: &Hit) -> RGB {
+        self.color
+    }
+
+    fn check(&self) -> Option<&'static str> {
+        (!in_unit_range(&self.color)).then_some("color is outside [0, 1]")
+    }
+
+    fn shadow_catcher(&self) -> Option<f64> {
+        Some(self.reflectance)
+    }
+
+    fn scatter(&self, _ray: &Ray, _hit: &Hit, _rng: &mut Rng) -> Scattered {
+        [None, None]
+    }
+}
+
+// A transparent material such as glass or water, which both reflects and
+// refracts light, as determined by its index of refraction.
+pub struct Dielectric {
+    ior: f64,
+    // The color that light is filtered by on its way through the glass.
+    tint: RGB,
+}
+
+impl Dielectric {
+    pub fn new(ior: f64) -> Self {
+        Self::tinted(ior, RGB::white())
+    }
+
+    pub fn tinted(ior: f64, tint: RGB) -> Self {
+        Self { ior, tint }
+    }
+
+    // The fraction of light reflected head-on.
+    fn r0(&self) -> f64 {
+        ((1. - self.ior) / (1. + self.ior)).powi(2)
+    }
+
+    // The fraction of light that is reflected rather than refracted, where
+    // `cosine` is taken on the side of the surface with the lower index of
+    // refraction.
+    fn reflectance(&self, cosine: f64) -> f64 {
+        schlick(self.r0(), cosine)
+    }
+}
+
+impl Material for Dielectric {
+    fn name(&self) -> &'static str {
+        "dielectric"
+    }
+
+    // Glass has no color of its own, and takes on that of whatever lies behind
+    // it. When a color is needed anyway, clear glass may as well be white.
+    fn color(&self, _hit: &Hit) -> RGB {
+        RGB::white()
+    }
+
+    // Below 1, light would travel faster inside the material than in a vacuum.
+    fn check(&self) -> Option<&'static str> {
+        if self.ior < 1. {
+            Some("index of refraction is below 1")
+        } else if !in_unit_range(&self.tint) {
+            Some("tint is outside [0, 1]")
+        } else {
+            None
+        }
+    }
+
+    // Shadow rays carry straight on through glass, rather than bending as
+    // light does, so its shadow is only dimmed by the tint and the light
+    // reflected away as it enters and leaves, head-on.
+    fn transmission(&self) -> Option<RGB> {
+        Some(self.tint.scale((1. - self.r0()).powi(2)))
+    }
+
+    fn diffuse(&self, _hit: &Hit) -> Option<RGB> {
+        None
+    }
+
+    // Glass gets all of its color from the light passing through it, so it
+    // has no need for shadow rays.
+    fn combine(&self, _ray: &Ray, _hit: &Hit, _lights: &mut DirectLight, scattered: &RGB) -> RGB {
+        *scattered
+    }
+
+    fn scatter(&self, ray: &Ray, hit: &Hit, _rng: &mut Rng) -> Scattered {
+        // Work out which side of the surface the ray is coming from, and flip
+        // the normal around to face it if it's coming from the inside.
+        let (normal, eta) = if ray.direction.dot(&hit.normal) < 0. {
+            (hit.normal, 1. / self.ior)
+        } else {
+            (hit.normal.scale(-1.), self.ior)
+        };
+
+        let reflection = ray.reflect(&hit.point, &normal);
+        let cos_i = -ray.direction.dot(&normal);
+        let sin2_t = eta * eta * (1. - cos_i * cos_i);
+
+        // Past the critical angle, all of the light is reflected.
+        if sin2_t > 1. {
+            let reflection = Scatter {
+                ray: reflection,
+                attenuation: 1.,
+                tint: RGB::white(),
+                diffuse: false,
+            };
+            return [Some(reflection), None];
+        }
+
+        // Snell's law, for unit vectors. The result is a unit vector too.
+        let cos_t = (1. - sin2_t).sqrt();
+        let direction = ray
+            .direction
+            .scale(eta)
+            .add(&normal.scale(eta * cos_i - cos_t));
+        let refraction = Ray {
+            origin: hit.point,
+            direction,
+        };
+
+        // Split the light between the two rays, weighted by the reflectance.
+        // Russian roulette soon stops following whichever carries little light.
+        let reflectance = self.reflectance(if eta < 1. { cos_i } else { cos_t });
+        [
+            Some(Scatter {
+                ray: reflection,
+                attenuation: reflectance,
+                tint: RGB::white(),
+                diffuse: false,
+            }),
+            // Light is tinted as it enters the glass, once for each time
+            // it passes through.
+            Some(Scatter {
+                ray: refraction,
+                attenuation: 1. - reflectance,
+                tint: if eta < 1. { self.tint } else { RGB::white() },
+                diffuse: false,
+            }),
+        ]
+    }
+}
+
+// A metallic surface, whose reflections get blurrier the rougher it is. Each
+// reflected ray is nudged in a random direction, by up to `roughness` times
+// its length, so that a roughness of zero makes for a perfect mirror.
+pub struct Metal {
+    color: RGB,
+    roughness: f64,
+}
+
+impl Metal {
+    pub fn new(color: RGB, roughness: f64) -> Self {
+        Self {
+            color,
+            roughness: roughness.clamp(0., 1.),
+        }
+    }
+}
+
+impl Material for Metal {
+    fn name(&self) -> &'static str {
+        "metal"
+    }
+
+    fn color(&self, _hit: &Hit) -> RGB {
+        self.color
+    }
+
+    fn check(&self) -> Option<&'static str> {
+        (!in_unit_range(&self.color)).then_some("color is outside [0, 1]")
+    }
+
+    fn diffuse(&self, _hit: &Hit) -> Option<RGB> {
+        None
+    }
+
+    fn scatter(&self, ray: &Ray, hit: &Hit, rng: &mut Rng) -> Scattered {
+        let mirror = ray.reflect(&hit.point, &hit.normal);
+        if self.roughness == 0. {
+            return [
+                Some(Scatter {
+                    ray: mirror,
+                    attenuation: 1.,
+                    tint: RGB::white(),
+                    diffuse: false,
+                }),
+                None,
+            ];
+        }
+
+        let direction = mirror
+            .direction
+            .add(&in_unit_sphere(rng).scale(self.roughness));
+
+        // Rays nudged below the surface are absorbed.
+        if direction.dot(&hit.normal) <= 0. {
+            return [None, None];
+        }
+
+        let reflection = Scatter {
+            ray: Ray::new(hit.point, direction),
+            attenuation: 1.,
+            tint: RGB::white(),
+            diffuse: false,
+        };
+        [Some(reflection), None]
+    }
+}
+
+// A perfectly matte surface, which scatters light equally in all directions.
+// Rays are scattered with a cosine-weighted distribution over the hemisphere
+// around the normal, which exactly cancels out the cosine in the rendering
+// equation, so the scattered light only needs tinting by the surface color.
+// Light bouncing off nearby surfaces picks up their color along the way.
+pub struct Lambertian {
+    albedo: Box<dyn Texture>,
+}
+
+impl Lambertian {
+    pub fn new<T: Texture + 'static>(albedo: T) -> Self {
+        Self {
+            albedo: Box::new(albedo),
+        }
+    }
+}
+
+impl Material for Lambertian {
+    fn name(&self) -> &'static str {
+        "lambertian"
+    }
+
+    fn color(&self, hit: &Hit) -> RGB {
+        self.albedo.value(hit)
+    }
+
+    fn combine(&self, _ray: &Ray, hit: &Hit, lights: &mut DirectLight, scattered: &RGB) -> RGB {
+        let direct = lights.map(|(_, e)| e).sum::<RGB>().max(0.);
+        let incoming = direct.add(scattered);
+        self.color(hit).tint(&incoming)
+    }
+
+    fn scatter(&self, _ray: &Ray, hit: &Hit, rng: &mut Rng) -> Scattered {
+        let scatter = Scatter {
+            ray: Ray::new(hit.point, cosine_weighted(&hit.normal, rng)),
+            attenuation: 1.,
+            tint: RGB::white(),
+            diffuse: true,
+        };
+        [Some(scatter), None]
+    }
+}
+
+// An approximation of subsurface scattering, for translucent materials like
+// wax, skin or jade, which scatter light around beneath their surface before
+// it comes back out. Rather than tracing light through the volume, two cheap
+// tricks stand in for it: wrap lighting lets light creep past the terminator,
+// softening it, and light reaching the back of an object shines through to
+// the front, dimming with the distance it travels inside. Otherwise, it acts
+// as a matte surface.
+pub struct Subsurface {
+    color: RGB,
+    translucency: Translucency,
+}
+
+#[derive(Copy, Clone)]
+pub struct Translucency {
+    // How far past the terminator light wraps around, from 0 for not at all
+    // to 1 for all the way round to the far side.
+    pub wrap: f64,
+    // The distance, in world units, over which light travelling through the
+    // object falls to 1/e of its strength.
+    pub depth: f64,
+}
+
+impl Subsurface {
+    pub fn new(color: RGB, wrap: f64, depth: f64) -> Self {
+        Self {
+            color,
+            translucency: Translucency {
+                wrap: wrap.clamp(0., 1.),
+                depth: depth.max(EPSILON),
+            },
+        }
+    }
+}
+
+impl Material for Subsurface {
+    fn name(&self) -> &'static str {
+        "subsurface"
+    }
+
+    fn color(&self, _hit: &Hit) -> RGB {
+        self.color
+    }
+
+    fn check(&self) -> Option<&'static str> {
+        (!in_unit_range(&self.color)).then_some("color is outside [0, 1]")
+    }
+
+    fn translucency(&self) -> Option<Translucency> {
+        Some(self.translucency)
+    }
+
+    fn combine(&self, _ray: &Ray, hit: &Hit, lights: &mut DirectLight, scattered: &RGB) -> RGB {
+        let direct = lights.map(|(_, e)| e).sum::<RGB>().max(0.);
+        let incoming = direct.add(scattered);
+        self.color(hit).tint(&incoming)
+    }
+
+    fn scatter(&self, _ray: &Ray, hit: &Hit, rng: &mut Rng) -> Scattered {
+        let scatter = Scatter {
+            ray: Ray::new(hit.point, cosine_weighted(&hit.normal, rng)),
+            attenuation: 1.,
+            tint: RGB::white(),
+            diffuse: true,
+        };
+        [Some(scatter), None]
+    }
+}
+
+// A physically based material following the metallic-roughness model used by
+// glTF, with a Lambertian diffuse lobe and a GGX microfacet specular lobe.
+// Dielectrics (metallic = 0) reflect 4% of light head-on, whitely, while
+// metals (metallic = 1) reflect their albedo and have no diffuse lobe at all.
+pub struct Pbr {
+    albedo: RGB,
+    metallic: f64,
+    roughness: f64,
+}
+
+// The GGX distribution degenerates into a spike as roughness approaches zero,
+// so it is kept at least this rough when evaluated.
+const MIN_ALPHA: f64 = 1e-3;
+
+impl Pbr {
+    pub fn new(albedo: RGB, metallic: f64, roughness: f64) -> Self {
+        Self {
+            albedo,
+            metallic: metallic.clamp(0., 1.),
+            roughness: roughness.clamp(0., 1.),
+        }
+    }
+
+    // Roughness is perceptually linear, whereas GGX's alpha isn't.
+    fn alpha(&self) -> f64 {
+        (self.roughness * self.roughness).max(MIN_ALPHA)
+    }
+
+    // Schlick's approximation of the Fresnel reflectance, starting from 4% at
+    // normal incidence for dielectrics, or from the albedo for metals.
+    fn fresnel(&self, cosine: f64) -> RGB {
+        let f0 = RGB::new(0.04, 0.04, 0.04)
+            .scale(1. - self.metallic)
+            .add(&self.albedo.scale(self.metallic));
+        RGB::new(
+            schlick(f0.red, cosine),
+            schlick(f0.green, cosine),
+            schlick(f0.blue, cosine),
+        )
+    }
+
+    // The GGX normal distribution function, for a microfacet normal at an
+    // angle with the given cosine to the surface normal.
+    fn distribution(&self, cosine: f64) -> f64 {
+        let a2 = self.alpha().powi(2);
+        a2 / (PI * (cosine * cosine * (a2 - 1.) + 1.).powi(2))
+    }
+
+    // The Smith masking-shadowing term, using Schlick's approximation of G1.
+    fn geometry(&self, n_dot_v: f64, n_dot_l: f64) -> f64 {
+        let k = self.alpha() / 2.;
+        let g1 = |cosine: f64| cosine / (cosine * (1. - k) + k);
+        g1(n_dot_v) * g1(n_dot_l)
+    }
+}
+
+impl Material for Pbr {
+    fn name(&self) -> &'static str {
+        "pbr"
+    }
+
+    fn color(&self, _hit: &Hit) -> RGB {
+        self.albedo
+    }
+
+    fn check(&self) -> Option<&'static str> {
+        (!in_unit_range(&self.albedo)).then_some("color is outside [0, 1]")
+    }
+
+    fn diffuse(&self, _hit: &Hit) -> Option<RGB> {
+        if self.metallic < 1. {
+            Some(self.albedo.scale(1. - self.metallic))
+        } else {
+            None
+        }
+    }
+
+    fn combine(&self, ray: &Ray, hit: &Hit, lights: &mut DirectLight, scattered: &RGB) -> RGB {
+        let normal = &hit.normal;
+        let view = ray.direction.scale(-1.);
+        let n_dot_v = normal.dot(&view).max(EPSILON);
+        let diffuse = self.albedo.scale(1. - self.metallic);
+
+        let mut color = *scattered;
+        for (light, irradiance) in lights {
+            if irradiance.is_black() {
+                continue;
+            }
+
+            let half = view.add(&light).unit();
+            let n_dot_l = normal.dot(&light);
+            let fresnel = self.fresnel(view.dot(&half));
+
+            // The specular BRDF is scaled up by π, to match the scene's light
+            // units, in which a white Lambertian surface has a BRDF of 1.
+            let specular =
+                PI * self.distribution(normal.dot(&half)) * self.geometry(n_dot_v, n_dot_l)
+                    / (4. * n_dot_v * n_dot_l);
+
+            let reflected = diffuse
+                .tint(&RGB::white().subtract(&fresnel))
+                .add(&fresnel.scale(specular));
+            color = color.add(&reflected.tint(&irradiance));
+        }
+
+        color
+    }
+
+    fn scatter(&self, ray: &Ray, hit: &Hit, rng: &mut Rng) -> Scattered {
+        let normal = &hit.normal;
+        let view = ray.direction.scale(-1.);
+        let n_dot_v = normal.dot(&view);
+        if n_dot_v <= 0. {
+            return [None, None];
+        }
+
+        // Sample a microfacet normal in proportion to how much of the surface
+        // faces that way, and reflect the ray off it.
+        let a2 = self.alpha().powi(2);
+        let (u, v) = (rng.next_f64(), rng.next_f64());
+        let cos_theta = ((1. - u) / (1. + (a2 - 1.) * u)).sqrt();
+        let sin_theta = (1. - cos_theta * cos_theta).sqrt();
+        let phi = 2. * PI * v;
+
+        let (tangent, bitangent) = basis(normal);
+        let half = tangent
+            .scale(sin_theta * phi.cos())
+            .add(&bitangent.scale(sin_theta * phi.sin()))
+            .add(&normal.scale(cos_theta));
+
+        let reflection = ray.reflect(&hit.point, &half);
+        let n_dot_l = normal.dot(&reflection.direction);
+        let specular = if n_dot_l > 0. {
+            let v_dot_h = view.dot(&half);
+            let weight = self.geometry(n_dot_v, n_dot_l) * v_dot_h / (n_dot_v * cos_theta);
+            Some(Scatter {
+                ray: reflection,
+                attenuation: weight.min(1.),
+                tint: self.fresnel(v_dot_h),
+                diffuse: false,
+            })
+        } else {
+            None
+        };
+
+        let diffuse = if self.metallic < 1. {
+            Some(Scatter {
+                ray: Ray::new(hit.point, cosine_weighted(normal, rng)),
+                attenuation: 1. - self.metallic,
+                tint: self.albedo,
+                diffuse: true,
+            })
+        } else {
+            None
+        };
+
+        [specular, diffuse]
+    }
+}
+
+// A brushed metal, whose microfacets are stretched in one direction across
+// the surface, smearing highlights and reflections out at right angles to the
+// brushing. This uses the anisotropic form of GGX, with separate roughnesses
+// along the tangent and the bitangent of the hit.
+pub struct Anisotropic {
+    color: RGB,
+    alpha_t: f64,
+    alpha_b: f64,
+}
+
+impl Anisotropic {
+    pub fn new(color: RGB, roughness_t: f64, roughness_b: f64) -> Self {
+        // As with `Pbr`, roughness is squared to make it perceptually linear.
+        let alpha = |r: f64| r.clamp(0., 1.).powi(2).max(MIN_ALPHA);
+        Self {
+            color,
+            alpha_t: alpha(roughness_t),
+            alpha_b: alpha(roughness_b),
+        }
+    }
+
+    // The GGX normal distribution function, for a microfacet normal given in
+    // the hit's tangent frame.
+    fn distribution(&self, half: &Vec3) -> f64 {
+        let (x, y) = (half.x / self.alpha_t, half.y / self.alpha_b);
+        let d = x * x + y * y + half.z * half.z;
+        1. / (PI * self.alpha_t * self.alpha_b * d * d)
+    }
+
+    // The height-correlated Smith masking-shadowing term, for directions given
+    // in the hit's tangent frame.
+    fn geometry(&self, view: &Vec3, light: &Vec3) -> f64 {
+        let lambda = |v: &Vec3| {
+            let a2 = (self.alpha_t * v.x).powi(2) + (self.alpha_b * v.y).powi(2);
+            ((1. + a2 / (v.z * v.z)).sqrt() - 1.) / 2.
+        };
+        1. / (1. + lambda(view) + lambda(light))
+    }
+
+    fn fresnel(&self, cosine: f64) -> RGB {
+        RGB::new(
+            schlick(self.color.red, cosine),
+            schlick(self.color.green, cosine),
+            schlick(self.color.blue, cosine),
+        )
+    }
+}
+
+// Expresses a direction in the tangent frame of a hit, with the normal as Z.
+fn to_tangent(hit: &Hit, v: &Vec3) -> Vec3 {
+    Vec3::new(hit.tangent.dot(v), hit.bitangent.dot(v), hit.normal.dot(v))
+}
+
+impl Material for Anisotropic {
+    fn name(&self) -> &'static str {
+        "anisotropic"
+    }
+
+    fn color(&self, _hit: &Hit) -> RGB {
+        self.color
+    }
+
+    fn check(&self) -> Option<&'static str> {
+        (!in_unit_range(&self.color)).then_some("color is outside [0, 1]")
+    }
+
+    fn diffuse(&self, _hit: &Hit) -> Option<RGB> {
+        None
+    }
+
+    fn combine(&self, ray: &Ray, hit: &Hit, lights: &mut DirectLight, scattered: &RGB) -> RGB {
+        let view = ray.direction.scale(-1.);
+        let v = to_tangent(hit, &view);
+        if v.z <= 0. {
+            return *scattered;
+        }
+
+        let mut color = *scattered;
+        for (light, irradiance) in lights {
+            if irradiance.is_black() {
+                continue;
+            }
+
+            let half = view.add(&light).unit();
+            let l = to_tangent(hit, &light);
+            let h = to_tangent(hit, &half);
+
+            // Scaled up by π to match the scene's light units, as in `Pbr`.
+            let specular = PI * self.distribution(&h) * self.geometry(&v, &l) / (4. * v.z * l.z);
+            let reflected = self.fresnel(view.dot(&half)).scale(specular);
+            color = color.add(&reflected.tint(&irradiance));
+        }
+
+        color
+    }
+
+    fn scatter(&self, ray: &Ray, hit: &Hit, rng: &mut Rng) -> Scattered {
+        let view = ray.direction.scale(-1.);
+        let v = to_tangent(hit, &view);
+        if v.z <= 0. {
+            return [None, None];
+        }
+
+        // Sample a microfacet normal in proportion to how much of the surface
+        // faces that way: first its azimuth, squeezed towards the smoother
+        // direction, and then its slope, given the roughness along it.
+        let (u1, u2) = (rng.next_f64(), rng.next_f64());
+        let phi = (self.alpha_b * (2. * PI * u2).sin()).atan2(self.alpha_t * (2. * PI * u2).cos());
+        let (sin_phi, cos_phi) = phi.sin_cos();
+        let alpha2 = 1. / ((cos_phi / self.alpha_t).powi(2) + (sin_phi / self.alpha_b).powi(2));
+        let tan2_theta = alpha2 * u1 / (1. - u1).max(EPSILON);
+        let cos_theta = 1. / (1. + tan2_theta).sqrt();
+        let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+
+        let half = hit
+            .tangent
+            .scale(sin_theta * cos_phi)
+            .add(&hit.bitangent.scale(sin_theta * sin_phi))
+            .add(&hit.normal.scale(cos_theta));
+
+        let reflection = ray.reflect(&hit.point, &half);
+        let l = to_tangent(hit, &reflection.direction);
+        if l.z <= 0. {
+            return [None, None];
+        }
+
+        let v_dot_h = view.dot(&half);
+        let weight = self.geometry(&v, &l) * v_dot_h / (v.z * cos_theta);
+        let specular = Scatter {
+            ray: reflection,
+            attenuation: weight.min(1.),
+            tint: self.fresnel(v_dot_h),
+            diffuse: false,
+        };
+        [Some(specular), None]
+    }
+}
+
+// Picks one of the scattered rays at random, in proportion to how much light
+// each carries, and returns it along with its weight: the light it carries,
+// divided by the probability of picking it.
+pub fn pick(scattered: &Scattered, rng: &mut Rng) -> Option<(Ray, RGB)> {
+    let total: f64 = scattered.iter().flatten().map(|s| s.attenuation).sum();
+    if total <= 0. {
+        return None;
+    }
+
+    let mut threshold = rng.next_f64() * total;
+    let mut picked = None;
+    for scatter in scattered.iter().flatten() {
+        picked = Some(scatter);
+        threshold -= scatter.attenuation;
+        if threshold < 0. {
+            break;
+        }
+    }
+
+    picked.map(|s| (s.ray, s.tint.scale(total)))
+}
+
+// Schlick's approximation of the Fresnel equations, giving the fraction of
+// light reflected off a surface that reflects `f0` of it head-on, when lit at
+// an angle with the given cosine to the normal.
+fn schlick(f0: f64, cosine: f64) -> f64 {
+    f0 + (1. - f0) * (1. - cosine.clamp(0., 1.)).powi(5)
+}
+
+// Colors that surfaces reflect or filter light through must lie within [0, 1],
+// or else the surface would create light out of nowhere.
+fn in_unit_range(color: &RGB) -> bool {
+    let ok = |c: f64| (0. ..=1.).contains(&c);
+    ok(color.red) && ok(color.green) && ok(color.blue)
+}
+
+// Picks a direction at random over the hemisphere around `normal`, with a
+// probability proportional to the cosine of its angle to the normal.
+pub fn cosine_weighted(normal: &Vec3, rng: &mut Rng) -> Vec3 {
+    // Adding a uniformly distributed unit vector to the normal yields exactly
+    // that. The sum can only vanish if the two point in opposite directions,
+    // in which case the normal itself will do.
+    let direction = normal.add(&on_unit_sphere(rng));
+    if direction.length_sqr() < 1e-12 {
+        *normal
+    } else {
+        direction
+    }
+}
+
+// Builds two unit vectors that, together with the unit vector `n`, form an
+// orthonormal basis, following Duff et al. (2017).
+pub fn basis(n: &Vec3) -> (Vec3, Vec3) {
+    let sign = 1_f64.copysign(n.z);
+    let a = -1. / (sign + n.z);
+    let b = n.x * n.y * a;
+    (
+        Vec3::new(1. + sign * n.x * n.x * a, sign * b, -sign * n.x),
+        Vec3::new(b, sign + n.y * n.y * a, -n.y),
+    )
+}
+
+// Picks a point uniformly at random inside the unit sphere, by rejection.
+fn in_unit_sphere(rng: &mut Rng) -> Vec3 {
+    loop {
+        let p = Vec3::new(
+            2. * rng.next_f64() - 1.,
+            2. * rng.next_f64() - 1.,
+            2. * rng.next_f64() - 1.,
+        );
+        if p.length_sqr() < 1. {
+            return p;
+        }
+    }
+}
+
+// Picks a point uniformly at random on the surface of the unit sphere.
+fn on_unit_sphere(rng: &mut Rng) -> Vec3 {
+    loop {
+        let p = in_unit_sphere(rng);
+        let len_sqr = p.length_sqr();
+        if len_sqr > 1e-12 {
+            return p.scale(1. / len_sqr.sqrt());
+        }
+    }
+}
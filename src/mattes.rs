@@ -0,0 +1,117 @@
+use crate::holdout::COVERAGE_SAMPLES;
+use crate::texture::holds_image;
+use crate::{Scene, Sphere};
+use wasm_bindgen::prelude::*;
+
+// What the IDs in a matte identify.
+#[wasm_bindgen]
+#[derive(Copy, Clone, PartialEq)]
+pub enum MatteKind {
+    // Each sphere has an ID of its own: its index in the scene, plus one.
+    Object,
+    // Spheres of the same kind of material share an ID, which is a hash of
+    // the material's name. See `materialId`.
+    Material,
+}
+
+// The number of (ID, coverage) pairs kept for each pixel, starting with the
+// ID that covers the most of it.
+const RANKS: usize = 2;
+
+// The ID given to the background, in either kind of matte.
+const BACKGROUND: u32 = 0;
+
+#[wasm_bindgen]
+impl Scene {
+    // Writes ID mattes for a `width` × `height` frame, in the style of
+    // Cryptomatte. Every pixel gets `RANKS` (2) pairs of an ID, in `ids`, and
+    // the fraction of the pixel it covers, in `coverage`, sorted by coverage,
+    // so that compositors can pull a clean matte for any object, even along
+    // anti-aliased edges. Coverage is measured with the same sub-pixel grid
+    // as holdouts use; unused pairs are left with zero coverage. Buffers too
+    // small to hold the whole frame are left untouched.
+    #[wasm_bindgen(js_name = renderMattes)]
+    pub fn render_mattes(
+        &mut self,
+        width: usize,
+        height: usize,
+        kind: MatteKind,
+        ids: &mut [u32],
+        coverage: &mut [f32],
+    ) {
+        if !holds_image(ids.len(), width, height, RANKS)
+            || !holds_image(coverage.len(), width, height, RANKS)
+        {
+            return;
+        }
+        self.fit_film(width, height);
+
+        let visible = self.visible_spheres();
+        let n = COVERAGE_SAMPLES;
+        let weight = 1. / (n * n) as f64;
+        let (w, h) = (width as f64, height as f64);
+
+        let mut counts: Vec<(u32, f64)> = Vec::with_capacity(n * n);
+
+        for y in 0..height {
+            for x in 0..width {
+                counts.clear();
+
+                for i in 0..n {
+                    for j in 0..n {
+                        let u = (x as f64 + (i as f64 + 0.5) / n as f64) / w;
+                        let v = (y as f64 + (j as f64 + 0.5) / n as f64) / h;
+                        let ray = self.camera.cast(u, v);
+
+                        let id = visible
+                            .nearest(&ray, &self.stats)
+                            .map_or(BACKGROUND, |(i, _)| match kind {
+                                MatteKind::Object => i as u32 + 1,
+                                MatteKind::Material => material_id(&self.spheres[i]),
+                            });
+
+                        match counts.iter_mut().find(|(other, _)| *other == id) {
+                            Some((_, c)) => *c += weight,
+                            None => counts.push((id, weight)),
+                        }
+                    }
+                }
+
+                counts.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+
+                let idx = RANKS * (y * width + x);
+                for rank in 0..RANKS {
+                    let (id, c) = counts.get(rank).copied().unwrap_or((BACKGROUND, 0.));
+                    ids[idx + rank] = id;
+                    coverage[idx + rank] = c as f32;
+                }
+            }
+        }
+    }
+
+    // The name of the material of the sphere at `index`, if there is one.
+    #[wasm_bindgen(js_name = materialName)]
+    pub fn material_name(&self, index: usize) -> Option<String> {
+        let sphere = self.spheres.get(index)?;
+        Some(sphere.material.name().to_string())
+    }
+
+    // The ID that the material of the sphere at `index` has in material
+    // mattes, if there is one.
+    #[wasm_bindgen(js_name = materialId)]
+    pub fn material_id(&self, index: usize) -> Option<u32> {
+        self.spheres.get(index).map(material_id)
+    }
+}
+
+// Hashes the material's name, steering clear of the background's ID.
+fn material_id(sphere: &Sphere) -> u32 {
+    fnv1a(sphere.material.name()).max(BACKGROUND + 1)
+}
+
+// The 32-bit FNV-1a hash of a string.
+fn fnv1a(name: &str) -> u32 {
+    name.bytes().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
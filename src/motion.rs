@@ -0,0 +1,68 @@
+use crate::texture::holds_image;
+use crate::{Camera, Scene, Vec3};
+use wasm_bindgen::prelude::*;
+
+// The camera and sphere positions that motion vectors are measured against.
+pub struct MotionReference {
+    camera: Camera,
+    centers: Vec<Vec3>,
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Writes a 2D motion vector for every pixel of a `width` × `height` frame
+    // into `out`, as interleaved (x, y) pairs. Each vector is the distance in
+    // pixels that the surface seen through the pixel has moved since the
+    // previous call, taking into account both the camera and the spheres.
+    // Pixels showing the background, or surfaces that were behind the camera,
+    // get a zero vector, as do spheres added since the previous call. The
+    // first call measures against the current frame. Buffers too small to
+    // hold the whole frame are left untouched.
+    #[wasm_bindgen(js_name = renderMotionVectors)]
+    pub fn render_motion_vectors(&mut self, width: usize, height: usize, out: &mut [f32]) {
+        if !holds_image(out.len(), width, height, 2) {
+            return;
+        }
+        self.fit_film(width, height);
+
+        let reference = self
+            .motion_reference
+            .take()
+            .unwrap_or_else(|| MotionReference {
+                camera: self.camera,
+                centers: self.spheres.iter().map(|s| s.center).collect(),
+            });
+
+        let visible = self.visible_spheres();
+        let (w, h) = (width as f64, height as f64);
+
+        for y in 0..height {
+            for x in 0..width {
+                let (u, v) = (x as f64 / w, y as f64 / h);
+                let ray = self.camera.cast(u, v);
+
+                let motion = visible.nearest(&ray, &self.stats).and_then(|(i, t)| {
+                    // Carry the point along with its sphere back to where it
+                    // was, then find where the old camera saw it. Spheres
+                    // that weren't there yet are taken to have stood still.
+                    let offset = reference.centers.get(i).map_or(Vec3::new(0., 0., 0.), |c| {
+                        self.spheres[i].center.subtract(c)
+                    });
+                    let before = ray.point_at(t).subtract(&offset);
+                    let (u0, v0) = reference.camera.project(&before)?;
+                    Some(((u - u0) * w, (v - v0) * h))
+                });
+
+                let (dx, dy) = motion.unwrap_or((0., 0.));
+                let idx = 2 * (y * width + x);
+                out[idx] = dx as f32;
+                out[idx + 1] = dy as f32;
+            }
+        }
+
+        self.motion_reference = Some(MotionReference {
+            camera: self.camera,
+            centers: self.spheres.iter().map(|s| s.center).collect(),
+        });
+    }
+}
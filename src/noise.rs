@@ -0,0 +1,54 @@
+use crate::Vec3;
+
+// Smooth 3D value noise, for procedural textures: random values at the corners
+// of a unit lattice, blended smoothly in between. It is deterministic, so the
+// same point always gets the same value.
+
+// Hashes a lattice point to a value in [-1, 1].
+fn lattice(x: i64, y: i64, z: i64) -> f64 {
+    let mut h = (x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ (y as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f)
+        ^ (z as u64).wrapping_mul(0x1656_67b1_9e37_79f9);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h ^= h >> 33;
+    (h >> 11) as f64 / (1u64 << 52) as f64 - 1.
+}
+
+// Returns the noise at `p`, in [-1, 1].
+pub fn noise(p: &Vec3) -> f64 {
+    let (x0, y0, z0) = (p.x.floor(), p.y.floor(), p.z.floor());
+    let fade = |t: f64| t * t * (3. - 2. * t);
+    let (u, v, w) = (fade(p.x - x0), fade(p.y - y0), fade(p.z - z0));
+    let (x0, y0, z0) = (x0 as i64, y0 as i64, z0 as i64);
+
+    let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+    let corner = |dx: i64, dy: i64, dz: i64| lattice(x0 + dx, y0 + dy, z0 + dz);
+
+    lerp(
+        lerp(
+            lerp(corner(0, 0, 0), corner(1, 0, 0), u),
+            lerp(corner(0, 1, 0), corner(1, 1, 0), u),
+            v,
+        ),
+        lerp(
+            lerp(corner(0, 0, 1), corner(1, 0, 1), u),
+            lerp(corner(0, 1, 1), corner(1, 1, 1), u),
+            v,
+        ),
+        w,
+    )
+}
+
+// Sums octaves of noise at doubling frequencies and halving amplitudes, which
+// gives the ragged, self-similar look of natural materials. The result is
+// roughly in [0, 1].
+pub fn turbulence(p: &Vec3, octaves: u32) -> f64 {
+    let mut sum = 0.;
+    let mut scale = 1.;
+    for _ in 0..octaves {
+        sum += noise(&p.scale(scale)).abs() / scale;
+        scale *= 2.;
+    }
+    sum
+}
@@ -0,0 +1,61 @@
+use crate::material::{cosine_weighted, Hit};
+use crate::region::Region;
+use crate::rng::Rng;
+use crate::stats::Counters;
+use crate::{Ray, Scene};
+use wasm_bindgen::prelude::*;
+
+// Ambient light reaches into the corners and crevices of a scene as readily
+// as anywhere else, which makes objects look as if they float above whatever
+// they rest on. Ambient occlusion darkens it wherever nearby geometry hides
+// part of the sky, by casting rays over the hemisphere above each point and
+// counting how many of them escape within a short radius. It adds the contact
+// shadows where objects meet.
+#[derive(Copy, Clone)]
+pub struct Occlusion {
+    // How far away geometry can be and still occlude a point.
+    radius: f64,
+    samples: usize,
+}
+
+impl Default for Occlusion {
+    fn default() -> Self {
+        Self {
+            radius: 1.,
+            samples: 16,
+        }
+    }
+}
+
+impl Scene {
+    // The fraction of the ambient light that reaches `hit`, weighted by the
+    // cosine of the angle it arrives at, as a diffuse surface sees it.
+    pub(crate) fn openness(&self, hit: &Hit, occlusion: &Occlusion, rng: &mut Rng) -> f64 {
+        Counters::bump(&self.stats.shadow_rays, occlusion.samples as u64);
+        let open = (0..occlusion.samples)
+            .filter(|_| {
+                let ray = Ray::new(hit.point, cosine_weighted(&hit.normal, rng).unit());
+                !self.occluders.occluded(&ray, occlusion.radius, &self.stats)
+            })
+            .count();
+        open as f64 / occlusion.samples as f64
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Darkens ambient light where geometry within `radius` of a surface hides
+    // part of the sky from it, found by casting `samples` rays from each
+    // point. It also sets how the AmbientOcclusion integrator renders. A
+    // radius or sample count of zero turns it off.
+    #[wasm_bindgen(js_name = setAmbientOcclusion)]
+    pub fn set_ambient_occlusion(&mut self, radius: f64, samples: usize) {
+        self.occlusion = if radius > 0. && samples > 0 {
+            Some(Occlusion { radius, samples })
+        } else {
+            None
+        };
+        self.forget_irradiance();
+        self.dirty = Some(Region::full());
+    }
+}
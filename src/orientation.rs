@@ -0,0 +1,217 @@
+use crate::region::Region;
+use crate::{Camera, Scene, Vec3};
+use wasm_bindgen::prelude::*;
+
+// Which way the camera faces. The film and everything drawn over it are laid
+// out in the camera's own frame of reference, so turning it only changes the
+// three directions that frame is built from.
+
+impl Camera {
+    // Turns the camera to face `target`, keeping the horizon level, and
+    // centers the film on it. A target at the eye is ignored. Looking
+    // straight up or down, the film keeps running across the way it did.
+    pub(crate) fn look_at(&mut self, target: &Vec3) {
+        let towards = target.subtract(&self.eye);
+        if towards.length_sqr() == 0. {
+            return;
+        }
+        let forward = towards.unit();
+
+        let mut right = Vec3::new(0., 1., 0.).cross(&forward);
+        if right.length_sqr() < 1e-12 {
+            right = self
+                .right
+                .subtract(&forward.scale(self.right.dot(&forward)));
+        }
+        if right.length_sqr() < 1e-12 {
+            right = Vec3::new(1., 0., 0.);
+        }
+
+        self.pivot = *target;
+        self.right = right.unit();
+        self.forward = forward;
+        self.up = forward.cross(&self.right);
+        self.film.origin.x = -self.film.width / 2.;
+        self.film.origin.y = -self.film.height / 2.;
+    }
+
+    // Turns the camera about its own axes, by angles in radians: `yaw` to the
+    // right about its up direction, then `pitch` upwards about its right
+    // direction, then `roll` clockwise about its forward direction, as seen
+    // from behind. The pivot turns with it, keeping its distance.
+    pub(crate) fn rotate(&mut self, yaw: f64, pitch: f64, roll: f64) {
+        let distance = self.pivot.subtract(&self.eye).length();
+
+        self.right = rotate(&self.right, &self.up, yaw);
+        self.forward = rotate(&self.forward, &self.up, yaw);
+        self.up = rotate(&self.up, &self.right, -pitch);
+        self.forward = rotate(&self.forward, &self.right, -pitch);
+        self.right = rotate(&self.right, &self.forward, -roll);
+        self.up = rotate(&self.up, &self.forward, -roll);
+        self.settle(distance);
+    }
+
+    // Turns the camera the way first-person games do: `yaw` radians to the
+    // right about the vertical, then `pitch` radians downwards about its
+    // right direction, stopping just short of straight up or down.
+    pub(crate) fn look_around(&mut self, yaw: f64, pitch: f64) {
+        let distance = self.pivot.subtract(&self.eye).length();
+
+        let vertical = Vec3::new(0., 1., 0.);
+        self.right = rotate(&self.right, &vertical, yaw);
+        self.up = rotate(&self.up, &vertical, yaw);
+        self.forward = rotate(&self.forward, &vertical, yaw);
+
+        let limit = MAX_ELEVATION.to_radians();
+        let elevation = self.forward.y.clamp(-1., 1.).asin();
+        let pitch = elevation - (elevation - pitch).clamp(-limit, limit);
+        self.up = rotate(&self.up, &self.right, pitch);
+        self.forward = rotate(&self.forward, &self.right, pitch);
+        self.settle(distance);
+    }
+
+    // Squares up the camera's directions after turning it, keeping rounding
+    // errors from building up over many small turns, and puts its pivot back
+    // the given distance in front of it.
+    fn settle(&mut self, distance: f64) {
+        self.forward = self.forward.unit();
+        self.right = self.up.cross(&self.forward).unit();
+        self.up = self.forward.cross(&self.right);
+
+        self.pivot = self.eye.add(&self.forward.scale(distance));
+    }
+}
+
+// How close dollying may bring the camera to its pivot, in metres.
+const MIN_PIVOT_DISTANCE: f64 = 0.1;
+
+// How near the camera may orbit to straight above or below its pivot, in
+// degrees, where which way is right stops being well defined.
+const MAX_ELEVATION: f64 = 89.;
+
+// Rotates `v` by `angle` radians about the unit vector `axis`, after Rodrigues,
+// anticlockwise when looking back along the axis.
+fn rotate(v: &Vec3, axis: &Vec3, angle: f64) -> Vec3 {
+    let (sin, cos) = angle.sin_cos();
+    v.scale(cos)
+        .add(&axis.cross(v).scale(sin))
+        .add(&axis.scale(axis.dot(v) * (1. - cos)))
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Turns the camera to face the given point, with it in the middle of the
+    // frame and the horizon level.
+    #[wasm_bindgen(js_name = lookAt)]
+    pub fn look_at(&mut self, x: f64, y: f64, z: f64) {
+        self.camera.look_at(&Vec3::new(x, y, z));
+        self.invalidate(Region::full());
+    }
+
+    // Swings the camera around its pivot, keeping its distance, by `theta`
+    // degrees to its right and `phi` degrees upwards, and turns it to face
+    // the pivot. The pivot is the point last looked at, or the middle of the
+    // default scene. It stops just short of straight above or below.
+    pub fn orbit(&mut self, theta: f64, phi: f64) {
+        if !theta.is_finite() || !phi.is_finite() {
+            return;
+        }
+
+        let camera = &mut self.camera;
+        let offset = camera.eye.subtract(&camera.pivot);
+        let distance = offset.length();
+        if distance == 0. {
+            return;
+        }
+
+        let azimuth = offset.x.atan2(offset.z) - theta.to_radians();
+        let limit = MAX_ELEVATION.to_radians();
+        let elevation = ((offset.y / distance).asin() + phi.to_radians()).clamp(-limit, limit);
+        let offset = Vec3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            elevation.cos() * azimuth.cos(),
+        );
+        let pivot = camera.pivot;
+        camera.eye = pivot.add(&offset.scale(distance));
+        camera.look_at(&pivot);
+
+        self.follow_camera();
+        self.invalidate(Region::full());
+    }
+
+    // Slides the camera and its pivot by `dx` to its right and `dy` upwards,
+    // in metres, without turning it.
+    pub fn pan(&mut self, dx: f64, dy: f64) {
+        if !dx.is_finite() || !dy.is_finite() {
+            return;
+        }
+
+        let camera = &mut self.camera;
+        let offset = camera.right.scale(dx).add(&camera.up.scale(dy));
+        camera.translate(&offset);
+
+        self.follow_camera();
+        self.invalidate(Region::full());
+    }
+
+    // Moves the camera `dz` metres towards its pivot, or away from it for
+    // negative distances, stopping short of reaching it.
+    pub fn dolly(&mut self, dz: f64) {
+        let camera = &mut self.camera;
+        let offset = camera.eye.subtract(&camera.pivot);
+        let distance = offset.length();
+        if distance == 0. || !dz.is_finite() {
+            return;
+        }
+
+        let closer = (distance - dz).max(MIN_PIVOT_DISTANCE);
+        camera.eye = camera.pivot.add(&offset.scale(closer / distance));
+
+        self.follow_camera();
+        self.invalidate(Region::full());
+    }
+
+    // Turns the camera the given number of degrees to its right, about its
+    // own up direction, or to its left for negative angles.
+    pub fn yaw(&mut self, degrees: f64) {
+        self.rotate_camera(degrees, 0., 0.);
+    }
+
+    // Tilts the camera the given number of degrees upwards, about its own
+    // right direction, or downwards for negative angles.
+    pub fn pitch(&mut self, degrees: f64) {
+        self.rotate_camera(0., degrees, 0.);
+    }
+
+    // Rolls the camera the given number of degrees clockwise, as seen from
+    // behind, about the direction it faces.
+    pub fn roll(&mut self, degrees: f64) {
+        self.rotate_camera(0., 0., degrees);
+    }
+
+    // Turns the camera by mouse movements, in radians: `dx` to the right
+    // about the vertical and `dy` downwards, as pointer-lock reports them.
+    // The camera stops just short of looking straight up or down.
+    #[wasm_bindgen(js_name = rotateView)]
+    pub fn rotate_view(&mut self, dx: f64, dy: f64) {
+        if !dx.is_finite() || !dy.is_finite() {
+            return;
+        }
+
+        self.camera.look_around(dx, dy);
+        self.invalidate(Region::full());
+    }
+}
+
+impl Scene {
+    fn rotate_camera(&mut self, yaw: f64, pitch: f64, roll: f64) {
+        if !yaw.is_finite() || !pitch.is_finite() || !roll.is_finite() {
+            return;
+        }
+
+        self.camera
+            .rotate(yaw.to_radians(), pitch.to_radians(), roll.to_radians());
+        self.invalidate(Region::full());
+    }
+}
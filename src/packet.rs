@@ -0,0 +1,123 @@
+use crate::accel::Accel;
+use crate::spheres::{nearest_root, SphereSet};
+use crate::stats::Counters;
+use crate::Ray;
+
+pub const PACKET_SIZE: usize = 4;
+
+// A bundle of rays traced together against the scene. Neighbouring primary
+// rays tend to hit the same objects, so testing them side by side keeps each
+// sphere's data in cache, and the structure-of-arrays layout lets the
+// per-lane arithmetic below be auto-vectorised.
+pub struct RayPacket {
+    ox: [f64; PACKET_SIZE],
+    oy: [f64; PACKET_SIZE],
+    oz: [f64; PACKET_SIZE],
+    dx: [f64; PACKET_SIZE],
+    dy: [f64; PACKET_SIZE],
+    dz: [f64; PACKET_SIZE],
+    // The rays themselves, for walking an index over the spheres.
+    rays: [Ray; PACKET_SIZE],
+}
+
+impl RayPacket {
+    pub fn new(rays: &[Ray; PACKET_SIZE]) -> Self {
+        let mut packet = Self {
+            ox: [0.; PACKET_SIZE],
+            oy: [0.; PACKET_SIZE],
+            oz: [0.; PACKET_SIZE],
+            dx: [0.; PACKET_SIZE],
+            dy: [0.; PACKET_SIZE],
+            dz: [0.; PACKET_SIZE],
+            rays: *rays,
+        };
+
+        for (i, ray) in rays.iter().enumerate() {
+            packet.ox[i] = ray.origin.x;
+            packet.oy[i] = ray.origin.y;
+            packet.oz[i] = ray.origin.z;
+            packet.dx[i] = ray.direction.x;
+            packet.dy[i] = ray.direction.y;
+            packet.dz[i] = ray.direction.z;
+        }
+
+        packet
+    }
+
+    // Finds the nearest sphere hit by each ray in the packet, returning its
+    // position in the scene's list of spheres along with the distance to it.
+    pub fn nearest(
+        &self,
+        spheres: &SphereSet,
+        stats: &Counters,
+    ) -> [Option<(usize, f64)>; PACKET_SIZE] {
+        if let Some(accel) = &spheres.accel {
+            return self.nearest_indexed(spheres, accel, stats);
+        }
+
+        let tests = PACKET_SIZE * spheres.index.len();
+        Counters::bump(&stats.intersection_tests, tests as u64);
+
+        let mut nearest = [None; PACKET_SIZE];
+        let mut min_t = [f64::INFINITY; PACKET_SIZE];
+
+        for j in 0..spheres.index.len() {
+            let (cx, cy, cz) = (spheres.cx[j], spheres.cy[j], spheres.cz[j]);
+            let radius_sqr = spheres.radius[j] * spheres.radius[j];
+            let mut t = [f64::INFINITY; PACKET_SIZE];
+
+            for (i, t) in t.iter_mut().enumerate() {
+                let ocx = self.ox[i] - cx;
+                let ocy = self.oy[i] - cy;
+                let ocz = self.oz[i] - cz;
+
+                let dot = self.dx[i] * ocx + self.dy[i] * ocy + self.dz[i] * ocz;
+                let c = ocx * ocx + ocy * ocy + ocz * ocz - radius_sqr;
+                *t = nearest_root(dot, c);
+            }
+
+            for i in 0..PACKET_SIZE {
+                if t[i] < min_t[i] {
+                    min_t[i] = t[i];
+                    nearest[i] = Some((spheres.index[j], t[i]));
+                }
+            }
+        }
+
+        nearest
+    }
+    // Like `nearest`, but only testing each ray against the spheres in the
+    // parts of the set's index that it passes through.
+    fn nearest_indexed(
+        &self,
+        spheres: &SphereSet,
+        accel: &Accel,
+        stats: &Counters,
+    ) -> [Option<(usize, f64)>; PACKET_SIZE] {
+        let mut nearest = [None; PACKET_SIZE];
+        let mut min_t = [f64::INFINITY; PACKET_SIZE];
+
+        accel.traverse_packet(&self.rays, stats, |items, lanes| {
+            for (lane, ray) in self.rays.iter().enumerate() {
+                if !lanes[lane] {
+                    continue;
+                }
+                Counters::bump(&stats.intersection_tests, items.len() as u64);
+                for &j in items {
+                    let t = spheres.distance(j, ray);
+                    if t < min_t[lane] {
+                        min_t[lane] = t;
+                        nearest[lane] = Some(j);
+                    }
+                }
+            }
+            min_t
+        });
+
+        let mut hits = [None; PACKET_SIZE];
+        for (lane, hit) in hits.iter_mut().enumerate() {
+            *hit = nearest[lane].map(|j| (spheres.index[j], min_t[lane]));
+        }
+        hits
+    }
+}
@@ -0,0 +1,75 @@
+use crate::region::Region;
+use crate::{Camera, Scene, Vec3};
+use std::f64::consts::PI;
+use wasm_bindgen::prelude::*;
+
+// How the camera maps directions onto the image. Besides the usual flat film,
+// it can spread a hemisphere across a circle, or the whole sphere of
+// directions across the frame, for domes and 360° viewers. Neither of those
+// maps straight lines to straight lines, so overlays and motion vectors are
+// only drawn with the flat film, and depth of field is ignored.
+#[wasm_bindgen]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum CameraModel {
+    // A pinhole camera, looking through the film.
+    Perspective,
+    // An angular fisheye: the angle from the direction the camera faces grows
+    // in proportion to the distance from the middle of the image, reaching
+    // 90° at the edges of its shorter side, for a 180° circle.
+    Fisheye,
+    // A full 360° panorama, with longitude across and latitude down, as VR
+    // viewers expect. Render it into an image twice as wide as it is tall.
+    Equirectangular,
+}
+
+impl Camera {
+    // The direction of the ray through the given film coordinates, in the
+    // camera's frame of reference. It isn't necessarily of unit length.
+    pub(crate) fn direction_through(&self, x: f64, y: f64) -> Vec3 {
+        match self.model {
+            CameraModel::Perspective => self.film.project(x, y),
+            CameraModel::Fisheye => {
+                let (half_width, half_height) = self.fisheye_extent();
+                let nx = (2. * x - 1.) * half_width;
+                let ny = (1. - 2. * y) * half_height;
+                let theta = (nx.hypot(ny) * PI / 2.).min(PI);
+                let phi = ny.atan2(nx);
+                Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                )
+            }
+            CameraModel::Equirectangular => {
+                let longitude = 2. * PI * (x - 0.5);
+                let latitude = PI * (0.5 - y);
+                Vec3::new(
+                    latitude.cos() * longitude.sin(),
+                    latitude.sin(),
+                    latitude.cos() * longitude.cos(),
+                )
+            }
+        }
+    }
+
+    // How far the image reaches from its middle, across and up, in units of
+    // the fisheye's circle, which fits the shorter side.
+    pub(crate) fn fisheye_extent(&self) -> (f64, f64) {
+        let aspect = self.film.width / self.film.height;
+        if aspect >= 1. {
+            (aspect, 1.)
+        } else {
+            (1., 1. / aspect)
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Switches between a flat film, a fisheye and a 360° panorama.
+    #[wasm_bindgen(js_name = setCameraModel)]
+    pub fn set_camera_model(&mut self, model: CameraModel) {
+        self.camera.model = model;
+        self.invalidate(Region::full());
+    }
+}
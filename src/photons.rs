@@ -0,0 +1,308 @@
+use crate::holdout::Footprint;
+use crate::material::{basis, pick};
+use crate::rng::Rng;
+use crate::stats::Counters;
+use crate::{Image, Ray, Scene, Sphere, Vec3, RGB};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+// Progressive photon mapping (Hachisuka et al., 2008), used to add caustics
+// (light focused onto diffuse surfaces by glass or mirrors) on top of what the
+// path tracer renders. The path tracer can't find these: its rays can never
+// hit a point light, so light reaching a surface by way of a specular bounce
+// goes missing.
+//
+// Each pixel's view ray is followed through any specular surfaces until it
+// lands on a diffuse one, where a hit point is recorded. Then, on every frame,
+// photons are shot from the lights through the specular surfaces, and each
+// hit point collects those landing within its radius. The radius shrinks a
+// little with every frame, so that the caustics get sharper as they converge.
+
+// The number of photons shot from each light towards each specular sphere, on
+// every frame.
+const PHOTONS_PER_PASS: usize = 10_000;
+
+// The radius that hit points start out collecting photons from.
+const INITIAL_RADIUS: f64 = 0.25;
+
+// The fraction of newly collected photons kept on every frame, which controls
+// how quickly the radius shrinks.
+const ALPHA: f64 = 0.7;
+
+// Paths through specular surfaces are cut short after this many bounces.
+const MAX_BOUNCES: usize = 8;
+
+struct HitPoint {
+    pixel: usize,
+    position: Vec3,
+    normal: Vec3,
+    // The fraction of the light leaving the hit point that reaches the pixel,
+    // after all the specular bounces in between.
+    weight: RGB,
+    radius_sqr: f64,
+    photons: f64,
+    flux: RGB,
+    // Photons collected on the current frame.
+    new_photons: f64,
+    new_flux: RGB,
+}
+
+pub struct PhotonMap {
+    width: usize,
+    height: usize,
+    // The path-traced color and opacity of each pixel, which caustics are
+    // added to.
+    base: Vec<(RGB, f64)>,
+    hit_points: Vec<HitPoint>,
+    // Hit points indexed by the cells of a uniform grid that they overlap,
+    // so that each photon only needs checking against those nearby.
+    grid: HashMap<(i64, i64, i64), Vec<usize>>,
+    passes: u32,
+}
+
+impl PhotonMap {
+    // The number of rounds of photons shot since the hit points were traced.
+    pub(crate) fn passes(&self) -> u32 {
+        self.passes
+    }
+}
+
+fn cell(p: &Vec3) -> (i64, i64, i64) {
+    let size = 2. * INITIAL_RADIUS;
+    (
+        (p.x / size).floor() as i64,
+        (p.y / size).floor() as i64,
+        (p.z / size).floor() as i64,
+    )
+}
+
+impl Scene {
+    // Renders the next frame of progressive photon mapping. The hit points are
+    // only traced again when the scene has changed since the previous frame;
+    // otherwise, this shoots another round of photons, and refines the frame.
+    pub(crate) fn render_photon_pass(&mut self, img: &mut Image) {
+        let mut map = match self.photon_map.take() {
+            Some(map)
+                if self.dirty.is_none() && map.width == img.width && map.height == img.height =>
+            {
+                map
+            }
+            _ => self.trace_hit_points(img.width, img.height),
+        };
+
+        self.trace_photons(&mut map);
+
+        for hp in &mut map.hit_points {
+            if hp.new_photons > 0. {
+                let photons = hp.photons + ALPHA * hp.new_photons;
+                let ratio = photons / (hp.photons + hp.new_photons);
+                hp.radius_sqr *= ratio;
+                hp.flux = hp.flux.add(&hp.new_flux).scale(ratio);
+                hp.photons = photons;
+                hp.new_photons = 0.;
+                hp.new_flux = RGB::black();
+            }
+        }
+        map.passes += 1;
+
+        let mut colors = map.base.clone();
+        for hp in &map.hit_points {
+            let caustic = hp.flux.scale(1. / (PI * hp.radius_sqr * map.passes as f64));
+            colors[hp.pixel].0 = colors[hp.pixel].0.add(&hp.weight.tint(&caustic));
+        }
+
+        for (i, (color, alpha)) in colors.iter().enumerate() {
+            img.draw_alpha(i % img.width, i / img.width, color, *alpha);
+        }
+
+        self.photon_map = Some(map);
+    }
+
+    fn trace_hit_points(&self, width: usize, height: usize) -> PhotonMap {
+        let mut map = PhotonMap {
+            width,
+            height,
+            base: Vec::with_capacity(width * height),
+            hit_points: vec![],
+            grid: HashMap::new(),
+            passes: 0,
+        };
+
+        let height_inv = 1. / height as f64;
+        let width_inv = 1. / width as f64;
+        let visible = self.visible_spheres();
+
+        for y in 0..height {
+            for x in 0..width {
+                let mut rng = Rng::for_pixel(x, y);
+                let mut ray = self
+                    .camera
+                    .cast(x as f64 * width_inv, y as f64 * height_inv);
+                Counters::bump(&self.stats.primary_rays, 1);
+                let nearest = self.nearest_visible(&visible, &ray);
+                let pixel = Footprint::new(x, y, width_inv, height_inv);
+                map.base
+                    .push(self.shade_pixel(&visible, pixel, &ray, nearest, &mut rng));
+
+                let mut weight = RGB::white();
+                for bounce in 0..MAX_BOUNCES {
+                    let nearest = if bounce == 0 {
+                        self.nearest_visible(&visible, &ray)
+                    } else {
+                        self.nearest(&ray)
+                    };
+                    let (sphere, t) = match nearest {
+                        Some((i, t)) => (&self.spheres[i], t),
+                        None => break,
+                    };
+
+                    let hit = sphere.hit(ray.point_at(t), 0., t * self.pixel_spread.get());
+
+                    if let Some(albedo) = sphere.material.diffuse(&hit) {
+                        map.hit_points.push(HitPoint {
+                            pixel: y * width + x,
+                            position: hit.point,
+                            normal: hit.normal,
+                            weight: weight.tint(&albedo),
+                            radius_sqr: INITIAL_RADIUS * INITIAL_RADIUS,
+                            photons: 0.,
+                            flux: RGB::black(),
+                            new_photons: 0.,
+                            new_flux: RGB::black(),
+                        });
+                        break;
+                    }
+
+                    let scattered = sphere.material.scatter(&ray, &hit, &mut rng);
+                    match pick(&scattered, &mut rng) {
+                        Some((next, w)) => {
+                            ray = next;
+                            weight = weight.tint(&w);
+                        }
+                        None => break,
+                    }
+                }
+            }
+        }
+
+        for (i, hp) in map.hit_points.iter().enumerate() {
+            let r = INITIAL_RADIUS;
+            let (x0, y0, z0) = cell(&hp.position.subtract(&Vec3::new(r, r, r)));
+            let (x1, y1, z1) = cell(&hp.position.add(&Vec3::new(r, r, r)));
+            for cx in x0..=x1 {
+                for cy in y0..=y1 {
+                    for cz in z0..=z1 {
+                        map.grid.entry((cx, cy, cz)).or_default().push(i);
+                    }
+                }
+            }
+        }
+
+        map
+    }
+
+    fn trace_photons(&self, map: &mut PhotonMap) {
+        let mut rng = Rng::new(map.passes as u64);
+        self.shoot_photons(PHOTONS_PER_PASS, &mut rng, &mut |point, normal, flux| {
+            deposit(map, point, normal, flux)
+        });
+    }
+
+    // Shoots `count` photons from each light towards each specular sphere,
+    // and hands those that land on a diffuse surface by way of it to
+    // `deposit`, along with the point and normal where they landed.
+    pub(crate) fn shoot_photons(&self, count: usize, rng: &mut Rng, deposit: &mut Deposit) {
+        let specular: Vec<&Sphere> = self
+            .spheres
+            .iter()
+            .filter(|s| s.resident && s.material.diffuse(&s.hit(s.top(), 0., 0.)).is_none())
+            .collect();
+
+        for light in &self.lights {
+            for sphere in &specular {
+                // Aim photons only at the cone of directions in which the
+                // sphere lies, and scale down their power to match.
+                let axis = sphere.center.subtract(&light.pos);
+                let dist_sqr = axis.length_sqr();
+                let radius_sqr = sphere.radius * sphere.radius;
+                if dist_sqr <= radius_sqr {
+                    continue;
+                }
+
+                let cos_max = (1. - radius_sqr / dist_sqr).sqrt();
+                let solid_angle = 2. * PI * (1. - cos_max);
+                let power = light.power * self.response() * solid_angle / (4. * PI) / count as f64;
+                let axis = axis.unit();
+                let (tangent, bitangent) = basis(&axis);
+
+                for _ in 0..count {
+                    let cos_theta = 1. - rng.next_f64() * (1. - cos_max);
+                    let sin_theta = (1. - cos_theta * cos_theta).sqrt();
+                    let phi = 2. * PI * rng.next_f64();
+                    let direction = tangent
+                        .scale(sin_theta * phi.cos())
+                        .add(&bitangent.scale(sin_theta * phi.sin()))
+                        .add(&axis.scale(cos_theta));
+
+                    let power = power * light.beam.falloff(&direction);
+                    if power <= 0. {
+                        continue;
+                    }
+
+                    let ray = Ray::new(light.pos, direction);
+                    let flux = light.color.scale(power);
+                    self.trace_photon(ray, flux, rng, deposit);
+                }
+            }
+        }
+    }
+
+    // Follows a photon through specular surfaces, and deposits it on the first
+    // diffuse surface it reaches. Photons that reach one straight from the
+    // light are dropped, since the path tracer already accounts for them.
+    fn trace_photon(&self, mut ray: Ray, mut flux: RGB, rng: &mut Rng, deposit: &mut Deposit) {
+        for bounce in 0..MAX_BOUNCES {
+            let (sphere, t) = match self.nearest(&ray) {
+                Some((i, t)) => (&self.spheres[i], t),
+                None => return,
+            };
+
+            let hit = sphere.hit(ray.point_at(t), 0., 0.);
+
+            if sphere.material.diffuse(&hit).is_some() {
+                if bounce > 0 {
+                    deposit(&hit.point, &hit.normal, &flux);
+                }
+                return;
+            }
+
+            let scattered = sphere.material.scatter(&ray, &hit, rng);
+            match pick(&scattered, rng) {
+                Some((next, w)) => {
+                    ray = next;
+                    flux = flux.tint(&w);
+                }
+                None => return,
+            }
+        }
+    }
+}
+
+// Stores a photon that landed on a diffuse surface, given the point and
+// normal where it landed, and its flux.
+pub type Deposit<'a> = dyn FnMut(&Vec3, &Vec3, &RGB) + 'a;
+
+fn deposit(map: &mut PhotonMap, point: &Vec3, normal: &Vec3, flux: &RGB) {
+    let hit_points = &mut map.hit_points;
+    if let Some(indices) = map.grid.get(&cell(point)) {
+        for &i in indices {
+            let hp = &mut hit_points[i];
+            if hp.normal.dot(normal) > 0.5
+                && hp.position.subtract(point).length_sqr() <= hp.radius_sqr
+            {
+                hp.new_photons += 1.;
+                hp.new_flux = hp.new_flux.add(flux);
+            }
+        }
+    }
+}
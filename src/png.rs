@@ -0,0 +1,102 @@
+// A minimal PNG encoder, for handing finished images to the host as files.
+// Pixels are stored with zlib's uncompressed blocks rather than deflated, so
+// files come out large, but there's no need to pull in a compression library.
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+// The most bytes that an uncompressed deflate block can hold.
+const MAX_STORED_BLOCK: usize = 0xFFFF;
+
+// Encodes `width` × `height` pixels, given as RGBA bytes one row after
+// another, as an 8-bit PNG. Each of `text` is written as a tEXt chunk, whose
+// keyword and value should be Latin-1.
+pub fn encode(width: usize, height: usize, rgba: &[u8], text: &[(&str, String)]) -> Vec<u8> {
+    let mut png = SIGNATURE.to_vec();
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bits per channel, RGBA, deflate, adaptive filtering and no interlacing.
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+    chunk(&mut png, b"IHDR", &header);
+
+    // Pixels are written in sRGB with a perceptual rendering intent.
+    chunk(&mut png, b"sRGB", &[0]);
+
+    for (keyword, value) in text {
+        let mut data = keyword.as_bytes().to_vec();
+        data.push(0);
+        data.extend_from_slice(value.as_bytes());
+        chunk(&mut png, b"tEXt", &data);
+    }
+
+    // Every row starts with the type of filter applied to it, which is none.
+    let stride = width * 4;
+    let mut raw = Vec::with_capacity((stride + 1) * height);
+    for row in rgba.chunks(stride.max(1)).take(height) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+    chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+// Wraps `data` in a zlib stream made of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // A 32K window and the default compression level, which is only a hint.
+    let mut out = vec![0x78, 0x01];
+
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(last as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // Summing up to 5552 bytes at a time can't overflow before reducing.
+    for block in data.chunks(5552) {
+        for &byte in block {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
@@ -0,0 +1,146 @@
+use crate::material::{basis, Hit};
+use crate::region::Region;
+use crate::rng::Rng;
+use crate::stats::Counters;
+use crate::{Ray, Scene, Vec3, RGB};
+use std::f64::consts::PI;
+use wasm_bindgen::prelude::*;
+
+// Portals: rectangles marking the windows and doorways through which the
+// environment lights an interior. Otherwise, light from the sky only reaches
+// a room when a ray scattered off a wall happens to leave through a window,
+// which is rare enough to bury the room in noise. Instead, at every diffuse
+// bounce, a point on one of the portals is picked, in proportion to their
+// areas, and a shadow ray is cast through it towards the sky. Rays scattered
+// off the surface then leave out the sky they find through a portal, so that
+// it isn't counted twice. Portals are invisible, and block nothing; they
+// shouldn't overlap, or the sky seen through both would be counted twice.
+pub struct Portal {
+    center: Vec3,
+    // The rectangle's edges, at right angles to each other.
+    width: Vec3,
+    height: Vec3,
+}
+
+impl Portal {
+    fn area(&self) -> f64 {
+        self.width.cross(&self.height).length()
+    }
+
+    // Whether `ray` passes through the portal.
+    fn crossed_by(&self, ray: &Ray) -> bool {
+        let normal = self.width.cross(&self.height);
+        let facing = normal.dot(&ray.direction);
+        if facing == 0. {
+            return false;
+        }
+
+        let t = normal.dot(&self.center.subtract(&ray.origin)) / facing;
+        if t <= 0. {
+            return false;
+        }
+
+        let offset = ray.point_at(t).subtract(&self.center);
+        let u = offset.dot(&self.width) / self.width.length_sqr();
+        let v = offset.dot(&self.height) / self.height.length_sqr();
+        u.abs() <= 0.5 && v.abs() <= 0.5
+    }
+}
+
+impl Scene {
+    // Samples the light from the environment that comes through the portals
+    // onto the diffuse surface at `hit`, as rendered color values, like those
+    // of scattered light.
+    pub(crate) fn sample_portals(&self, hit: &Hit, rng: &mut Rng) -> RGB {
+        let total: f64 = self.portals.iter().map(Portal::area).sum();
+        if total <= 0. {
+            return RGB::black();
+        }
+
+        // Pick one of the portals, in proportion to its area.
+        let mut pick = rng.next_f64() * total;
+        let portal = self
+            .portals
+            .iter()
+            .find(|p| {
+                pick -= p.area();
+                pick < 0.
+            })
+            .unwrap_or(&self.portals[self.portals.len() - 1]);
+
+        // Then a point on it, uniformly.
+        let (u, v) = (rng.next_f64(), rng.next_f64());
+        let point = portal
+            .center
+            .add(&portal.width.scale(u - 0.5))
+            .add(&portal.height.scale(v - 0.5));
+
+        let (ray, distance) = Ray::cast(&hit.point, &point);
+        let cosine = hit.normal.dot(&ray.direction);
+        let normal = portal.width.cross(&portal.height).unit();
+        let facing = normal.dot(&ray.direction).abs();
+        if cosine <= 0. || facing <= 0. {
+            return RGB::black();
+        }
+
+        Counters::bump(&self.stats.shadow_rays, 1);
+        if self.occluders.occluded(&ray, f64::INFINITY, &self.stats) {
+            return RGB::black();
+        }
+
+        // Picking points evenly over the portals, by area, picks directions
+        // with this probability per unit solid angle.
+        let probability = distance * distance / (total * facing);
+        self.transmittance_along(&ray, f64::INFINITY)
+            .tint(&self.background(&ray.direction))
+            .scale(cosine / (PI * probability))
+    }
+
+    // Whether `ray`, which escaped the scene, left it through a portal.
+    pub(crate) fn through_portal(&self, ray: &Ray) -> bool {
+        self.portals.iter().any(|portal| portal.crossed_by(ray))
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Adds a portal centered on the given point, and returns its index. It
+    // has no area, and lets nothing through, until `setPortalRect` sizes it.
+    #[wasm_bindgen(js_name = addPortal)]
+    pub fn add_portal(&mut self, x: f64, y: f64, z: f64) -> usize {
+        let none = Vec3::new(0., 0., 0.);
+        self.portals.push(Portal {
+            center: Vec3::new(x, y, z),
+            width: none,
+            height: none,
+        });
+        self.portals.len() - 1
+    }
+
+    // Makes the portal at `index` a `width` × `height` rectangle, facing the
+    // direction given. Either way will do, since light comes through both.
+    #[wasm_bindgen(js_name = setPortalRect)]
+    pub fn set_portal_rect(
+        &mut self,
+        index: usize,
+        width: f64,
+        height: f64,
+        nx: f64,
+        ny: f64,
+        nz: f64,
+    ) {
+        let normal = Vec3::new(nx, ny, nz);
+        if width <= 0. || height <= 0. || normal.length_sqr() == 0. {
+            return;
+        }
+
+        let (tangent, bitangent) = basis(&normal.unit());
+        let portal = match self.portals.get_mut(index) {
+            Some(portal) => portal,
+            None => return,
+        };
+        portal.width = tangent.scale(width);
+        portal.height = bitangent.scale(height);
+        self.dirty = Some(Region::full());
+    }
+}
@@ -0,0 +1,15 @@
+//! The math types used throughout Raymond, for applications that embed it and
+//! want to work with the same points, colors and rays that it does.
+//!
+//! ```
+//! use raymond::prelude::*;
+//!
+//! let (ray, distance) = Ray::cast(&Vec3::new(0., 0., 0.), &Vec3::new(0., 3., 4.));
+//! assert!((distance - 5.).abs() < 1e-6);
+//! assert!((ray.direction().length() - 1.).abs() < 1e-6);
+//!
+//! let orange = RGB::red().add(&RGB::green().scale(0.5));
+//! assert_eq!(orange.shade(0.5), RGB::new(0.5, 0.25, 0.));
+//! ```
+
+pub use crate::{Ray, Vec3, RGB};
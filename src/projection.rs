@@ -0,0 +1,117 @@
+use crate::region::Region;
+use crate::{Camera, Scene};
+use wasm_bindgen::prelude::*;
+
+// The camera's field of view, and the shape of its film. The film is as wide
+// as the field of view calls for, and as tall as the aspect ratio of the
+// image being rendered, so that spheres stay round on canvases of any shape.
+// Its center stays where it is, keeping any offset from the eye.
+
+// The width of a full-frame 35mm sensor, in millimetres, which focal lengths
+// are given for.
+const SENSOR_WIDTH: f64 = 36.;
+
+impl Camera {
+    // The distance from the eye to the film.
+    fn focal_length(&self) -> f64 {
+        self.film.origin.z
+    }
+
+    // Gives the film the aspect ratio of a `width` × `height` image, keeping
+    // its width, and returns whether its shape changed.
+    fn fit(&mut self, width: usize, height: usize) -> bool {
+        if width == 0 || height == 0 {
+            return false;
+        }
+
+        let film_height = self.film.width * height as f64 / width as f64;
+        if (film_height - self.film.height).abs() <= 1e-9 * self.film.height {
+            return false;
+        }
+        self.resize(self.film.width, film_height);
+        true
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Sets the camera's horizontal field of view, in degrees, between 1 and
+    // 179. The vertical field of view follows from the aspect ratio of the
+    // image rendered into. The default is about 67°.
+    #[wasm_bindgen(js_name = setFieldOfView)]
+    pub fn set_field_of_view(&mut self, degrees: f64) {
+        if !degrees.is_finite() {
+            return;
+        }
+
+        // A breathing zoom widens the film around the field of view.
+        let half = degrees.clamp(1., 179.).to_radians() / 2.;
+        let width = 2. * self.camera.focal_length() * half.tan() * self.shake.zoom;
+        let aspect = self.camera.film.height / self.camera.film.width;
+        self.camera.resize(width, width * aspect);
+        self.invalidate(Region::full());
+    }
+
+    // The camera's horizontal field of view, in degrees.
+    #[wasm_bindgen(js_name = fieldOfView)]
+    pub fn field_of_view(&self) -> f64 {
+        let half_width = self.camera.film.width / self.shake.zoom / 2.;
+        2. * half_width.atan2(self.camera.focal_length()).to_degrees()
+    }
+
+    // The ray that the renderer traces through the point (`x`, `y`) of a
+    // `width` × `height` image, in pixels from the top-left corner, as an
+    // array of its origin and unit direction: [ox, oy, oz, dx, dy, dz]. The
+    // renderer traces each pixel through its top-left corner, so add 0.5 to
+    // both for its middle. Depth of field and motion blur are left out. An
+    // empty image gives an empty array.
+    #[wasm_bindgen(js_name = rayForPixel)]
+    pub fn ray_for_pixel(&self, x: f64, y: f64, width: usize, height: usize) -> Vec<f64> {
+        if width == 0 || height == 0 {
+            return vec![];
+        }
+
+        let mut camera = self.camera;
+        camera.fit(width, height);
+        let ray = camera.cast(x / width as f64, y / height as f64);
+        let (o, d) = (ray.origin(), ray.direction());
+        vec![o.x, o.y, o.z, d.x, d.y, d.z]
+    }
+
+    // Zooms in by the given factor, narrowing the field of view without
+    // moving the camera, or zooms out for factors below 1.
+    pub fn zoom(&mut self, factor: f64) {
+        if factor > 0. && factor.is_finite() {
+            self.camera.widen(1. / factor);
+            self.invalidate(Region::full());
+        }
+    }
+
+    // Sets the field of view to that of a lens with the given focal length,
+    // in millimetres, on a full-frame 35mm camera. Longer lenses zoom in.
+    #[wasm_bindgen(js_name = setFocalLength)]
+    pub fn set_focal_length(&mut self, millimetres: f64) {
+        if millimetres > 0. {
+            let half = (SENSOR_WIDTH / 2.).atan2(millimetres);
+            self.set_field_of_view(2. * half.to_degrees());
+        }
+    }
+
+    // The focal length, in millimetres, of the full-frame lens with the
+    // camera's field of view.
+    #[wasm_bindgen(js_name = focalLength)]
+    pub fn focal_length(&self) -> f64 {
+        let half = (self.field_of_view() / 2.).to_radians();
+        SENSOR_WIDTH / 2. / half.tan()
+    }
+}
+
+impl Scene {
+    // Shapes the film to match the image about to be rendered, and marks the
+    // whole frame as changed if it had to.
+    pub(crate) fn fit_film(&mut self, width: usize, height: usize) {
+        if self.camera.fit(width, height) {
+            self.invalidate(Region::full());
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use crate::color::srgb_decode;
+use crate::{Image, Scene, Vec3, RGB};
+use wasm_bindgen::prelude::*;
+
+// A tiny rasterizer for drawing overlays (debugging aids, gizmos, HUDs) straight
+// into an image after it has been rendered. Colors are given as 0xRRGGBB.
+
+#[wasm_bindgen]
+impl Image {
+    // Draws a line between two pixels. Any part of it outside the image is
+    // left out.
+    #[wasm_bindgen(js_name = drawLine)]
+    pub fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: u32) {
+        let color = unpack(color);
+
+        // Clip the line to the image first, so that endpoints far outside it
+        // neither overflow below nor take forever to walk back in.
+        let last = ((self.width as f64 - 1.), (self.height as f64 - 1.));
+        let (a, b) = ((x0 as f64, y0 as f64), (x1 as f64, y1 as f64));
+        let ((x0, y0), (x1, y1)) = match clip_to_rect(a, b, last) {
+            Some((a, b)) => (
+                (a.0.round() as i32, a.1.round() as i32),
+                (b.0.round() as i32, b.1.round() as i32),
+            ),
+            None => return,
+        };
+
+        // Bresenham's line algorithm.
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (sx, sy) = ((x1 - x0).signum(), (y1 - y0).signum());
+        let (mut x, mut y, mut err) = (x0, y0, dx + dy);
+
+        loop {
+            self.plot(x, y, &color);
+            if x == x1 && y == y1 {
+                break;
+            }
+
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    #[wasm_bindgen(js_name = drawCircle)]
+    pub fn draw_circle(&mut self, cx: i32, cy: i32, radius: i32, color: u32) {
+        let color = unpack(color);
+
+        // The midpoint circle algorithm, which draws all eight octants at once.
+        let (mut x, mut y, mut err) = (radius, 0, 1 - radius);
+
+        while x >= y {
+            for &(px, py) in &[(x, y), (y, x), (-y, x), (-x, y)] {
+                self.plot(cx + px, cy + py, &color);
+                self.plot(cx - px, cy - py, &color);
+            }
+
+            y += 1;
+            if err < 0 {
+                err += 2 * y + 1;
+            } else {
+                x -= 1;
+                err += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    // Fills the `width` × `height` rectangle with its top-left corner at
+    // (x, y). Any part of it outside the image is left out.
+    #[wasm_bindgen(js_name = fillRect)]
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: i32, height: i32, color: u32) {
+        let color = unpack(color);
+
+        // Clamp the rectangle to the image up front, so that neither its far
+        // corner overflows nor any time is spent on pixels outside it.
+        let span = |start: i32, size: i32, limit: usize| {
+            let end = (start as i64 + size as i64).min(limit as i64);
+            start.max(0) as usize..end.max(0) as usize
+        };
+        for py in span(y, height, self.height) {
+            for px in span(x, width, self.width) {
+                self.draw(px, py, &color);
+            }
+        }
+    }
+
+    // Draws a line of text with its top-left corner at (x, y), using a 3×5
+    // pixel font blown up by `scale`. Lowercase letters are drawn as capitals,
+    // and characters without a glyph as question marks.
+    #[wasm_bindgen(js_name = drawText)]
+    pub fn draw_text(&mut self, x: i32, y: i32, text: &str, scale: i32, color: u32) {
+        let color = unpack(color);
+        let scale = scale.max(1);
+
+        for (i, c) in text.chars().enumerate() {
+            let glyph = glyph(c);
+            let left = x + i as i32 * (GLYPH_WIDTH + 1) * scale;
+
+            for row in 0..GLYPH_HEIGHT {
+                for col in 0..GLYPH_WIDTH {
+                    let bit = (GLYPH_HEIGHT - row) * GLYPH_WIDTH - col - 1;
+                    if glyph & (1 << bit) == 0 {
+                        continue;
+                    }
+
+                    for dy in 0..scale {
+                        for dx in 0..scale {
+                            self.plot(left + col * scale + dx, y + row * scale + dy, &color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Image {
+    // Draws a single pixel, silently ignoring any that fall outside the image.
+    fn plot(&mut self, x: i32, y: i32, color: &RGB) {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            self.draw(x as usize, y as usize, color);
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Draws a line between two points in world space, given as [x, y, z]
+    // arrays, as seen by the camera. Points with fewer than three coordinates
+    // are ignored.
+    #[wasm_bindgen(js_name = drawLine3d)]
+    pub fn draw_line_3d(&self, img: &mut Image, from: &[f64], to: &[f64], color: u32) {
+        if let (Some(from), Some(to)) = (point(from), point(to)) {
+            self.draw_segment(img, &from, &to, color);
+        }
+    }
+
+    // Draws the X, Y and Z axes, in red, green and blue respectively, as
+    // seen by the camera, starting from the given point in world space.
+    #[wasm_bindgen(js_name = drawAxes)]
+    pub fn draw_axes(&self, img: &mut Image, origin: &[f64], length: f64) {
+        let origin = match point(origin) {
+            Some(origin) => origin,
+            None => return,
+        };
+        let axes = [
+            (Vec3::new(length, 0., 0.), 0xFF_00_00),
+            (Vec3::new(0., length, 0.), 0x00_FF_00),
+            (Vec3::new(0., 0., length), 0x00_00_FF),
+        ];
+
+        for (axis, color) in &axes {
+            self.draw_segment(img, &origin, &origin.add(axis), *color);
+        }
+    }
+}
+
+impl Scene {
+    pub(crate) fn draw_segment(&self, img: &mut Image, from: &Vec3, to: &Vec3, color: u32) {
+        let clipped = self
+            .camera
+            .project_segment(from, to)
+            .and_then(|(a, b)| clip_to_rect(a, b, (1., 1.)));
+        if let Some(((x0, y0), (x1, y1))) = clipped {
+            let (w, h) = (img.width as f64, img.height as f64);
+            let px = |v: f64, size: f64| (v * size).round().max(-1.).min(size) as i32;
+            img.draw_line(px(x0, w), px(y0, h), px(x1, w), px(y1, h), color);
+        }
+    }
+}
+
+// Clips the segment between two points to the rectangle from the origin to
+// `max`, after Liang and Barsky, or returns `None` if it misses the rectangle
+// entirely. Points can land arbitrarily far outside it, such as those just
+// past the film, where clamping their coordinates would bend the line.
+fn clip_to_rect(a: (f64, f64), b: (f64, f64), max: (f64, f64)) -> Option<((f64, f64), (f64, f64))> {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let (mut t0, mut t1) = (0., 1.);
+    for &(p, q) in &[(-dx, a.0), (dx, max.0 - a.0), (-dy, a.1), (dy, max.1 - a.1)] {
+        if p == 0. {
+            if q < 0. {
+                return None;
+            }
+        } else {
+            let t = q / p;
+            if p < 0. {
+                t0 = f64::max(t0, t);
+            } else {
+                t1 = f64::min(t1, t);
+            }
+        }
+    }
+
+    if t0 > t1 {
+        return None;
+    }
+    let at = |t: f64| (a.0 + t * dx, a.1 + t * dy);
+    Some((at(t0), at(t1)))
+}
+
+// The size in pixels of a line of text drawn by `drawText` at `scale`.
+pub(crate) fn text_size(text: &str, scale: i32) -> (i32, i32) {
+    let len = text.chars().count() as i32;
+    let width = (len * (GLYPH_WIDTH + 1) - 1).max(0) * scale;
+    (width, GLYPH_HEIGHT * scale)
+}
+
+// Decodes a 0xRRGGBB color, written in sRGB as on the web, to linear values.
+pub(crate) fn unpack(color: u32) -> RGB {
+    let channel = |shift: u32| srgb_decode(((color >> shift) & 0xFF) as f64 / 255.);
+    RGB::new(channel(16), channel(8), channel(0))
+}
+
+fn point(xyz: &[f64]) -> Option<Vec3> {
+    match *xyz {
+        [x, y, z, ..] => Some(Vec3::new(x, y, z)),
+        _ => None,
+    }
+}
+
+const GLYPH_WIDTH: i32 = 3;
+const GLYPH_HEIGHT: i32 = 5;
+
+// Each glyph is a 3×5 bitmap, stored one row of three bits after another
+// starting from the top, with the most significant bit on the left.
+fn glyph(c: char) -> u16 {
+    match c.to_ascii_uppercase() {
+        ' ' => 0,
+        '0' => 0b111_101_101_101_111,
+        '1' => 0b010_110_010_010_111,
+        '2' => 0b111_001_111_100_111,
+        '3' => 0b111_001_111_001_111,
+        '4' => 0b101_101_111_001_001,
+        '5' => 0b111_100_111_001_111,
+        '6' => 0b111_100_111_101_111,
+        '7' => 0b111_001_001_001_001,
+        '8' => 0b111_101_111_101_111,
+        '9' => 0b111_101_111_001_111,
+        'A' => 0b010_101_111_101_101,
+        'B' => 0b110_101_110_101_110,
+        'C' => 0b011_100_100_100_011,
+        'D' => 0b110_101_101_101_110,
+        'E' => 0b111_100_110_100_111,
+        'F' => 0b111_100_110_100_100,
+        'G' => 0b011_100_101_101_011,
+        'H' => 0b101_101_111_101_101,
+        'I' => 0b111_010_010_010_111,
+        'J' => 0b001_001_001_101_010,
+        'K' => 0b101_101_110_101_101,
+        'L' => 0b100_100_100_100_111,
+        'M' => 0b101_111_111_101_101,
+        'N' => 0b110_101_101_101_101,
+        'O' => 0b010_101_101_101_010,
+        'P' => 0b110_101_110_100_100,
+        'Q' => 0b010_101_101_110_011,
+        'R' => 0b110_101_110_101_101,
+        'S' => 0b011_100_010_001_110,
+        'T' => 0b111_010_010_010_010,
+        'U' => 0b101_101_101_101_111,
+        'V' => 0b101_101_101_101_010,
+        'W' => 0b101_101_111_111_101,
+        'X' => 0b101_101_010_101_101,
+        'Y' => 0b101_101_010_010_010,
+        'Z' => 0b111_001_010_100_111,
+        '.' => 0b000_000_000_000_010,
+        ',' => 0b000_000_000_010_100,
+        ':' => 0b000_010_000_010_000,
+        '-' => 0b000_000_111_000_000,
+        '+' => 0b000_010_111_010_000,
+        '=' => 0b000_111_000_111_000,
+        '/' => 0b001_001_010_100_100,
+        '%' => 0b101_001_010_100_101,
+        '(' => 0b001_010_010_010_001,
+        ')' => 0b100_010_010_010_100,
+        _ => 0b111_001_010_000_010,
+    }
+}
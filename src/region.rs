@@ -0,0 +1,78 @@
+use std::ops::Range;
+
+// A rectangular area of the frame, expressed in film coordinates where (0, 0) is
+// the top-left corner and (1, 1) the bottom-right one. Working in film space
+// rather than in pixels means a region stays valid whatever the image size.
+#[derive(Copy, Clone)]
+pub struct Region {
+    x0: f64,
+    y0: f64,
+    x1: f64,
+    y1: f64,
+}
+
+impl Region {
+    pub fn full() -> Self {
+        Self {
+            x0: 0.,
+            y0: 0.,
+            x1: 1.,
+            y1: 1.,
+        }
+    }
+
+    // Returns the smallest region containing every one of the given points.
+    pub fn around<I>(points: I) -> Self
+    where
+        I: IntoIterator<Item = (f64, f64)>,
+    {
+        let empty = Self {
+            x0: f64::INFINITY,
+            y0: f64::INFINITY,
+            x1: f64::NEG_INFINITY,
+            y1: f64::NEG_INFINITY,
+        };
+
+        points.into_iter().fold(empty, |r, (x, y)| Self {
+            x0: r.x0.min(x),
+            y0: r.y0.min(y),
+            x1: r.x1.max(x),
+            y1: r.y1.max(y),
+        })
+    }
+
+    pub fn union(&self, other: &Region) -> Region {
+        Region {
+            x0: self.x0.min(other.x0),
+            y0: self.y0.min(other.y0),
+            x1: self.x1.max(other.x1),
+            y1: self.y1.max(other.y1),
+        }
+    }
+
+    pub fn overlaps(&self, other: &Region) -> bool {
+        self.x0 <= other.x1 && other.x0 <= self.x1 && self.y0 <= other.y1 && other.y0 <= self.y1
+    }
+
+    pub fn expand(&self, margin: f64) -> Region {
+        Region {
+            x0: self.x0 - margin,
+            y0: self.y0 - margin,
+            x1: self.x1 + margin,
+            y1: self.y1 + margin,
+        }
+    }
+
+    // Converts the region into the ranges of pixel columns and rows it covers
+    // in an image of the given size, clamped to the image's bounds.
+    pub fn pixels(&self, width: usize, height: usize) -> (Range<usize>, Range<usize>) {
+        let clamp = |v: f64, max: usize| v.max(0.).min(max as f64) as usize;
+
+        let xs = clamp((self.x0 * width as f64).floor(), width)
+            ..clamp((self.x1 * width as f64).ceil(), width);
+        let ys = clamp((self.y0 * height as f64).floor(), height)
+            ..clamp((self.y1 * height as f64).ceil(), height);
+
+        (xs, ys)
+    }
+}
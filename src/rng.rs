@@ -0,0 +1,39 @@
+// A small, fast pseudo-random number generator (xorshift64*). Raymond only
+// needs well-distributed numbers, not cryptographically secure ones, and the
+// `rand` crate would noticeably inflate the size of the wasm binary.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Scramble the seed with a round of SplitMix64, so that similar seeds
+        // (such as those of neighbouring pixels) produce unrelated sequences.
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+
+        // The all-zeroes state is a fixed point of xorshift.
+        Self { state: z.max(1) }
+    }
+
+    // Seeds a generator from a pixel's coordinates, so that each pixel sees the
+    // same sequence of numbers every time it is rendered. This keeps partial
+    // re-renders consistent with the rest of the frame.
+    pub fn for_pixel(x: usize, y: usize) -> Self {
+        Self::new(((y as u64) << 32) | x as u64)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    // Returns a number uniformly distributed in [0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
@@ -0,0 +1,110 @@
+use crate::noise::noise;
+use crate::region::Region;
+use crate::{Scene, Vec3};
+use std::f64::consts::PI;
+use wasm_bindgen::prelude::*;
+
+// Procedural modifiers layered on top of the camera's pose, to make animations
+// feel less locked off: handheld shake, which jostles the camera around with
+// smooth noise, and breathing, which slowly zooms in and out. Both are driven
+// by the time passed to `setCameraTime`, so the host only has to advance a
+// clock. The pose that was last applied is remembered and undone before the
+// next, so moving the camera in between works as usual.
+pub struct CameraShake {
+    // The furthest the camera strays from its pose, in world units.
+    amplitude: f64,
+    // Roughly how many times per second the shake changes direction.
+    frequency: f64,
+    // The fraction by which the field of view grows and shrinks.
+    breathing: f64,
+    // How many breaths are taken per second.
+    breathing_rate: f64,
+    time: f64,
+    // The offset and zoom currently applied to the camera.
+    offset: Vec3,
+    pub zoom: f64,
+}
+
+impl Default for CameraShake {
+    fn default() -> Self {
+        Self {
+            amplitude: 0.,
+            frequency: 0.,
+            breathing: 0.,
+            breathing_rate: 0.,
+            time: 0.,
+            offset: Vec3::new(0., 0., 0.),
+            zoom: 1.,
+        }
+    }
+}
+
+impl CameraShake {
+    fn offset_at(&self, time: f64) -> Vec3 {
+        if self.amplitude == 0. {
+            return Vec3::new(0., 0., 0.);
+        }
+
+        // Each axis follows its own slice of the noise, with a second octave
+        // for the smaller, quicker tremors of a hand.
+        let t = time * self.frequency;
+        let axis = |slice: f64| {
+            let p = |t: f64| Vec3::new(t, slice, 0.5);
+            noise(&p(t)) + 0.5 * noise(&p(2. * t + 7.))
+        };
+        Vec3::new(axis(0.5), axis(10.5), 0.).scale(self.amplitude / 1.5)
+    }
+
+    fn zoom_at(&self, time: f64) -> f64 {
+        1. + self.breathing * (2. * PI * self.breathing_rate * time).sin()
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Shakes the camera as if it were handheld, by up to `amplitude` world
+    // units, changing direction about `frequency` times per second. Passing
+    // an amplitude of zero holds it steady again.
+    #[wasm_bindgen(js_name = setCameraShake)]
+    pub fn set_camera_shake(&mut self, amplitude: f64, frequency: f64) {
+        self.shake.amplitude = amplitude.max(0.);
+        self.shake.frequency = frequency.max(0.);
+        self.apply_shake(self.shake.time);
+    }
+
+    // Zooms the camera slowly in and out, widening and narrowing the field of
+    // view by the given fraction, `frequency` times per second. Passing an
+    // amount of zero turns it off again.
+    #[wasm_bindgen(js_name = setBreathingZoom)]
+    pub fn set_breathing_zoom(&mut self, amount: f64, frequency: f64) {
+        self.shake.breathing = amount.clamp(0., 0.9);
+        self.shake.breathing_rate = frequency.max(0.);
+        self.apply_shake(self.shake.time);
+    }
+
+    // Moves the camera modifiers on to the given time, in seconds. Call this
+    // once per frame, before rendering it.
+    #[wasm_bindgen(js_name = setCameraTime)]
+    pub fn set_camera_time(&mut self, seconds: f64) {
+        self.apply_shake(seconds);
+    }
+}
+
+impl Scene {
+    fn apply_shake(&mut self, time: f64) {
+        let offset = self.shake.offset_at(time);
+        let zoom = self.shake.zoom_at(time);
+
+        self.shake.time = time;
+        if offset == self.shake.offset && zoom == self.shake.zoom {
+            return;
+        }
+
+        self.camera.translate(&offset.subtract(&self.shake.offset));
+        self.camera.widen(zoom / self.shake.zoom);
+        self.follow_camera();
+        self.shake.offset = offset;
+        self.shake.zoom = zoom;
+        self.invalidate(Region::full());
+    }
+}
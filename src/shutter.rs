@@ -0,0 +1,107 @@
+use crate::region::Region;
+use crate::rng::Rng;
+use crate::spheres::SphereSet;
+use crate::{Ray, Scene, Vec3, REFLECTION_MARGIN};
+use std::cell::Cell;
+use wasm_bindgen::prelude::*;
+
+// Motion blur. Spheres and the camera can be given a velocity, as the distance
+// they move over a frame, and while the shutter is open, each pixel is traced
+// at a random time within it, with everything moved to where it is at that
+// time. Over many samples, as in a still, moving spheres streak along their
+// paths. Every ray traced for a pixel shares its time, so moving spheres cast
+// their shadows and reflections from where they are seen.
+pub struct Shutter {
+    // The fraction of the frame that the shutter is open for, from the start
+    // of it. Zero turns motion blur off.
+    open: f64,
+    camera_velocity: Vec3,
+    // The time that the pixel being shaded is traced at.
+    pub time: Cell<f64>,
+}
+
+impl Default for Shutter {
+    fn default() -> Self {
+        Self {
+            open: 0.,
+            camera_velocity: Vec3::new(0., 0., 0.),
+            time: Cell::new(0.),
+        }
+    }
+}
+
+impl Shutter {
+    pub fn is_open(&self) -> bool {
+        self.open > 0.
+    }
+
+    // How far the camera moves while the shutter is open.
+    pub fn camera_travel(&self) -> Vec3 {
+        self.camera_velocity.scale(self.open)
+    }
+}
+
+impl Scene {
+    // Casts the primary ray through the given film coordinates, at a random
+    // time while the shutter is open, and moves the spheres in `visible` and
+    // in the rest of the scene to where they are at that time. With the
+    // shutter closed, `rng` is left untouched.
+    pub(crate) fn primary_ray(&self, visible: &SphereSet, u: f64, v: f64, rng: &mut Rng) -> Ray {
+        let mut ray = self.camera.cast_through_lens(u, v, rng);
+        if self.shutter.is_open() {
+            let time = self.shutter.open * rng.next_f64();
+            visible.time.set(time);
+            self.set_shutter_time(time);
+            let offset = self.shutter.camera_velocity.scale(time);
+            ray.origin = ray.origin.add(&offset);
+        }
+        ray
+    }
+
+    fn set_shutter_time(&self, time: f64) {
+        self.shutter.time.set(time);
+        for set in [&self.geometry, &self.occluders, &self.ghosts] {
+            set.time.set(time);
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Opens the shutter for the given fraction of each frame, from 0 to 1,
+    // blurring whatever moves in that time. Zero turns motion blur off. A
+    // single render shows a grainy blur, so capture a still with plenty of
+    // samples to smooth it out.
+    #[wasm_bindgen(js_name = setShutter)]
+    pub fn set_shutter(&mut self, open: f64) {
+        self.shutter.open = if open.is_finite() {
+            open.clamp(0., 1.)
+        } else {
+            0.
+        };
+        self.set_shutter_time(0.);
+        self.invalidate(Region::full());
+    }
+
+    // Sets how far the camera moves over a frame, for motion blur.
+    #[wasm_bindgen(js_name = setCameraVelocity)]
+    pub fn set_camera_velocity(&mut self, x: f64, y: f64, z: f64) {
+        self.shutter.camera_velocity = Vec3::new(x, y, z);
+        self.invalidate(Region::full());
+    }
+
+    // Sets how far the sphere at `index` moves over a frame, for motion blur.
+    // Moving spheres are left out of the scene's accelerator.
+    #[wasm_bindgen(js_name = setSphereVelocity)]
+    pub fn set_sphere_velocity(&mut self, index: usize, x: f64, y: f64, z: f64) {
+        let before = match self.spheres.get(index) {
+            Some(sphere) => self.sphere_bounds(sphere),
+            None => return,
+        };
+        self.spheres[index].velocity = Vec3::new(x, y, z);
+        self.rebuild_geometry();
+        let after = self.sphere_bounds(&self.spheres[index]);
+
+        self.invalidate(before.union(&after).expand(REFLECTION_MARGIN));
+    }
+}
@@ -0,0 +1,168 @@
+use crate::color::chromaticity_to_srgb;
+use crate::region::Region;
+use crate::sun::DirectionalLight;
+use crate::units::nits_to_lux;
+use crate::{Scene, Vec3, RGB};
+use std::f64::consts::PI;
+use wasm_bindgen::prelude::*;
+
+// A clear sky, after Preetham, Shirley and Smits' analytic model, together
+// with the sun that lights it. The sky's color and brightness in every
+// direction follow from where the sun is and how hazy the air is, so outdoor
+// scenes look plausible without an environment map. Like the environment map,
+// the sky is seen in the background and lights the scene through every ray
+// that escapes into it, while the sun is a directional light of its own.
+pub struct PhysicalSky {
+    // A unit vector pointing towards the sun, which is never below the
+    // horizon as far as the sky's colors are concerned.
+    towards: Vec3,
+    // The Perez distribution's coefficients, for luminance and each of the
+    // two chromaticity coordinates, and their values at the zenith.
+    perez: [[f64; 5]; 3],
+    zenith: [f64; 3],
+    pub turbidity: f64,
+    pub sun: Option<DirectionalLight>,
+}
+
+// The illuminance of sunlight before it enters the atmosphere, in lux.
+const SOLAR_LUX: f64 = 128_000.;
+
+// How much of the sunlight each unit of turbidity scatters away over one air
+// mass, the thickness of the atmosphere straight up.
+const EXTINCTION: f64 = 0.04;
+
+// The fraction of the sky's light that the ground below the horizon reflects.
+const GROUND_ALBEDO: f64 = 0.3;
+
+impl PhysicalSky {
+    pub fn new(towards: Vec3, turbidity: f64) -> Self {
+        let t = turbidity;
+        let perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        // The sky is only defined with the sun above the horizon, so once it
+        // sets the sky stays as it was at sunset.
+        let sky_towards = Vec3::new(towards.x, towards.y.max(1e-3), towards.z).unit();
+        let theta = sky_towards.y.acos();
+        let (t2, th2, th3) = (t * t, theta * theta, theta * theta * theta);
+
+        let chi = (4. / 9. - t / 120.) * (PI - 2. * theta);
+        // In thousands of nits.
+        let luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let x = t2 * (0.00166 * th3 - 0.00375 * th2 + 0.00209 * theta)
+            + t * (-0.02903 * th3 + 0.06377 * th2 - 0.03202 * theta + 0.00394)
+            + (0.11693 * th3 - 0.21196 * th2 + 0.06052 * theta + 0.25886);
+        let y = t2 * (0.00275 * th3 - 0.00610 * th2 + 0.00317 * theta)
+            + t * (-0.04214 * th3 + 0.08970 * th2 - 0.04153 * theta + 0.00516)
+            + (0.15346 * th3 - 0.26756 * th2 + 0.06670 * theta + 0.26688);
+
+        let sun = if towards.y > 0. {
+            Some(DirectionalLight::new(
+                towards.scale(-1.),
+                sunlight(towards.y, t),
+            ))
+        } else {
+            None
+        };
+
+        Self {
+            towards: sky_towards,
+            perez,
+            zenith: [luminance.max(0.) * 1000., x, y],
+            turbidity,
+            sun,
+        }
+    }
+
+    // The light arriving from the given direction, a unit vector, in rendered
+    // color values before the camera's response. Below the horizon, the
+    // ground reflects some of the light of the sky mirrored above it.
+    pub fn radiance(&self, direction: &Vec3) -> RGB {
+        if direction.y < 0. {
+            let mirrored = Vec3::new(direction.x, -direction.y, direction.z);
+            return self.radiance(&mirrored).scale(GROUND_ALBEDO);
+        }
+
+        // The distribution blows up right at the horizon.
+        let cos_theta = direction.y.max(1e-3);
+        let cos_gamma = direction.dot(&self.towards).clamp(-1., 1.);
+        let cos_sun = self.towards.y;
+
+        let [lum, x, y] = [0, 1, 2].map(|i| {
+            let relative =
+                perez(&self.perez[i], cos_theta, cos_gamma) / perez(&self.perez[i], 1., cos_sun);
+            self.zenith[i] * relative
+        });
+
+        // Luminance is left out of the conversion until the end, so that it
+        // can be converted to the renderer's units.
+        chromaticity_to_srgb(x, y).scale(nits_to_lux(lum))
+    }
+}
+
+// The Perez sky distribution, for a direction at an angle θ from the zenith
+// and γ from the sun, given their cosines.
+fn perez(c: &[f64; 5], cos_theta: f64, cos_gamma: f64) -> f64 {
+    let gamma = cos_gamma.acos();
+    (1. + c[0] * (c[1] / cos_theta).exp())
+        * (1. + c[2] * (c[3] * gamma).exp() + c[4] * cos_gamma * cos_gamma)
+}
+
+// The illuminance, in lux, of sunlight that reaches the ground through air of
+// the given turbidity, from a sun whose direction has the given cosine with
+// the zenith.
+pub fn sunlight(cos_zenith: f64, turbidity: f64) -> f64 {
+    SOLAR_LUX * (-EXTINCTION * turbidity * air_mass(cos_zenith)).exp()
+}
+
+// How many times thicker the atmosphere is along a ray heading towards the
+// sun than straight up, after Kasten and Young, which stays finite at the
+// horizon.
+fn air_mass(cos_zenith: f64) -> f64 {
+    let elevation = 90. - cos_zenith.acos().to_degrees();
+    1. / (cos_zenith + 0.50572 * (elevation + 6.07995).powf(-1.6364))
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Surrounds the scene with a clear sky, lit by a sun in the direction
+    // given, pointing from the scene towards it. Turbidity is the haziness of
+    // the air, from 2 for a crisp, deep blue sky, to 10 or so for a pale,
+    // hazy one. The sky and sun are in physical units, so they call for a
+    // white point in the tens of thousands of lux by day. Once the sun drops
+    // below the horizon, it goes out, and the sky stays as it was at sunset.
+    // A turbidity of zero turns the sky off again. An environment map, if
+    // one is set, is seen in the sky's place.
+    #[wasm_bindgen(js_name = setPhysicalSky)]
+    pub fn set_physical_sky(&mut self, dx: f64, dy: f64, dz: f64, turbidity: f64) {
+        let towards = Vec3::new(dx, dy, dz);
+        self.sky = if turbidity > 0. && towards.length_sqr() > 0. {
+            Some(PhysicalSky::new(towards.unit(), turbidity.max(1.)))
+        } else {
+            None
+        };
+        self.forget_irradiance();
+        self.dirty = Some(Region::full());
+    }
+}
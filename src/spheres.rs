@@ -0,0 +1,246 @@
+use crate::accel::Accel;
+use crate::stats::Counters;
+use crate::{Ray, Sphere, Vec3, EPSILON};
+use std::cell::Cell;
+
+// The geometry of a set of spheres, stored as a structure of arrays. The
+// intersection loops only ever read centers and radii, so keeping those in
+// contiguous arrays of their own (rather than interleaved with colors and
+// other surface properties) means that every byte pulled into the cache gets
+// used, and the loops can stream through memory without any gathers.
+#[derive(Clone)]
+pub struct SphereSet {
+    // The position of each sphere in the scene's list of spheres.
+    pub index: Vec<usize>,
+    pub cx: Vec<f64>,
+    pub cy: Vec<f64>,
+    pub cz: Vec<f64>,
+    pub radius: Vec<f64>,
+    // How far each sphere moves over a frame, and whether any of them do.
+    // Moving spheres are tested where they are at `time`, one at a time.
+    pub velocity: Vec<Vec3>,
+    pub moving: bool,
+    // The time within the frame that rays are being traced at, for motion
+    // blur.
+    pub time: Cell<f64>,
+    // The index over the spheres that narrows down which ones a ray might
+    // hit. Without one, rays are tested against every sphere.
+    pub accel: Option<Accel>,
+}
+
+impl SphereSet {
+    pub fn new<'a, I>(spheres: I) -> Self
+    where
+        I: IntoIterator<Item = (usize, &'a Sphere)>,
+    {
+        let mut set = Self {
+            index: vec![],
+            cx: vec![],
+            cy: vec![],
+            cz: vec![],
+            radius: vec![],
+            velocity: vec![],
+            moving: false,
+            time: Cell::new(0.),
+            accel: None,
+        };
+
+        for (i, sphere) in spheres {
+            set.index.push(i);
+            set.cx.push(sphere.center.x);
+            set.cy.push(sphere.center.y);
+            set.cz.push(sphere.center.z);
+            set.radius.push(sphere.radius);
+            set.velocity.push(sphere.velocity);
+            set.moving |= sphere.velocity.length_sqr() > 0.;
+        }
+
+        set
+    }
+
+    // Finds the nearest sphere hit by `ray`, returning its position in the
+    // scene's list of spheres along with the distance to it.
+    #[inline]
+    pub fn nearest(&self, ray: &Ray, stats: &Counters) -> Option<(usize, f64)> {
+        if let Some(accel) = &self.accel {
+            let mut nearest = None;
+            let mut min_t = f64::INFINITY;
+            accel.traverse(ray, f64::INFINITY, stats, |items| {
+                Counters::bump(&stats.intersection_tests, items.len() as u64);
+                for &i in items {
+                    let t = self.distance(i, ray);
+                    if t < min_t {
+                        min_t = t;
+                        nearest = Some(i);
+                    }
+                }
+                min_t
+            });
+            return nearest.map(|i| (self.index[i], min_t));
+        }
+
+        Counters::bump(&stats.intersection_tests, self.index.len() as u64);
+        if self.moving {
+            let distances = (0..self.index.len()).map(|i| (i, self.distance(i, ray)));
+            let (i, t) = distances.fold((0, f64::INFINITY), |a, b| if b.1 < a.1 { b } else { a });
+            return if t < f64::INFINITY {
+                Some((self.index[i], t))
+            } else {
+                None
+            };
+        }
+
+        let (o, d) = (&ray.origin, &ray.direction);
+        let mut nearest = None;
+        let mut min_t = f64::INFINITY;
+
+        // Re-slicing every array to the same length lets the compiler prove
+        // that none of the accesses below can go out of bounds.
+        let n = self.index.len();
+        let (cx, cy, cz, radius) = (
+            &self.cx[..n],
+            &self.cy[..n],
+            &self.cz[..n],
+            &self.radius[..n],
+        );
+
+        for i in 0..n {
+            let (ocx, ocy, ocz) = (o.x - cx[i], o.y - cy[i], o.z - cz[i]);
+            let dot = d.x * ocx + d.y * ocy + d.z * ocz;
+            let c = ocx * ocx + ocy * ocy + ocz * ocz - radius[i] * radius[i];
+            let t = nearest_root(dot, c);
+
+            if t < min_t {
+                min_t = t;
+                nearest = Some(i);
+            }
+        }
+
+        nearest.map(|i| (self.index[i], min_t))
+    }
+
+    // A cheaper alternative to `nearest` for shadow rays, which only need to
+    // know whether any sphere blocks the ray before `max_t`.
+    #[inline]
+    pub fn occluded(&self, ray: &Ray, max_t: f64, stats: &Counters) -> bool {
+        if let Some(accel) = &self.accel {
+            let mut blocked = false;
+            accel.traverse(ray, max_t, stats, |items| {
+                let blocker = items.iter().position(|&i| self.distance(i, ray) < max_t);
+                let tests = blocker.map_or(items.len(), |i| i + 1);
+                Counters::bump(&stats.intersection_tests, tests as u64);
+
+                blocked = blocker.is_some();
+                if blocked {
+                    f64::NEG_INFINITY
+                } else {
+                    max_t
+                }
+            });
+            return blocked;
+        }
+
+        if self.moving {
+            let blocker = (0..self.index.len()).position(|i| self.distance(i, ray) < max_t);
+            let tests = blocker.map_or(self.index.len(), |i| i + 1);
+            Counters::bump(&stats.intersection_tests, tests as u64);
+            return blocker.is_some();
+        }
+
+        let (o, d) = (&ray.origin, &ray.direction);
+
+        let n = self.index.len();
+        let (cx, cy, cz, radius) = (
+            &self.cx[..n],
+            &self.cy[..n],
+            &self.cz[..n],
+            &self.radius[..n],
+        );
+
+        let blocker = (0..n).position(|i| {
+            let r = radius[i];
+            let (ocx, ocy, ocz) = (o.x - cx[i], o.y - cy[i], o.z - cz[i]);
+            let dot = d.x * ocx + d.y * ocy + d.z * ocz;
+            let c = ocx * ocx + ocy * ocy + ocz * ocz - r * r;
+
+            // Reject spheres lying entirely behind the ray's origin, or entirely
+            // beyond `max_t`, without having to take a square root.
+            if (dot > 0. && c > 0.) || -dot - r >= max_t {
+                return false;
+            }
+
+            nearest_root(dot, c) < max_t
+        });
+
+        let tests = blocker.map_or(n, |i| i + 1);
+        Counters::bump(&stats.intersection_tests, tests as u64);
+
+        blocker.is_some()
+    }
+
+    // The positions in the scene's list of spheres of every sphere that `ray`
+    // passes through before `max_t`, in the order they appear in the list,
+    // along with the distance to each.
+    pub fn crossed(&self, ray: &Ray, max_t: f64, stats: &Counters) -> Vec<(usize, f64)> {
+        let candidates = match &self.accel {
+            Some(accel) => {
+                let mut candidates = vec![];
+                accel.traverse(ray, max_t, stats, |items| {
+                    candidates.extend_from_slice(items);
+                    max_t
+                });
+                candidates.sort_unstable();
+                candidates.dedup();
+                candidates
+            }
+            None => (0..self.index.len()).collect(),
+        };
+
+        Counters::bump(&stats.intersection_tests, candidates.len() as u64);
+        candidates
+            .into_iter()
+            .map(|i| (self.index[i], self.distance(i, ray)))
+            .filter(|&(_, t)| t < max_t)
+            .collect()
+    }
+
+    // The distance along `ray` to the sphere at position `i` in the set, or
+    // infinity if it misses.
+    pub fn distance(&self, i: usize, ray: &Ray) -> f64 {
+        let (o, d) = (&ray.origin, &ray.direction);
+        let (mut cx, mut cy, mut cz) = (self.cx[i], self.cy[i], self.cz[i]);
+        if self.moving {
+            let offset = self.velocity[i].scale(self.time.get());
+            cx += offset.x;
+            cy += offset.y;
+            cz += offset.z;
+        }
+        let (ocx, ocy, ocz) = (o.x - cx, o.y - cy, o.z - cz);
+        let dot = d.x * ocx + d.y * ocy + d.z * ocz;
+        let c = ocx * ocx + ocy * ocy + ocz * ocz - self.radius[i] * self.radius[i];
+        nearest_root(dot, c)
+    }
+}
+
+// Given the terms of the quadratic equation for a ray hitting a sphere, returns
+// the distance to the nearest root in front of the ray's origin, or infinity
+// if there is none.
+#[inline]
+pub fn nearest_root(dot: f64, c: f64) -> f64 {
+    let sqrt_term = dot * dot - c;
+    if sqrt_term < 0. {
+        return f64::INFINITY;
+    }
+
+    let sqrt = sqrt_term.sqrt();
+    let near = -dot - sqrt;
+    let far = -dot + sqrt;
+
+    if near >= EPSILON {
+        near
+    } else if far >= EPSILON {
+        far
+    } else {
+        f64::INFINITY
+    }
+}
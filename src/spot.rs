@@ -0,0 +1,88 @@
+use crate::clusters::LightClusters;
+use crate::{Scene, Vec3};
+use wasm_bindgen::prelude::*;
+
+// The directions in which a light shines. Point lights shine equally in every
+// direction, while spot lights confine themselves to a cone.
+#[derive(Copy, Clone)]
+pub enum Beam {
+    Omni,
+    Spot(Cone),
+}
+
+impl Beam {
+    // The fraction of the light's full strength that it gives off in the
+    // given direction, a unit vector pointing away from the light.
+    pub fn falloff(&self, direction: &Vec3) -> f64 {
+        match self {
+            Beam::Omni => 1.,
+            Beam::Spot(cone) => cone.falloff(direction),
+        }
+    }
+}
+
+// A spot light's cone. Within the inner angle the light is at full strength,
+// beyond the outer one it gives off nothing, and in between it fades out
+// smoothly, which softens the edge of the pool of light it casts.
+#[derive(Copy, Clone)]
+pub struct Cone {
+    // A unit vector along the middle of the cone, pointing away from the light.
+    axis: Vec3,
+    cos_inner: f64,
+    cos_outer: f64,
+}
+
+impl Cone {
+    fn falloff(&self, direction: &Vec3) -> f64 {
+        let cosine = direction.dot(&self.axis);
+        if cosine >= self.cos_inner {
+            return 1.;
+        }
+        if cosine <= self.cos_outer {
+            return 0.;
+        }
+
+        let t = (cosine - self.cos_outer) / (self.cos_inner - self.cos_outer);
+        t * t * (3. - 2. * t)
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Turns the light at `index` into a spot light, shining in the direction
+    // given. The angles are in degrees, measured from the middle of the cone
+    // to its edge: the light is at full strength out to `inner_angle`, and
+    // fades away to nothing at `outer_angle`. A spot light is as bright as a
+    // point light of the same power, wherever its cone reaches, so narrowing
+    // the cone doesn't brighten it. An outer angle of 180° or more lights
+    // every direction, making it a point light again.
+    #[wasm_bindgen(js_name = setLightCone)]
+    pub fn set_light_cone(
+        &mut self,
+        index: usize,
+        dx: f64,
+        dy: f64,
+        dz: f64,
+        inner_angle: f64,
+        outer_angle: f64,
+    ) {
+        let light = match self.lights.get_mut(index) {
+            Some(light) => light,
+            None => return,
+        };
+        let outer = outer_angle.max(0.);
+        let inner = inner_angle.clamp(0., outer);
+
+        light.beam = if outer >= 180. {
+            Beam::Omni
+        } else {
+            Beam::Spot(Cone {
+                axis: Vec3::new(dx, dy, dz).unit(),
+                cos_inner: inner.to_radians().cos(),
+                cos_outer: outer.to_radians().cos(),
+            })
+        };
+        self.light_clusters = LightClusters::new(&self.lights);
+        self.invalidate_lighting();
+    }
+}
@@ -0,0 +1,162 @@
+use crate::background::Background;
+use crate::noise::turbulence;
+use crate::region::Region;
+use crate::rng::Rng;
+use crate::temperature::kelvin_to_rgb;
+use crate::{Scene, Vec3, RGB};
+use std::f64::consts::PI;
+use wasm_bindgen::prelude::*;
+
+// A procedural night sky, for space scenes. Stars are scattered through a
+// grid of unit cells around a sphere, one to a cell, at a point hashed from
+// the cell's coordinates, and kept if that point lies within half a cell of
+// the sphere's surface. A ray escaping the scene looks up the cells around
+// where it meets the sphere, so the same stars always turn up in the same
+// places, whichever way the camera looks. Each star gets a brightness, most
+// of them faint and a few bright, and a color from its temperature.
+
+// How wide a star looks, in radians: about a pixel across at the default
+// field of view, so that stars stay sharp without flickering.
+const STAR_SIZE: f64 = 0.002;
+
+// The cooler and hotter ends of the temperatures that stars are given, in
+// kelvin, from red dwarfs to blue giants.
+const COOLEST_STAR: f64 = 2500.;
+const HOTTEST_STAR: f64 = 12_000.;
+
+pub struct Starfield {
+    // The radius of the sphere that the grid's unit cells surround.
+    radius: f64,
+    // How bright the brightest stars are, in rendered units.
+    brightness: f64,
+    milky_way: Option<MilkyWay>,
+}
+
+// A faint, mottled band of light along a great circle of the sky.
+struct MilkyWay {
+    // The direction at right angles to the band.
+    pole: Vec3,
+    // How far the band reaches either side of its middle, as a sine.
+    width: f64,
+    brightness: f64,
+}
+
+struct Star {
+    direction: Vec3,
+    color: RGB,
+}
+
+impl Starfield {
+    // The light seen in the given direction, a unit vector.
+    pub fn radiance(&self, direction: &Vec3) -> RGB {
+        let p = direction.scale(self.radius);
+        let (x, y, z) = (p.x.floor() as i64, p.y.floor() as i64, p.z.floor() as i64);
+
+        // A star kept within half a cell of the sphere lies in a cell next
+        // to the one that the direction passes through.
+        let mut light = RGB::black();
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    if let Some(star) = self.star(x + dx, y + dy, z + dz) {
+                        // For small angles, 2 (1 - cos θ) is close to θ².
+                        let angle_sqr = 2. * (1. - star.direction.dot(direction));
+                        let falloff = (-angle_sqr / (STAR_SIZE * STAR_SIZE)).exp();
+                        light = light.add(&star.color.scale(falloff));
+                    }
+                }
+            }
+        }
+
+        match &self.milky_way {
+            Some(band) => light.add(&band.radiance(direction)),
+            None => light,
+        }
+    }
+
+    // The star in the cell at the given coordinates, if it has one.
+    fn star(&self, x: i64, y: i64, z: i64) -> Option<Star> {
+        let seed = (x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+            ^ (y as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f)
+            ^ (z as u64).wrapping_mul(0x1656_67b1_9e37_79f9);
+        let mut rng = Rng::new(seed);
+
+        let point = Vec3::new(
+            x as f64 + rng.next_f64(),
+            y as f64 + rng.next_f64(),
+            z as f64 + rng.next_f64(),
+        );
+        let distance = point.length();
+        if (distance - self.radius).abs() > 0.5 {
+            return None;
+        }
+
+        // Raising a uniform number to a high power leaves most stars faint.
+        let brightness = self.brightness * rng.next_f64().powi(4);
+        let kelvin = COOLEST_STAR + (HOTTEST_STAR - COOLEST_STAR) * rng.next_f64();
+        Some(Star {
+            direction: point.scale(1. / distance),
+            color: kelvin_to_rgb(kelvin).scale(brightness),
+        })
+    }
+}
+
+impl MilkyWay {
+    fn radiance(&self, direction: &Vec3) -> RGB {
+        let offset = direction.dot(&self.pole) / self.width;
+        let band = (-offset * offset).exp();
+
+        // Dust lanes and star clouds break up the glow.
+        let mottle = 0.25 + 0.75 * turbulence(&direction.scale(6.), 4).min(1.);
+        RGB::new(1., 0.93, 0.82).scale(self.brightness * band * mottle)
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Fills the background with a starfield of roughly the given number of
+    // stars over the whole sky, the brightest of them at the given
+    // brightness. The stars are the same every time. Zero stars, or a
+    // brightness of zero, goes back to the default background.
+    #[wasm_bindgen(js_name = setStarfield)]
+    pub fn set_starfield(&mut self, stars: u32, brightness: f64) {
+        self.backdrop = if stars > 0 && brightness > 0. {
+            // Stars are kept from a shell one cell thick, so there are about
+            // as many as there are cells on the sphere's surface.
+            Background::Starfield(Starfield {
+                radius: (stars as f64 / (4. * PI)).sqrt(),
+                brightness,
+                milky_way: None,
+            })
+        } else {
+            Background::Classic
+        };
+        self.forget_irradiance();
+        self.dirty = Some(Region::full());
+    }
+
+    // Adds a band of the Milky Way across the starfield, running around the
+    // sky at right angles to the given direction, with the given half-width
+    // in degrees and brightness. A brightness of zero takes it away again.
+    // Without a starfield, this does nothing.
+    #[wasm_bindgen(js_name = setMilkyWay)]
+    pub fn set_milky_way(&mut self, px: f64, py: f64, pz: f64, degrees: f64, brightness: f64) {
+        let pole = Vec3::new(px, py, pz);
+        let starfield = match &mut self.backdrop {
+            Background::Starfield(starfield) => starfield,
+            _ => return,
+        };
+
+        starfield.milky_way = if brightness > 0. && degrees > 0. && pole.length_sqr() > 0. {
+            Some(MilkyWay {
+                pole: pole.unit(),
+                width: degrees.min(90.).to_radians().sin(),
+                brightness,
+            })
+        } else {
+            None
+        };
+        self.forget_irradiance();
+        self.dirty = Some(Region::full());
+    }
+}
@@ -0,0 +1,120 @@
+use crate::utils;
+use std::cell::Cell;
+use wasm_bindgen::prelude::*;
+
+// Counts the work done by the tracer while rendering a frame.
+#[derive(Clone, Default)]
+pub struct Counters {
+    pub primary_rays: Cell<u64>,
+    pub shadow_rays: Cell<u64>,
+    pub reflection_rays: Cell<u64>,
+    pub intersection_tests: Cell<u64>,
+    // The nodes or cells of the accelerator that rays passed through.
+    pub node_visits: Cell<u64>,
+    // The milliseconds spent building accelerators for the frame, and for the
+    // scene's geometry the last time it changed. The latter outlives frames.
+    pub build_time: Cell<f64>,
+    pub geometry_build_time: Cell<f64>,
+    // The total distance, in world units, travelled by rays before hitting
+    // something. This feeds the path heat AOV rather than `RenderStats`.
+    pub path_length: Cell<f64>,
+    // When the frame was started, in milliseconds.
+    pub started: Cell<f64>,
+}
+
+impl Counters {
+    pub fn bump(counter: &Cell<u64>, n: u64) {
+        counter.set(counter.get() + n);
+    }
+
+    pub fn travel(&self, distance: f64) {
+        self.path_length.set(self.path_length.get() + distance);
+    }
+
+    pub fn reset(&self) {
+        self.primary_rays.set(0);
+        self.shadow_rays.set(0);
+        self.reflection_rays.set(0);
+        self.intersection_tests.set(0);
+        self.node_visits.set(0);
+        self.build_time.set(0.);
+        self.path_length.set(0.);
+        self.started.set(utils::now());
+    }
+
+    // Puts every counter back to what it was in `saved`, undoing any work
+    // counted since.
+    pub fn restore(&self, saved: &Counters) {
+        self.primary_rays.set(saved.primary_rays.get());
+        self.shadow_rays.set(saved.shadow_rays.get());
+        self.reflection_rays.set(saved.reflection_rays.get());
+        self.intersection_tests.set(saved.intersection_tests.get());
+        self.node_visits.set(saved.node_visits.get());
+        self.build_time.set(saved.build_time.get());
+        self.geometry_build_time
+            .set(saved.geometry_build_time.get());
+        self.path_length.set(saved.path_length.get());
+        self.started.set(saved.started.get());
+    }
+
+    pub fn snapshot(&self) -> RenderStats {
+        RenderStats {
+            primary_rays: self.primary_rays.get(),
+            shadow_rays: self.shadow_rays.get(),
+            reflection_rays: self.reflection_rays.get(),
+            intersection_tests: self.intersection_tests.get(),
+            node_visits: self.node_visits.get(),
+            build_time: self.build_time.get() + self.geometry_build_time.get(),
+        }
+    }
+}
+
+// A snapshot of the work done to render the most recent frame. Counts are
+// returned to JS as numbers rather than BigInts, which are exact up to 2^53.
+#[wasm_bindgen]
+pub struct RenderStats {
+    primary_rays: u64,
+    shadow_rays: u64,
+    reflection_rays: u64,
+    intersection_tests: u64,
+    node_visits: u64,
+    build_time: f64,
+}
+
+#[wasm_bindgen]
+impl RenderStats {
+    #[wasm_bindgen(getter = primaryRays)]
+    pub fn primary_rays(&self) -> f64 {
+        self.primary_rays as f64
+    }
+
+    #[wasm_bindgen(getter = shadowRays)]
+    pub fn shadow_rays(&self) -> f64 {
+        self.shadow_rays as f64
+    }
+
+    #[wasm_bindgen(getter = reflectionRays)]
+    pub fn reflection_rays(&self) -> f64 {
+        self.reflection_rays as f64
+    }
+
+    #[wasm_bindgen(getter = intersectionTests)]
+    pub fn intersection_tests(&self) -> f64 {
+        self.intersection_tests as f64
+    }
+
+    // The nodes or cells of the accelerator that rays passed through, which
+    // together with `intersectionTests` makes up the cost of traversing it.
+    #[wasm_bindgen(getter = nodeVisits)]
+    pub fn node_visits(&self) -> f64 {
+        self.node_visits as f64
+    }
+
+    // The time, in milliseconds, spent building the accelerator: for the
+    // spheres the camera can see, which happens every frame, and for the rest
+    // of the scene, which happens whenever its spheres change.
+    #[wasm_bindgen(getter = buildTime)]
+    pub fn build_time(&self) -> f64 {
+        self.build_time
+    }
+}
@@ -0,0 +1,49 @@
+use crate::region::Region;
+use crate::{Image, Scene};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+impl Scene {
+    // Renders the scene twice side by side, as seen by a left and a right eye
+    // the given distance apart in metres, into the left and right halves of
+    // `img`, for cardboard-style VR viewers. People's eyes are about 0.064 m
+    // apart. The eyes look straight ahead in parallel, either side of the
+    // camera, and each half is rendered as a frame of its own. Photon mapping
+    // starts over for each eye, so its caustics don't converge across calls.
+    #[wasm_bindgen(js_name = renderStereo)]
+    pub fn render_stereo(&mut self, img: &mut Image, separation: f64) {
+        let (width, height) = (img.width, img.height);
+        let half = width / 2;
+        if half == 0 || !separation.is_finite() {
+            return;
+        }
+
+        // Render each eye into an image half as wide, with the same settings,
+        // then copy it across into its side of the frame.
+        let mut frame = std::mem::replace(&mut img.pixels, vec![0; half * height * 4]);
+        img.width = half;
+
+        let camera = self.camera;
+        for (side, offset) in [(0, -separation / 2.), (1, separation / 2.)] {
+            self.camera = camera;
+            self.camera.translate(&camera.right.scale(offset));
+            // Nothing traced for the other eye, such as photon mapping's hit
+            // points, carries over to this one.
+            self.invalidate(Region::full());
+            self.render(img);
+
+            for y in 0..height {
+                let row = &img.pixels[y * half * 4..(y + 1) * half * 4];
+                let start = (y * width + side * half) * 4;
+                frame[start..start + half * 4].copy_from_slice(row);
+            }
+        }
+        self.camera = camera;
+
+        img.pixels = frame;
+        img.width = width;
+
+        // Neither eye saw what the camera itself sees.
+        self.invalidate(Region::full());
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{png, utils, Image, Integrator, Scene};
+use wasm_bindgen::prelude::*;
+
+// A still captured by `captureStill`: the image as a PNG file, along with the
+// settings it was rendered with. These are also written into the file itself,
+// as text chunks.
+#[wasm_bindgen]
+pub struct Still {
+    png: Vec<u8>,
+    width: usize,
+    height: usize,
+    samples: usize,
+    integrator: Integrator,
+    render_time: f64,
+}
+
+#[wasm_bindgen]
+impl Still {
+    // The encoded PNG file, ready to save or turn into a Blob.
+    pub fn png(&self) -> Vec<u8> {
+        self.png.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn integrator(&self) -> Integrator {
+        self.integrator
+    }
+
+    // The milliseconds it took to render the still.
+    #[wasm_bindgen(getter = renderTime)]
+    pub fn render_time(&self) -> f64 {
+        self.render_time
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Renders the view as a `width` × `height` still, with `samples` paths per
+    // pixel spread over each pixel's area, and returns it as a PNG. This is
+    // meant for screenshots at a higher resolution or quality than the
+    // interactive renders, which it leaves alone: the frame still to be
+    // rendered and the statistics of the last one don't change. The film is
+    // stretched to fit, so the still should have the same aspect ratio as
+    // the frame. Pixels are written in sRGB with the neutral film curve, and
+    // without any guides or HUD. Photon mapping traces paths as PathTracing
+    // does, since its caustics take many frames to converge.
+    #[wasm_bindgen(js_name = captureStill)]
+    pub fn capture_still(&self, width: usize, height: usize, samples: usize) -> Still {
+        let samples = samples.max(1);
+        let stats = self.stats.clone();
+        let start = utils::now();
+
+        let mut img = Image::new(width, height);
+        let height_inv = 1. / height as f64;
+        let width_inv = 1. / width as f64;
+        let visible = self.visible_spheres();
+
+        for y in 0..height {
+            for x in 0..width {
+                let (color, alpha) =
+                    self.supersample(&visible, x, y, width_inv, height_inv, samples);
+                img.draw_alpha(x, y, &color, alpha);
+            }
+        }
+
+        let render_time = utils::now() - start;
+        self.stats.restore(&stats);
+
+        let eye = &self.camera.eye;
+        let text = [
+            ("Software", "raymond".to_string()),
+            ("Integrator", self.integrator.name().to_string()),
+            ("Samples", samples.to_string()),
+            ("Eye", format!("{} {} {}", eye.x, eye.y, eye.z)),
+            ("Render Time", format!("{:.0} ms", render_time)),
+        ];
+
+        Still {
+            png: png::encode(width, height, &img.pixels, &text),
+            width,
+            height,
+            samples,
+            integrator: self.integrator,
+            render_time,
+        }
+    }
+}
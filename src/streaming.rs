@@ -0,0 +1,151 @@
+use crate::region::Region;
+use crate::{Scene, Sphere, Vec3};
+use wasm_bindgen::prelude::*;
+
+// Pages spheres in and out of the tracer as the camera moves through a large
+// scene. Space is divided into cubic cells, and only the spheres overlapping
+// the camera's cell, or those within a margin of cells around it, are
+// resident: the rest are left out of every set of spheres that rays are
+// traced against, so they cost nothing until the camera comes near. The host
+// hears about spheres coming and going through `streamingChanges`, and can
+// load or free whatever it keeps for them, such as their textures.
+pub struct Streaming {
+    cell_size: f64,
+    // The number of cells either side of the camera's that stay resident.
+    margin: f64,
+    // The cell that the camera was in when residency was last worked out.
+    cell: [i64; 3],
+    // Spheres paged in or out since the host last asked.
+    changes: Vec<(usize, bool)>,
+}
+
+impl Streaming {
+    fn cell_of(&self, point: &Vec3) -> [i64; 3] {
+        [point.x, point.y, point.z].map(|c| (c / self.cell_size).floor() as i64)
+    }
+
+    // Whether `sphere` overlaps the cells around the camera's.
+    fn covers(&self, sphere: &Sphere) -> bool {
+        let c = &sphere.center;
+        let center = [c.x, c.y, c.z];
+        (0..3).all(|a| {
+            let lo = (self.cell[a] as f64 - self.margin) * self.cell_size;
+            let hi = (self.cell[a] as f64 + 1. + self.margin) * self.cell_size;
+            center[a] + sphere.radius >= lo && center[a] - sphere.radius <= hi
+        })
+    }
+}
+
+// The spheres paged in or out of the tracer since the last call to
+// `streamingChanges`, in the order it happened.
+#[wasm_bindgen]
+pub struct StreamingChanges {
+    changes: Vec<(usize, bool)>,
+}
+
+#[wasm_bindgen]
+impl StreamingChanges {
+    pub fn count(&self) -> usize {
+        self.changes.len()
+    }
+
+    // The index of the sphere that changed.
+    pub fn sphere(&self, i: usize) -> usize {
+        self.changes[i].0
+    }
+
+    // Whether the sphere was paged in, rather than out.
+    pub fn resident(&self, i: usize) -> bool {
+        self.changes[i].1
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Turns on streaming, keeping only the spheres within `margin` cells of
+    // the camera's, on a grid of `cell_size` world units, resident for
+    // tracing. A cell size of zero turns it off, and pages every sphere back
+    // in without reporting it.
+    #[wasm_bindgen(js_name = setStreaming)]
+    pub fn set_streaming(&mut self, cell_size: f64, margin: f64) {
+        if cell_size > 0. {
+            let changes = self
+                .streaming
+                .take()
+                .map_or_else(Vec::new, |streaming| streaming.changes);
+            let mut streaming = Streaming {
+                cell_size,
+                margin: margin.max(0.),
+                cell: [0; 3],
+                changes,
+            };
+            streaming.cell = streaming.cell_of(&self.camera.eye);
+            self.streaming = Some(streaming);
+        } else {
+            self.streaming = None;
+            for sphere in &mut self.spheres {
+                sphere.resident = true;
+            }
+        }
+
+        self.rebuild_geometry();
+        self.dirty = Some(Region::full());
+    }
+
+    // Returns the spheres paged in or out since the last call, for the host to
+    // stream whatever it keeps for them in or out in turn.
+    #[wasm_bindgen(js_name = streamingChanges)]
+    pub fn streaming_changes(&mut self) -> StreamingChanges {
+        let changes = match &mut self.streaming {
+            Some(streaming) => std::mem::take(&mut streaming.changes),
+            None => vec![],
+        };
+        StreamingChanges { changes }
+    }
+
+    #[wasm_bindgen(js_name = isSphereResident)]
+    pub fn is_sphere_resident(&self, index: usize) -> bool {
+        self.spheres
+            .get(index)
+            .is_some_and(|sphere| sphere.resident)
+    }
+}
+
+impl Scene {
+    // Works out which spheres are resident again, if the camera has moved
+    // into a different cell since the last time.
+    pub(crate) fn follow_camera(&mut self) {
+        if let Some(streaming) = &mut self.streaming {
+            let cell = streaming.cell_of(&self.camera.eye);
+            if cell == streaming.cell {
+                return;
+            }
+
+            streaming.cell = cell;
+            if self.update_residency() {
+                self.rebuild_geometry();
+                self.dirty = Some(Region::full());
+            }
+        }
+    }
+
+    // Brings each sphere's residency up to date, and returns whether that of
+    // any of them changed.
+    pub(crate) fn update_residency(&mut self) -> bool {
+        let streaming = match &mut self.streaming {
+            Some(streaming) => streaming,
+            None => return false,
+        };
+
+        let mut changed = false;
+        for (i, sphere) in self.spheres.iter_mut().enumerate() {
+            let resident = streaming.covers(sphere);
+            if resident != sphere.resident {
+                sphere.resident = resident;
+                streaming.changes.push((i, resident));
+                changed = true;
+            }
+        }
+        changed
+    }
+}
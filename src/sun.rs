@@ -0,0 +1,108 @@
+use crate::material::{Hit, Translucency};
+use crate::spheres::SphereSet;
+use crate::stats::Counters;
+use crate::{translucent_strength, Ray, Scene, Vec3, RGB};
+use wasm_bindgen::prelude::*;
+
+// A light so far away that its rays all arrive in parallel, like sunlight. It
+// has a direction but no position, and is just as bright everywhere, without
+// falling off with distance. Shadow rays head off towards it without end.
+#[derive(Copy, Clone)]
+pub struct DirectionalLight {
+    // A unit vector pointing from the scene towards the light.
+    towards: Vec3,
+    // The illuminance, in lux, on a surface facing the light head-on.
+    lux: f64,
+    // The color of the light, with a luminance of 1.
+    pub color: RGB,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec3, lux: f64) -> Self {
+        Self {
+            towards: direction.unit().scale(-1.),
+            lux,
+            color: RGB::white(),
+        }
+    }
+
+    // The illuminance in lux that a surface with the given normal would
+    // receive from the light, if nothing stood in the way.
+    pub fn unshadowed(&self, surface_normal: &Vec3) -> f64 {
+        self.lux * surface_normal.dot(&self.towards).max(0.)
+    }
+
+    // Returns the direction towards the light, and the illuminance in lux that
+    // `point` receives from it.
+    pub fn illuminate(
+        &self,
+        spheres: &SphereSet,
+        point: &Vec3,
+        surface_normal: &Vec3,
+        stats: &Counters,
+    ) -> (Vec3, f64) {
+        let cosine = surface_normal.dot(&self.towards);
+        if cosine <= 0. {
+            return (self.towards, 0.);
+        }
+
+        Counters::bump(&stats.shadow_rays, 1);
+        if spheres.occluded(&self.shadow_ray(point), f64::INFINITY, stats) {
+            return (self.towards, 0.);
+        }
+
+        (self.towards, self.lux * cosine)
+    }
+
+    // Like `illuminate`, for a point on a translucent sphere.
+    pub fn illuminate_translucent(
+        &self,
+        spheres: &SphereSet,
+        hit: &Hit,
+        translucency: Translucency,
+        stats: &Counters,
+    ) -> (Vec3, f64) {
+        let ray = self.shadow_ray(&hit.point);
+        let strength = translucent_strength(spheres, hit, &ray, f64::INFINITY, translucency, stats);
+        (self.towards, self.lux * strength)
+    }
+
+    pub fn shadow_ray(&self, point: &Vec3) -> Ray {
+        Ray::new(*point, self.towards)
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Adds a directional light, shining in the direction given, with the given
+    // illuminance in lux on surfaces that face it, and returns its index.
+    // Direct sunlight is around 100,000 lux.
+    #[wasm_bindgen(js_name = addDirectionalLight)]
+    pub fn add_directional_light(&mut self, dx: f64, dy: f64, dz: f64, lux: f64) -> usize {
+        self.suns
+            .push(DirectionalLight::new(Vec3::new(dx, dy, dz), lux));
+        self.invalidate_lighting();
+        self.suns.len() - 1
+    }
+
+    #[wasm_bindgen(js_name = setDirectionalLight)]
+    pub fn set_directional_light(&mut self, index: usize, dx: f64, dy: f64, dz: f64, lux: f64) {
+        let sun = match self.suns.get_mut(index) {
+            Some(sun) => sun,
+            None => return,
+        };
+        *sun = DirectionalLight {
+            color: sun.color,
+            ..DirectionalLight::new(Vec3::new(dx, dy, dz), lux)
+        };
+        self.invalidate_lighting();
+    }
+}
+
+impl Scene {
+    // Every directional light in the scene, including the physical sky's sun.
+    pub(crate) fn directional_lights(&self) -> impl Iterator<Item = &DirectionalLight> {
+        let sky_sun = self.sky.as_ref().and_then(|sky| sky.sun.as_ref());
+        self.suns.iter().chain(sky_sun)
+    }
+}
@@ -0,0 +1,76 @@
+use crate::clusters::LightClusters;
+use crate::color::chromaticity_to_srgb;
+use crate::{Scene, RGB};
+use wasm_bindgen::prelude::*;
+
+// Light colors given by their correlated color temperature, in kelvin: the
+// temperature of the black body whose glow they match. This is how lamps and
+// daylight are rated, from around 1,900K for candlelight and 2,700K to 3,200K
+// for tungsten bulbs, through 5,500K for midday sun, to 6,500K for an overcast
+// sky and beyond for blue sky. Colors are scaled to a luminance of 1, so that
+// a light's power still means what it says.
+
+// The temperatures that the approximation of the black body's color holds
+// between.
+const MIN_KELVIN: f64 = 1667.;
+const MAX_KELVIN: f64 = 25_000.;
+
+// The color of a black body at the given temperature, from the cubic spline
+// approximation of its chromaticity by Kang et al. (2002).
+pub fn kelvin_to_rgb(kelvin: f64) -> RGB {
+    let t = kelvin.clamp(MIN_KELVIN, MAX_KELVIN);
+    let (t2, t3) = (t * t, t * t * t);
+
+    let x = if t <= 4000. {
+        -0.266_123_9e9 / t3 - 0.234_358_9e6 / t2 + 0.877_695_6e3 / t + 0.179_910
+    } else {
+        -3.025_846_9e9 / t3 + 2.107_037_9e6 / t2 + 0.222_634_7e3 / t + 0.240_390
+    };
+
+    let (x2, x3) = (x * x, x * x * x);
+    let y = if t <= 2222. {
+        -1.106_381_4 * x3 - 1.348_110_20 * x2 + 2.185_558_32 * x - 0.202_196_83
+    } else if t <= 4000. {
+        -0.954_947_6 * x3 - 1.374_185_93 * x2 + 2.091_370_15 * x - 0.167_488_67
+    } else {
+        3.081_758_0 * x3 - 5.873_386_70 * x2 + 3.751_129_97 * x - 0.370_014_83
+    };
+
+    chromaticity_to_srgb(x, y)
+}
+
+// The color of a light at the given temperature, where zero or less means
+// plain white.
+fn light_color(kelvin: f64) -> RGB {
+    if kelvin > 0. {
+        kelvin_to_rgb(kelvin)
+    } else {
+        RGB::white()
+    }
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Colors the point light at `index` by its color temperature, in kelvin.
+    // Zero turns it back to white.
+    #[wasm_bindgen(js_name = setLightTemperature)]
+    pub fn set_light_temperature(&mut self, index: usize, kelvin: f64) {
+        match self.lights.get_mut(index) {
+            Some(light) => light.color = light_color(kelvin),
+            None => return,
+        }
+        self.light_clusters = LightClusters::new(&self.lights);
+        self.invalidate_lighting();
+    }
+
+    // Colors the directional light at `index` by its color temperature, in
+    // kelvin. Zero turns it back to white.
+    #[wasm_bindgen(js_name = setDirectionalLightTemperature)]
+    pub fn set_directional_light_temperature(&mut self, index: usize, kelvin: f64) {
+        match self.suns.get_mut(index) {
+            Some(sun) => sun.color = light_color(kelvin),
+            None => return,
+        }
+        self.invalidate_lighting();
+    }
+}
@@ -0,0 +1,543 @@
+use crate::bc1::Bc1Image;
+use crate::color::{srgb_decode, srgb_encode};
+use crate::material::{basis, Hit, Lambertian};
+use crate::noise::{noise, turbulence};
+use crate::{Scene, Vec3, RGB};
+use std::f64::consts::PI;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+
+// A color that varies over a surface, for use in place of a solid one.
+pub trait Texture {
+    fn value(&self, hit: &Hit) -> RGB;
+}
+
+impl Texture for RGB {
+    fn value(&self, _hit: &Hit) -> RGB {
+        *self
+    }
+}
+
+// The coordinates that a procedural texture is laid out in. In world space,
+// objects slide through the texture as they move; in object space, the texture
+// moves along with them.
+#[derive(Copy, Clone, PartialEq)]
+pub enum Space {
+    World,
+    Object,
+}
+
+// A 3D checkerboard of cubes, `scale` units across, alternating between two
+// colors. Slicing it with a plane makes the classic checkered floor.
+pub struct Checker {
+    even: RGB,
+    odd: RGB,
+    scale: f64,
+    space: Space,
+}
+
+impl Checker {
+    pub fn new(even: RGB, odd: RGB, scale: f64, space: Space) -> Self {
+        Self {
+            even,
+            odd,
+            scale,
+            space,
+        }
+    }
+}
+
+impl Texture for Checker {
+    fn value(&self, hit: &Hit) -> RGB {
+        let p = match self.space {
+            Space::World => &hit.point,
+            Space::Object => &hit.local,
+        };
+
+        let cell = |c: f64| (c / self.scale).floor() as i64;
+        if (cell(p.x) + cell(p.y) + cell(p.z)).rem_euclid(2) == 0 {
+            self.even
+        } else {
+            self.odd
+        }
+    }
+}
+
+// Veined marble: stripes running across the X axis, warped by turbulence into
+// irregular veins of one color through a body of another.
+pub struct Marble {
+    body: RGB,
+    veins: RGB,
+    scale: f64,
+}
+
+impl Marble {
+    pub fn new(body: RGB, veins: RGB, scale: f64) -> Self {
+        Self { body, veins, scale }
+    }
+}
+
+impl Texture for Marble {
+    fn value(&self, hit: &Hit) -> RGB {
+        let p = hit.local.scale(1. / self.scale);
+        let stripe = (PI * (p.x + 2. * turbulence(&p, 4))).sin();
+
+        // Sharpen the stripes into thin veins.
+        let vein = (1. - stripe.abs()).powi(6);
+        self.body.scale(1. - vein).add(&self.veins.scale(vein))
+    }
+}
+
+// Wood grain: concentric rings around the Y axis, as if the object had been
+// carved out of a log standing upright, wobbled a little by noise.
+pub struct Wood {
+    light: RGB,
+    dark: RGB,
+    scale: f64,
+}
+
+impl Wood {
+    pub fn new(light: RGB, dark: RGB, scale: f64) -> Self {
+        Self { light, dark, scale }
+    }
+}
+
+impl Texture for Wood {
+    fn value(&self, hit: &Hit) -> RGB {
+        let p = hit.local.scale(1. / self.scale);
+        let radius = (p.x * p.x + p.z * p.z).sqrt() + 0.15 * noise(&p.scale(2.));
+        let ring = (radius * 4.).fract();
+
+        // Each ring darkens gradually, then ends sharply.
+        let t = ring * ring;
+        self.light.scale(1. - t).add(&self.dark.scale(t))
+    }
+}
+
+// An image wrapped around a sphere, like a map around a globe. The middle of
+// the image faces -Z, towards the default camera, its left and right edges
+// meet at the back, and its top and bottom edges are squeezed into the poles.
+//
+// Lookups are filtered bilinearly between the four nearest texels. Where the
+// surface is far enough away that a pixel covers several texels, they come
+// from a mip chain instead: copies of the image halved in size again and
+// again, so that a single lookup stands in for the average of many texels,
+// and the texture doesn't shimmer as the camera moves.
+//
+// Texels are kept as the bytes they were uploaded as, three to a texel, and
+// only decoded as they're looked up. Decoded, they would take up eight times
+// as much memory. Compressed textures take up a sixth of that again.
+pub struct ImageTexture {
+    levels: Levels,
+    decoder: Decoder,
+}
+
+// The full-size image first, then each level half the size of the last.
+enum Levels {
+    Bytes(Vec<MipLevel<[u8; 3]>>),
+    Bc1(Vec<Bc1Image>),
+}
+
+// Decodes texels kept as bytes, by looking up the linear value that each byte
+// stands for in the image's encoding.
+pub struct Decoder {
+    values: Vec<f64>,
+}
+
+impl Decoder {
+    pub fn new(encoding: TextureEncoding) -> Self {
+        Self {
+            values: (0..=u8::MAX).map(|b| encoding.decode(b)).collect(),
+        }
+    }
+
+    pub fn decode(&self, [red, green, blue]: [u8; 3]) -> RGB {
+        let value = |byte: u8| self.values[byte as usize];
+        RGB::new(value(red), value(green), value(blue))
+    }
+}
+
+// How the bytes of an uploaded image stand for the values they hold.
+#[wasm_bindgen]
+#[derive(Copy, Clone, PartialEq)]
+pub enum TextureEncoding {
+    // Each byte is proportional to the value it stands for, as in normal and
+    // height maps, and anything else that holds data rather than colors.
+    Linear,
+    // Bytes are encoded with the sRGB transfer function, as in almost every
+    // image drawn on or read back from a canvas, and photographs.
+    Srgb,
+}
+
+impl TextureEncoding {
+    fn decode(self, byte: u8) -> f64 {
+        let value = byte as f64 / 255.;
+        match self {
+            TextureEncoding::Linear => value,
+            TextureEncoding::Srgb => srgb_decode(value),
+        }
+    }
+
+    fn encode(self, value: f64) -> u8 {
+        let value = match self {
+            TextureEncoding::Linear => value,
+            TextureEncoding::Srgb => srgb_encode(value),
+        };
+        (value.clamp(0., 1.) * 255.).round() as u8
+    }
+}
+
+// An image, row by row from the top, whose texels are colors by default, or
+// anything that can be turned into one.
+pub struct MipLevel<T = RGB> {
+    width: usize,
+    height: usize,
+    texels: Vec<T>,
+}
+
+impl<T: Copy> MipLevel<T> {
+    pub fn new(width: usize, height: usize, texels: Vec<T>) -> Self {
+        Self {
+            width,
+            height,
+            texels,
+        }
+    }
+
+    // Box filters each 2 × 2 block of texels down to one, averaging their
+    // colors. Odd rows and columns at the edge are averaged with themselves.
+    fn halve_with(&self, decode: impl Fn(T) -> RGB, encode: impl Fn(RGB) -> T) -> Self {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut texels = Vec::with_capacity(width * height);
+        let texel = |x: usize, y: usize| decode(self.texels[y * self.width + x]);
+
+        for y in 0..height {
+            for x in 0..width {
+                let (x0, y0) = ((2 * x).min(self.width - 1), (2 * y).min(self.height - 1));
+                let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+                let sum = texel(x0, y0)
+                    .add(&texel(x1, y0))
+                    .add(&texel(x0, y1))
+                    .add(&texel(x1, y1));
+                texels.push(encode(sum.scale(0.25)));
+            }
+        }
+
+        Self {
+            width,
+            height,
+            texels,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn texel(&self, x: usize, y: usize) -> T {
+        self.texels[y * self.width + x]
+    }
+
+    fn blend_with(&self, u: f64, v: f64, wrap: bool, decode: impl Fn(T) -> RGB) -> RGB {
+        blend(self.width, self.height, u, v, wrap, |x, y| {
+            decode(self.texel(x, y))
+        })
+    }
+}
+
+// Blends the four texels of a `width` × `height` image nearest to (u, v),
+// given a way of looking them up. Images that wrap do so from left to right.
+pub fn blend(
+    width: usize,
+    height: usize,
+    u: f64,
+    v: f64,
+    wrap: bool,
+    texel: impl Fn(usize, usize) -> RGB,
+) -> RGB {
+    let x = u * width as f64 - 0.5;
+    let x = if wrap {
+        x
+    } else {
+        x.clamp(0., (width - 1) as f64)
+    };
+    let y = (v * height as f64 - 0.5).clamp(0., (height - 1) as f64);
+    let (fx, fy) = (x - x.floor(), y - y.floor());
+
+    let (x0, x1) = if wrap {
+        let x0 = (x.floor() as isize).rem_euclid(width as isize) as usize;
+        (x0, (x0 + 1) % width)
+    } else {
+        let x0 = x.floor() as usize;
+        (x0, (x0 + 1).min(width - 1))
+    };
+    let y0 = y.floor() as usize;
+    let y1 = (y0 + 1).min(height - 1);
+
+    let top = texel(x0, y0).scale(1. - fx).add(&texel(x1, y0).scale(fx));
+    let bottom = texel(x0, y1).scale(1. - fx).add(&texel(x1, y1).scale(fx));
+    top.scale(1. - fy).add(&bottom.scale(fy))
+}
+
+impl MipLevel {
+    // Blends the four texels nearest to (u, v). The image wraps around from
+    // left to right, but not from top to bottom, since those are the poles.
+    pub fn bilinear(&self, u: f64, v: f64) -> RGB {
+        self.blend_with(u, v, true, |c| c)
+    }
+}
+
+impl MipLevel<[u8; 3]> {
+    // Keeps the red, green and blue bytes of an image given as RGBA bytes,
+    // row by row from the top.
+    pub fn from_rgba(width: usize, height: usize, rgba: &[u8]) -> Self {
+        let texels = rgba
+            .chunks_exact(4)
+            .take(width * height)
+            .map(|p| [p[0], p[1], p[2]])
+            .collect();
+        Self::new(width, height, texels)
+    }
+
+    // Blends the four texels nearest to (u, v), decoding them first, for
+    // images that don't wrap around at all, such as the faces of a cubemap.
+    // Past an edge, the texels along it carry on.
+    pub fn bilinear_clamped(&self, u: f64, v: f64, decoder: &Decoder) -> RGB {
+        self.blend_with(u, v, false, |t| decoder.decode(t))
+    }
+}
+
+impl ImageTexture {
+    // Builds a texture from RGBA bytes, row by row from the top, in the given
+    // encoding. The alpha channel is ignored.
+    pub fn new(width: usize, height: usize, rgba: &[u8], encoding: TextureEncoding) -> Self {
+        let decoder = Decoder::new(encoding);
+
+        // Each level is averaged from the decoded values of the last, so that
+        // sRGB images don't darken as they shrink.
+        let decode = |t: [u8; 3]| decoder.decode(t);
+        let encode = |c: RGB| [c.red, c.green, c.blue].map(|v| encoding.encode(v));
+        let mut levels = vec![MipLevel::from_rgba(width, height, rgba)];
+        while let Some(last) = levels.last().filter(|l| l.width > 1 || l.height > 1) {
+            let next = last.halve_with(decode, encode);
+            levels.push(next);
+        }
+
+        Self {
+            levels: Levels::Bytes(levels),
+            decoder,
+        }
+    }
+
+    // Compresses every level of the texture in the BC1 block format.
+    pub fn compress(self) -> Self {
+        let levels = match self.levels {
+            Levels::Bytes(levels) => Levels::Bc1(levels.iter().map(Bc1Image::compress).collect()),
+            compressed => compressed,
+        };
+        Self { levels, ..self }
+    }
+
+    fn level_count(&self) -> usize {
+        match &self.levels {
+            Levels::Bytes(levels) => levels.len(),
+            Levels::Bc1(levels) => levels.len(),
+        }
+    }
+
+    fn width(&self) -> usize {
+        match &self.levels {
+            Levels::Bytes(levels) => levels[0].width,
+            Levels::Bc1(levels) => levels[0].width(),
+        }
+    }
+
+    fn bilinear(&self, level: usize, u: f64, v: f64) -> RGB {
+        match &self.levels {
+            Levels::Bytes(levels) => {
+                levels[level].blend_with(u, v, true, |t| self.decoder.decode(t))
+            }
+            Levels::Bc1(levels) => levels[level].bilinear(u, v, &self.decoder),
+        }
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, hit: &Hit) -> RGB {
+        let (u, v) = spherical_uv(&hit.local);
+
+        // How many texels of the full-size image the pixel spans, measured
+        // around the equator, where they're widest.
+        let circumference = 2. * PI * hit.local.length();
+        let texels = hit.spread * self.width() as f64 / circumference;
+
+        let lod = texels.log2().clamp(0., (self.level_count() - 1) as f64);
+        let level = lod.floor() as usize;
+        let t = lod - level as f64;
+
+        let near = self.bilinear(level, u, v);
+        if t == 0. {
+            return near;
+        }
+        let far = self.bilinear(level + 1, u, v);
+        near.scale(1. - t).add(&far.scale(t))
+    }
+}
+
+// Whether `len` values are enough for a `width` × `height` image, with the
+// given number of channels per texel. Empty images never are, and nor are
+// those too large to address.
+pub fn holds_image(len: usize, width: usize, height: usize, channels: usize) -> bool {
+    let needed = width
+        .checked_mul(height)
+        .and_then(|n| n.checked_mul(channels));
+    width > 0 && height > 0 && needed.is_some_and(|n| len >= n)
+}
+
+// Maps a point relative to the center of a sphere to texture coordinates in
+// [0, 1], with u running around the equator and v from the north pole down.
+pub fn spherical_uv(local: &Vec3) -> (f64, f64) {
+    let d = local.unit();
+    let u = 0.5 + d.x.atan2(-d.z) / (2. * PI);
+    let v = 0.5 - d.y.clamp(-1., 1.).asin() / PI;
+    (u, v)
+}
+
+// The directions in which u and v increase at a point on the unit sphere,
+// as unit vectors. Both are taken from the surface as seen from outside, so
+// that the tangent points right and the bitangent up, as in an image.
+pub fn tangent_frame(normal: &Vec3) -> (Vec3, Vec3) {
+    let tangent = normal.cross(&Vec3::new(0., 1., 0.));
+
+    // At the poles, u runs in every direction at once, so any will do.
+    if tangent.length_sqr() < 1e-12 {
+        return basis(normal);
+    }
+
+    let tangent = tangent.unit();
+    (tangent, tangent.cross(normal))
+}
+
+// Tilts a sphere's normal according to a texel of a tangent-space normal map,
+// whose red, green and blue channels hold the components of the new normal
+// along the tangent, the bitangent and the original normal.
+pub fn perturb(normal: &Vec3, texel: &RGB) -> Vec3 {
+    let (tangent, bitangent) = tangent_frame(normal);
+    let component = |c: f64| 2. * c - 1.;
+
+    let perturbed = tangent
+        .scale(component(texel.red))
+        .add(&bitangent.scale(component(texel.green)))
+        .add(&normal.scale(component(texel.blue)));
+
+    if perturbed.length_sqr() < 1e-12 {
+        *normal
+    } else {
+        perturbed.unit()
+    }
+}
+
+// The procedural patterns that spheres can be given from JS.
+#[wasm_bindgen]
+#[derive(Copy, Clone, PartialEq)]
+pub enum Pattern {
+    Marble,
+    Wood,
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Gives the sphere at `index` a matte material with a procedural pattern,
+    // laid out in the sphere's own space so that it moves along with it. The
+    // scale is the size of the pattern's features, in world units.
+    #[wasm_bindgen(js_name = setSpherePattern)]
+    pub fn set_sphere_pattern(&mut self, index: usize, pattern: Pattern, scale: f64) {
+        let material = match pattern {
+            Pattern::Marble => Lambertian::new(Marble::new(
+                RGB::new(0.93, 0.92, 0.9),
+                RGB::new(0.35, 0.36, 0.4),
+                scale,
+            )),
+            Pattern::Wood => Lambertian::new(Wood::new(
+                RGB::new(0.8, 0.6, 0.38),
+                RGB::new(0.45, 0.28, 0.14),
+                scale,
+            )),
+        };
+        match self.spheres.get_mut(index) {
+            Some(sphere) => sphere.material = Rc::new(material),
+            None => return,
+        }
+        self.invalidate_lighting();
+    }
+
+    // Wraps an image, given as RGBA bytes in the given encoding, around the
+    // sphere at `index`, replacing its material with a matte one. Images
+    // taken from a canvas are sRGB.
+    #[wasm_bindgen(js_name = setSphereTexture)]
+    pub fn set_sphere_texture(
+        &mut self,
+        index: usize,
+        width: usize,
+        height: usize,
+        rgba: &[u8],
+        encoding: TextureEncoding,
+    ) {
+        if !holds_image(rgba.len(), width, height, 4) {
+            return;
+        }
+        let sphere = match self.spheres.get_mut(index) {
+            Some(sphere) => sphere,
+            None => return,
+        };
+
+        let mut texture = ImageTexture::new(width, height, rgba, encoding);
+        if self.texture_compression {
+            texture = texture.compress();
+        }
+        sphere.material = Rc::new(Lambertian::new(texture));
+        self.invalidate_lighting();
+    }
+
+    // Compresses the images given to later calls to `setSphereTexture` in the
+    // BC1 block format, as GPUs do, so that they take up a sixth of the
+    // memory, at the cost of some banding and blockiness. Textures already
+    // set are left as they are.
+    #[wasm_bindgen(js_name = setTextureCompression)]
+    pub fn set_texture_compression(&mut self, enabled: bool) {
+        self.texture_compression = enabled;
+    }
+
+    // Wraps a tangent-space normal map, given as RGBA bytes, around the sphere
+    // at `index`, in the same way as `setSphereTexture`. Passing an empty image
+    // removes it.
+    #[wasm_bindgen(js_name = setSphereNormalMap)]
+    pub fn set_sphere_normal_map(
+        &mut self,
+        index: usize,
+        width: usize,
+        height: usize,
+        rgba: &[u8],
+    ) {
+        let sphere = match self.spheres.get_mut(index) {
+            Some(sphere) => sphere,
+            None => return,
+        };
+        sphere.normal_map = if !holds_image(rgba.len(), width, height, 4) {
+            None
+        } else {
+            Some(ImageTexture::new(
+                width,
+                height,
+                rgba,
+                TextureEncoding::Linear,
+            ))
+        };
+        self.invalidate_lighting();
+    }
+}
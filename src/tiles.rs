@@ -0,0 +1,95 @@
+use std::ops::Range;
+use wasm_bindgen::prelude::*;
+
+// Progressive renders proceed one square tile of pixels at a time.
+pub const TILE_SIZE: usize = 16;
+
+// The order in which tiles are rendered by progressive renders.
+#[wasm_bindgen]
+#[derive(Copy, Clone, PartialEq)]
+pub enum RenderOrder {
+    // Left to right, then top to bottom.
+    Scanline,
+    // Along a Hilbert curve, which keeps consecutive tiles next to each other
+    // and so makes the best use of the cache.
+    Hilbert,
+    // Outwards from the center of the image, where the viewer's attention
+    // usually lies.
+    Spiral,
+}
+
+pub struct Tile {
+    pub xs: Range<usize>,
+    pub ys: Range<usize>,
+}
+
+// Splits an image of the given size into tiles, listed in the given order.
+pub fn tiles(width: usize, height: usize, order: RenderOrder) -> Vec<Tile> {
+    let cols = width.div_ceil(TILE_SIZE);
+    let rows = height.div_ceil(TILE_SIZE);
+
+    let mut coords: Vec<(usize, usize)> = match order {
+        RenderOrder::Scanline | RenderOrder::Spiral => (0..rows)
+            .flat_map(|row| (0..cols).map(move |col| (col, row)))
+            .collect(),
+        RenderOrder::Hilbert => {
+            // Walk a Hilbert curve over the smallest power-of-two grid that
+            // covers the image, skipping cells that fall outside of it.
+            let n = cols.max(rows).next_power_of_two();
+            (0..n * n)
+                .map(|d| hilbert(n, d))
+                .filter(|&(col, row)| col < cols && row < rows)
+                .collect()
+        }
+    };
+
+    if order == RenderOrder::Spiral {
+        // Sort tiles into concentric rings around the center, and each ring by
+        // angle. The sort is stable, so the order is fully deterministic.
+        let cx = (cols as f64 - 1.) / 2.;
+        let cy = (rows as f64 - 1.) / 2.;
+        let key = |&(col, row): &(usize, usize)| {
+            let dx = col as f64 - cx;
+            let dy = row as f64 - cy;
+            (dx.abs().max(dy.abs()), dy.atan2(dx))
+        };
+
+        coords.sort_by(|a, b| key(a).partial_cmp(&key(b)).unwrap());
+    }
+
+    coords
+        .into_iter()
+        .map(|(col, row)| Tile {
+            xs: col * TILE_SIZE..usize::min((col + 1) * TILE_SIZE, width),
+            ys: row * TILE_SIZE..usize::min((row + 1) * TILE_SIZE, height),
+        })
+        .collect()
+}
+
+// Converts a distance `d` along the Hilbert curve filling an n × n grid, where
+// n is a power of two, into the coordinates of the cell it lands on.
+fn hilbert(n: usize, d: usize) -> (usize, usize) {
+    let (mut x, mut y) = (0, 0);
+    let mut t = d;
+    let mut s = 1;
+
+    while s < n {
+        let rx = 1 & (t / 2);
+        let ry = 1 & (t ^ rx);
+
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2;
+    }
+
+    (x, y)
+}
@@ -0,0 +1,122 @@
+use crate::rng::Rng;
+use crate::spheres::SphereSet;
+use crate::stats::Counters;
+use crate::{Integrator, Light, Ray, Sampled, Scene, Sphere, Vec3, RGB};
+use wasm_bindgen::prelude::*;
+
+// Transparent spheres let some of what lies behind them show through, as if
+// they had been painted onto a sheet of clear film: the ray carries straight
+// on through the sphere, without bending, and the sphere's own color is laid
+// over what it finds. This is far cheaper than a dielectric, and suits
+// ghosted objects and simple glass that doesn't need to refract. Their
+// shadows are dimmed and tinted to match, rather than solid, as are those of
+// glass spheres.
+
+// The spheres that dim the shadow rays passing through them.
+pub fn ghosts(spheres: &[Sphere]) -> SphereSet {
+    SphereSet::new(
+        spheres
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.resident && s.visibility.shadow && lets_light_through(s)),
+    )
+}
+
+// Whether some light gets through the sphere to its shadow.
+pub fn lets_light_through(sphere: &Sphere) -> bool {
+    sphere.transparency > 0. || sphere.material.transmission().is_some()
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Sets the fraction of light that passes straight through the sphere at
+    // `index`, from 0 for an opaque sphere to 1 for an invisible one.
+    #[wasm_bindgen(js_name = setSphereTransparency)]
+    pub fn set_sphere_transparency(&mut self, index: usize, transparency: f64) {
+        match self.spheres.get_mut(index) {
+            Some(sphere) => sphere.transparency = transparency.clamp(0., 1.),
+            None => return,
+        }
+        self.rebuild_geometry();
+        self.invalidate_lighting();
+    }
+}
+
+impl Scene {
+    // Computes the color seen through the transparent sphere that `ray` hits
+    // at a distance of `t`, of whatever lies beyond it.
+    pub(crate) fn see_through(
+        &self,
+        ray: &Ray,
+        (sphere, t): (&Sphere, f64),
+        depth: u8,
+        throughput: f64,
+        sampled: Sampled,
+        rng: &mut Rng,
+    ) -> RGB {
+        // Rays entering the sphere carry on from where they leave it again.
+        // Those already inside it carry on from the point they hit.
+        let point = ray.point_at(t);
+        let center = sphere.center_at(self.shutter.time.get());
+        let chord = (-2. * point.subtract(&center).dot(&ray.direction)).max(0.);
+        let beyond = Ray::new(ray.point_at(t + chord), ray.direction);
+
+        Counters::bump(&self.stats.reflection_rays, 1);
+        let throughput = throughput * sphere.transparency;
+        self.light(&beyond, depth, throughput, sampled, rng)
+    }
+
+    // The fraction of the light from `light`, in each color, that reaches
+    // `point` through any transparent or glass spheres in between. Opaque
+    // spheres are left to the shadow test proper.
+    pub(crate) fn transmittance(&self, point: &Vec3, light: &Light) -> RGB {
+        if self.ghosts.index.is_empty() {
+            return RGB::white();
+        }
+
+        let (ray, len) = Ray::cast(point, &light.pos);
+        self.transmittance_of(&ray, len, |i| self.shadows(light, i))
+    }
+
+    // Like `transmittance`, for the light travelling back along a shadow ray
+    // from a light `len` away.
+    pub(crate) fn transmittance_along(&self, ray: &Ray, len: f64) -> RGB {
+        self.transmittance_of(ray, len, |_| true)
+    }
+
+    // Like `transmittance_along`, through only the spheres at the positions
+    // for which `casts_shadow` holds.
+    fn transmittance_of(&self, ray: &Ray, len: f64, casts_shadow: impl Fn(usize) -> bool) -> RGB {
+        if self.ghosts.index.is_empty() {
+            return RGB::white();
+        }
+
+        // Photon mapping and caustic photon maps bring the light through glass
+        // as caustics, which would only be counted twice if shadow rays let
+        // it through too.
+        let photons = self.integrator == Integrator::PhotonMapping || self.caustics.is_some();
+
+        self.ghosts
+            .crossed(ray, len, &self.stats)
+            .into_iter()
+            .filter(|&(i, _)| casts_shadow(i))
+            .map(|(i, t)| {
+                let sphere = &self.spheres[i];
+                let glass = match sphere.material.transmission() {
+                    Some(transmission) if !photons => transmission,
+                    _ => RGB::black(),
+                };
+                if sphere.transparency == 0. {
+                    return glass;
+                }
+
+                // A transparent sphere filters light through its own color,
+                // like a sheet of colored film, and whatever light doesn't
+                // pass through the film meets the material beneath it.
+                let hit = sphere.hit(ray.point_at(t), self.shutter.time.get(), 0.);
+                let film = sphere.material.color(&hit).scale(sphere.transparency);
+                film.add(&glass.scale(1. - sphere.transparency))
+            })
+            .fold(RGB::white(), |acc, c| acc.tint(&c))
+    }
+}
@@ -0,0 +1,72 @@
+use crate::region::Region;
+use crate::Scene;
+use std::f64::consts::PI;
+use wasm_bindgen::prelude::*;
+
+// The units that light is measured in, so that values taken from lighting
+// references can be used as they are:
+//
+// - Distances are in metres.
+// - Point lights are rated by their luminous flux, in lumens, which they give
+//   off equally in every direction.
+// - The light falling on a surface is its illuminance, in lux (lumens per
+//   square metre).
+// - Glowing surfaces are rated by their luminance, in nits (candela per
+//   square metre).
+//
+// The camera's response is set by its white point: the illuminance at which a
+// white diffuse surface, facing the light, comes out at full brightness.
+
+// The luminous efficacy of light at 555nm, where the eye is most sensitive.
+// Other light sources are far less efficient: an incandescent bulb manages
+// around 15 lumens per watt, and an LED bulb around 100.
+pub const MAX_LUMINOUS_EFFICACY: f64 = 683.;
+
+// The luminous flux of a point light with the given luminous intensity, in
+// candela, in every direction.
+pub fn candela_to_lumens(candela: f64) -> f64 {
+    4. * PI * candela
+}
+
+// The illuminance that a white diffuse surface must receive to have the given
+// luminance, which is how emissive surfaces are stored so that they can be
+// added straight to reflected light.
+pub fn nits_to_lux(nits: f64) -> f64 {
+    PI * nits
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Adds a point light with the given luminous intensity, in candela, and
+    // returns its index.
+    #[wasm_bindgen(js_name = addLightCandela)]
+    pub fn add_light_candela(&mut self, x: f64, y: f64, z: f64, candela: f64) -> usize {
+        self.add_light(x, y, z, candela_to_lumens(candela))
+    }
+
+    // Adds a point light drawing the given electrical power, in watts, and
+    // returns its index. The efficacy is the number of lumens the light gives
+    // off per watt, at most `MAX_LUMINOUS_EFFICACY`.
+    #[wasm_bindgen(js_name = addLightWatts)]
+    pub fn add_light_watts(&mut self, x: f64, y: f64, z: f64, watts: f64, efficacy: f64) -> usize {
+        let efficacy = efficacy.min(MAX_LUMINOUS_EFFICACY);
+        self.add_light(x, y, z, watts * efficacy)
+    }
+
+    // Sets the illuminance, in lux, at which a white diffuse surface facing
+    // the light is drawn at full brightness. This defaults to 1 lux, which
+    // suits the dim demo scene; a brightly lit office is closer to 500.
+    #[wasm_bindgen(js_name = setWhitePoint)]
+    pub fn set_white_point(&mut self, lux: f64) {
+        self.white_point = lux;
+        self.forget_irradiance();
+        self.dirty = Some(Region::full());
+    }
+}
+
+impl Scene {
+    // The factor converting illuminance, in lux, to rendered color values.
+    pub(crate) fn response(&self) -> f64 {
+        1. / self.white_point
+    }
+}
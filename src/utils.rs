@@ -15,3 +15,38 @@ cfg_if! {
         pub fn set_panic_hook() {}
     }
 }
+
+cfg_if! {
+    // `std::time` isn't available when targeting wasm, so in the browser we
+    // read the time through JavaScript instead.
+    if #[cfg(target_arch = "wasm32")] {
+        // Returns the current time in milliseconds.
+        pub fn now() -> f64 {
+            js_sys::Date::now()
+        }
+    } else {
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        // Returns the current time in milliseconds.
+        pub fn now() -> f64 {
+            let elapsed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+            elapsed.as_secs_f64() * 1000.
+        }
+    }
+}
+
+cfg_if! {
+    // The memory in use is only known when targeting wasm, where it's the size
+    // of the module's linear memory, which grows but never shrinks.
+    if #[cfg(target_arch = "wasm32")] {
+        // Returns the number of bytes of memory in use, if known.
+        pub fn memory_bytes() -> Option<usize> {
+            Some(core::arch::wasm32::memory_size(0) * 65536)
+        }
+    } else {
+        // Returns the number of bytes of memory in use, if known.
+        pub fn memory_bytes() -> Option<usize> {
+            None
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use crate::emitters::emitters;
+use crate::region::Region;
+use crate::spheres::SphereSet;
+use crate::transparency::{ghosts, lets_light_through};
+use crate::{Scene, Sphere};
+use wasm_bindgen::prelude::*;
+
+// Which kinds of ray can see an object. An object hidden from the camera can
+// still show up in reflections and cast shadows, which makes it a light
+// blocker or the stand-in for something in a photograph; one that casts no
+// shadow can light up its surroundings without darkening them.
+#[derive(Copy, Clone)]
+pub struct Visibility {
+    pub camera: bool,
+    // Covers every secondary ray: reflections, refractions and bounces.
+    pub reflection: bool,
+    pub shadow: bool,
+}
+
+impl Default for Visibility {
+    fn default() -> Self {
+        Self {
+            camera: true,
+            reflection: true,
+            shadow: true,
+        }
+    }
+}
+
+// The spheres that secondary rays can hit.
+pub fn reflected(spheres: &[Sphere]) -> SphereSet {
+    SphereSet::new(
+        spheres
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.resident && s.visibility.reflection),
+    )
+}
+
+// The spheres that block shadow rays outright. Transparent ones only dim
+// them, and are kept apart.
+pub fn occluders(spheres: &[Sphere]) -> SphereSet {
+    SphereSet::new(
+        spheres
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.resident && s.visibility.shadow && !lets_light_through(s)),
+    )
+}
+
+#[wasm_bindgen]
+impl Scene {
+    // Sets which kinds of ray can see the sphere at `index`: camera rays,
+    // secondary rays (reflections, refractions and bounces), and shadow rays.
+    #[wasm_bindgen(js_name = setSphereVisibility)]
+    pub fn set_sphere_visibility(
+        &mut self,
+        index: usize,
+        camera: bool,
+        reflection: bool,
+        shadow: bool,
+    ) {
+        let sphere = match self.spheres.get_mut(index) {
+            Some(sphere) => sphere,
+            None => return,
+        };
+        sphere.visibility = Visibility {
+            camera,
+            reflection,
+            shadow,
+        };
+        self.rebuild_geometry();
+        self.dirty = Some(Region::full());
+    }
+}
+
+impl Scene {
+    pub(crate) fn rebuild_geometry(&mut self) {
+        self.update_residency();
+        self.stale_visible = true;
+        self.forget_caustics();
+        self.forget_irradiance();
+
+        let clock = &self.stats.geometry_build_time;
+        clock.set(0.);
+        let geometry = self.accelerate(reflected(&self.spheres), Some(&self.geometry), clock);
+        let occluders = self.accelerate(occluders(&self.spheres), Some(&self.occluders), clock);
+        let ghosts = self.accelerate(ghosts(&self.spheres), Some(&self.ghosts), clock);
+
+        self.geometry = geometry;
+        self.occluders = occluders;
+        self.ghosts = ghosts;
+        self.emitters = emitters(&self.spheres);
+        self.rebuild_light_links();
+    }
+}
@@ -2,12 +2,485 @@
 
 #![cfg(target_arch = "wasm32")]
 
+extern crate raymond;
 extern crate wasm_bindgen_test;
+use raymond::{
+    Accelerator, Image, Integrator, LintKind, MatteKind, PathInterpolation, RenderLayers,
+    RenderOrder, Scene, TextureEncoding,
+};
 use wasm_bindgen_test::*;
 
 wasm_bindgen_test_configure!(run_in_browser);
 
+const WIDTH: usize = 64;
+const HEIGHT: usize = 36;
+
+fn pixels(img: &Image) -> Vec<u8> {
+    unsafe { std::slice::from_raw_parts(img.pixels(), WIDTH * HEIGHT * 4).to_vec() }
+}
+
+fn rendered(scene: &mut Scene) -> Vec<u8> {
+    let mut img = Image::new(WIDTH, HEIGHT);
+    scene.render(&mut img);
+    pixels(&img)
+}
+
+fn rgb(pixels: &[u8], x: usize, y: usize) -> (u8, u8, u8) {
+    let idx = 4 * (y * WIDTH + x);
+    (pixels[idx], pixels[idx + 1], pixels[idx + 2])
+}
+
+#[wasm_bindgen_test]
+fn render_writes_opaque_pixels() {
+    let pixels = rendered(&mut Scene::new());
+    assert!(pixels.chunks(4).all(|p| p[3] == u8::MAX));
+}
+
+#[wasm_bindgen_test]
+fn red_sphere_covers_its_silhouette() {
+    let pixels = rendered(&mut Scene::new());
+
+    // The red sphere at (-1, 4, 15) projects to around (30, 3), with a radius
+    // of a little over four pixels.
+    let mut inside = 0;
+    let mut red = 0;
+    for y in 0..7 {
+        for x in 27..34 {
+            let (dx, dy) = (x as f64 - 29.7, y as f64 - 2.9);
+            if dx * dx + dy * dy < 9. {
+                let (r, g, b) = rgb(&pixels, x, y);
+                inside += 1;
+                if r > g && r > b {
+                    red += 1;
+                }
+            }
+        }
+    }
+
+    assert!(red * 10 >= inside * 9, "{} of {} pixels red", red, inside);
+}
+
+#[wasm_bindgen_test]
+fn background_is_visible_in_the_corners() {
+    let pixels = rendered(&mut Scene::new());
+
+    // The background gradient is grey where the view is level, and always
+    // has equal red and blue components.
+    let (r, g, b) = rgb(&pixels, WIDTH - 1, HEIGHT / 2);
+    assert_eq!(r, b);
+    assert!(r >= g);
+}
+
+#[wasm_bindgen_test]
+fn packet_tracing_matches_scalar_tracing() {
+    let scalar = rendered(&mut Scene::new());
+
+    let mut scene = Scene::new();
+    scene.set_packet_tracing(true);
+    assert_eq!(rendered(&mut scene), scalar);
+}
+
+#[wasm_bindgen_test]
+fn every_accelerator_matches_brute_force() {
+    let brute = rendered(&mut Scene::new());
+
+    for &kind in &[Accelerator::Bvh, Accelerator::Grid, Accelerator::KdTree] {
+        let mut scene = Scene::new();
+        scene.set_accelerator(kind);
+        assert_eq!(rendered(&mut scene), brute, "{:?}", kind);
+    }
+}
+
+#[wasm_bindgen_test]
+fn packet_tracing_matches_scalar_tracing_with_every_accelerator() {
+    let scalar = rendered(&mut Scene::new());
+
+    for &kind in &[Accelerator::Bvh, Accelerator::Grid, Accelerator::KdTree] {
+        let mut scene = Scene::new();
+        scene.set_accelerator(kind);
+        scene.set_packet_tracing(true);
+        assert_eq!(rendered(&mut scene), scalar, "{:?}", kind);
+    }
+}
+
+#[wasm_bindgen_test]
+fn two_interlaced_fields_make_a_full_frame() {
+    let full = rendered(&mut Scene::new());
+
+    let mut scene = Scene::new();
+    let mut img = Image::new(WIDTH, HEIGHT);
+    scene.render_interlaced(&mut img);
+    scene.render_interlaced(&mut img);
+    assert_eq!(pixels(&img), full);
+}
+
+#[wasm_bindgen_test]
+fn budgeted_render_completes_the_frame() {
+    let full = rendered(&mut Scene::new());
+
+    for &order in &[
+        RenderOrder::Scanline,
+        RenderOrder::Hilbert,
+        RenderOrder::Spiral,
+    ] {
+        let mut scene = Scene::new();
+        scene.set_render_order(order);
+
+        let mut img = Image::new(WIDTH, HEIGHT);
+        let mut cursor = scene.render_budgeted(&mut img, 0., None);
+        while !cursor.done() {
+            cursor = scene.render_budgeted(&mut img, 0., Some(cursor));
+        }
+
+        assert_eq!(pixels(&img), full);
+    }
+}
+
+#[wasm_bindgen_test]
+fn stats_count_one_primary_ray_per_pixel() {
+    let mut scene = Scene::new();
+    rendered(&mut scene);
+    assert_eq!(scene.stats().primary_rays(), (WIDTH * HEIGHT) as f64);
+}
+
+#[wasm_bindgen_test]
+fn moving_a_sphere_changes_the_frame() {
+    let before = rendered(&mut Scene::new());
+
+    let mut scene = Scene::new();
+    scene.set_sphere_center(0, 30., 4., 15.);
+    assert_ne!(rendered(&mut scene), before);
+}
+
+#[wasm_bindgen_test]
+fn setters_ignore_indices_past_the_end() {
+    let before = rendered(&mut Scene::new());
+
+    let mut scene = Scene::new();
+    scene.set_sphere_center(99, 0., 0., 0.);
+    scene.set_sphere_transparency(99, 0.5);
+    scene.set_light_position(99, 0., 0., 0.);
+    scene.set_light_temperature(99, 3000.);
+    assert!(!scene.set_sphere_material(99, "missing"));
+    assert_eq!(scene.material_name(99), None);
+    assert!(!scene.is_sphere_resident(99));
+    assert_eq!(rendered(&mut scene), before);
+}
+
+// The color of a matte sphere with a flat texture of the given byte, lit by
+// nothing but white ambient light at the white point, which shows the texture
+// exactly as it is.
+fn lit_texture(byte: u8, encoding: TextureEncoding, compressed: bool) -> (u8, u8, u8) {
+    let mut scene = Scene::empty();
+    let sphere = scene.add_sphere(0., 0., 0., 3.);
+    scene.set_texture_compression(compressed);
+    scene.set_sphere_texture(sphere, 1, 1, &[byte, byte, byte, 255], encoding);
+    scene.set_ambient_light(0xFF_FF_FF, 1.);
+    scene.set_integrator(Integrator::LitPreview);
+    rgb(&rendered(&mut scene), WIDTH / 2, HEIGHT / 2)
+}
+
+#[wasm_bindgen_test]
+fn fully_lit_textures_keep_their_brightness() {
+    let textured = |byte: u8, encoding: TextureEncoding| lit_texture(byte, encoding, false);
+
+    let near = |(r, g, b): (u8, u8, u8), expected: u8| {
+        [r, g, b]
+            .iter()
+            .all(|&c| (c as i32 - expected as i32).abs() <= 1)
+    };
+
+    // sRGB 188 and linear 128 both stand for a reflectance of about a half.
+    assert!(near(textured(188, TextureEncoding::Srgb), 188));
+    assert!(near(textured(128, TextureEncoding::Linear), 188));
+    assert!(near(textured(188, TextureEncoding::Linear), 223));
+}
+
+#[wasm_bindgen_test]
+fn compressed_textures_stay_close_to_their_source() {
+    // A flat color rounds to 5:6:5 bits, and no further.
+    let (r, g, b) = lit_texture(188, TextureEncoding::Srgb, true);
+    assert!([r, g, b].iter().all(|&c| (c as i32 - 188).abs() <= 3));
+}
+
+#[wasm_bindgen_test]
+fn motion_vectors_survive_added_spheres_and_short_buffers() {
+    let mut scene = Scene::new();
+    let mut out = vec![0f32; 2 * WIDTH * HEIGHT];
+    scene.render_motion_vectors(WIDTH, HEIGHT, &mut out);
+
+    // A sphere added in the meantime has nowhere to have moved from.
+    scene.add_sphere(0., 4., 8., 1.);
+    scene.render_motion_vectors(WIDTH, HEIGHT, &mut out);
+    assert!(out.iter().all(|&m| m == 0.));
+
+    let mut short = vec![1f32; 10];
+    scene.render_motion_vectors(WIDTH, HEIGHT, &mut short);
+    assert!(short.iter().all(|&m| m == 1.));
+}
+
+#[wasm_bindgen_test]
+fn path_heat_leaves_the_frame_stats_alone() {
+    let mut scene = Scene::new();
+    rendered(&mut scene);
+    let stats = scene.stats().shadow_rays();
+
+    let mut out = vec![0f32; 2 * WIDTH * HEIGHT];
+    scene.render_path_heat(WIDTH, HEIGHT, 2, &mut out);
+    assert!(out.iter().any(|&h| h > 0.));
+    assert_eq!(scene.stats().shadow_rays(), stats);
+
+    let mut short = vec![1f32; 10];
+    scene.render_path_heat(WIDTH, HEIGHT, 2, &mut short);
+    assert!(short.iter().all(|&h| h == 1.));
+}
+
+#[wasm_bindgen_test]
+fn mattes_leave_short_buffers_alone() {
+    let mut scene = Scene::new();
+    let (mut ids, mut coverage) = (vec![7u32; 10], vec![1f32; 2 * 2 * WIDTH * HEIGHT]);
+    scene.render_mattes(WIDTH, HEIGHT, MatteKind::Object, &mut ids, &mut coverage);
+    assert!(ids.iter().all(|&id| id == 7));
+    assert!(coverage.iter().all(|&c| c == 1.));
+}
+
+#[wasm_bindgen_test]
+fn render_layers_split_the_frame_between_them() {
+    let mut scene = Scene::new();
+    scene.set_sphere_layer(0, 1);
+
+    let mut layers = RenderLayers::new(2, WIDTH, HEIGHT);
+    scene.render_layers(&mut layers);
+
+    let layer = |i: usize| unsafe {
+        std::slice::from_raw_parts(layers.pixels(i), WIDTH * HEIGHT * 4).to_vec()
+    };
+    let (background, red) = (layer(0), layer(1));
+    assert!(layers.pixels(2).is_null());
+
+    for (a, b) in background.chunks(4).zip(red.chunks(4)) {
+        assert_eq!(a[3] as u32 + b[3] as u32, u8::MAX as u32);
+    }
+    assert_eq!(rgb(&red, 30, 3), rgb(&rendered(&mut Scene::new()), 30, 3));
+}
+
+#[wasm_bindgen_test]
+fn lint_flags_a_light_inside_a_sphere() {
+    let mut scene = Scene::new();
+    assert_eq!(scene.lint().count(), 0);
+
+    // The red sphere has a radius of 2 around (-1, 4, 15).
+    let light = scene.add_light(-1., 4.5, 15., 1000.);
+    let lint = scene.lint();
+    assert_eq!(lint.count(), 1);
+    assert_eq!(lint.kind(0), Some(LintKind::LightInsideGeometry));
+    assert_eq!(lint.subject(0), Some(light));
+    assert_eq!(lint.other(0), Some(0));
+    assert_eq!(lint.kind(1), None);
+    assert_eq!(lint.message(1), None);
+}
+
+#[wasm_bindgen_test]
+fn streaming_pages_out_distant_spheres() {
+    let mut scene = Scene::new();
+    scene.set_streaming(10., 1.);
+
+    // The floor's top lies 14 units below the camera, out of reach of the
+    // cells either side of the camera's.
+    assert!(!scene.is_sphere_resident(9));
+    assert!(scene.is_sphere_resident(5));
+
+    let changes = scene.streaming_changes();
+    assert!((0..changes.count()).any(|i| changes.sphere(i) == 9 && !changes.resident(i)));
+    assert_eq!(scene.streaming_changes().count(), 0);
+}
+
+#[wasm_bindgen_test]
+fn capture_still_leaves_the_interactive_frame_alone() {
+    let mut scene = Scene::new();
+    let before = rendered(&mut scene);
+    let stats = scene.stats().primary_rays();
+
+    let still = scene.capture_still(32, 18, 2);
+    assert_eq!(
+        (still.width(), still.height(), still.samples()),
+        (32, 18, 2)
+    );
+    assert_eq!(&still.png()[..4], b"\x89PNG");
+    assert_eq!(scene.stats().primary_rays(), stats);
+
+    assert_eq!(rendered(&mut scene), before);
+}
+
+#[wasm_bindgen_test]
+fn overlays_far_outside_the_image_are_clipped() {
+    let mut scene = Scene::new();
+    let mut img = Image::new(WIDTH, HEIGHT);
+    scene.render(&mut img);
+    let before = pixels(&img);
+
+    // Neither of these would finish in reasonable time, or at all, if they
+    // walked every pixel between their ends.
+    img.draw_line(i32::MIN, i32::MIN, i32::MAX, i32::MAX, 0xFF_FF_FF);
+    img.fill_rect(i32::MAX - 1, i32::MAX - 1, i32::MAX, i32::MAX, 0xFF_FF_FF);
+    img.fill_rect(-5, -1, i32::MAX, 2, 0x00_00_00);
+    scene.draw_line_3d(&mut img, &[0., 0.], &[1., 1., 1.], 0xFF_FF_FF);
+    scene.draw_axes(&mut img, &[], 1.);
+
+    let after = pixels(&img);
+    assert_eq!(rgb(&after, 0, 0), (0, 0, 0));
+    assert_eq!(rgb(&after, 5, 5), (255, 255, 255));
+    assert_eq!(rgb(&after, HEIGHT - 1, HEIGHT - 1), (255, 255, 255));
+    assert_eq!(rgb(&after, 5, 6), rgb(&before, 5, 6));
+}
+
+// A 2×2×2 .cube LUT whose lattice maps the corners of its domain to the given
+// corners of the RGB cube, red varying fastest.
+fn cube_lut(header: &str, scale: f64) -> String {
+    let mut text = format!("TITLE \"test\"\n{}\nLUT_3D_SIZE 2\n", header);
+    for i in 0..8 {
+        let bit = |b: usize| ((i >> b) & 1) as f64 * scale;
+        text += &format!("{} {} {}\n", bit(0), bit(1), bit(2));
+    }
+    text
+}
+
+fn graded(lut: &str) -> Vec<u8> {
+    let mut img = Image::new(WIDTH, HEIGHT);
+    assert!(img.set_cube_lut(lut));
+    Scene::new().render(&mut img);
+    pixels(&img)
+}
+
+#[wasm_bindgen_test]
+fn identity_lut_leaves_the_render_alone() {
+    let before = rendered(&mut Scene::new());
+    assert_eq!(graded(&cube_lut("", 1.)), before);
+    assert_eq!(graded(""), before);
+}
+
+#[wasm_bindgen_test]
+fn lut_domain_maps_onto_the_lattice() {
+    let before = rendered(&mut Scene::new());
+
+    // Spanning [0, 2] with a lattice that does too, the LUT is still an
+    // identity.
+    let wide = "DOMAIN_MIN 0 0 0\nDOMAIN_MAX 2 2 2";
+    assert_eq!(graded(&cube_lut(wide, 2.)), before);
+
+    // Whereas a lattice spanning [0, 1] over that domain halves every color.
+    let halved = graded(&cube_lut(wide, 1.));
+    assert!(halved.iter().zip(&before).all(|(h, b)| h <= b));
+    assert!(halved.iter().zip(&before).any(|(h, b)| h < b));
+}
+
+#[wasm_bindgen_test]
+fn malformed_luts_are_rejected() {
+    let mut img = Image::new(WIDTH, HEIGHT);
+    let identity = cube_lut("", 1.);
+
+    let short = identity.replacen("1 1 1", "1 1", 1);
+    let missing = identity.replacen("1 1 1\n", "", 1);
+    let one_d = "LUT_1D_SIZE 2\n0 0 0\n1 1 1\n";
+    for lut in &[
+        short.as_str(),
+        missing.as_str(),
+        one_d,
+        "LUT_3D_SIZE 1\n0 0 0",
+        "LUT_3D_SIZE 2048\n",
+        "LUT_3D_SIZE 4194304\n",
+    ] {
+        assert!(!img.set_cube_lut(lut));
+    }
+
+    // A rejected LUT leaves the image ungraded.
+    Scene::new().render(&mut img);
+    assert_eq!(pixels(&img), rendered(&mut Scene::new()));
+}
+
+// Where the camera is, which way it looks through the middle of the frame,
+// and its field of view.
+fn camera(scene: &Scene) -> (Vec<f64>, Vec<f64>, f64) {
+    let ray = scene.ray_for_pixel(WIDTH as f64 / 2., HEIGHT as f64 / 2., WIDTH, HEIGHT);
+    (ray[..3].to_vec(), ray[3..].to_vec(), scene.field_of_view())
+}
+
+fn assert_near(actual: &[f64], expected: &[f64]) {
+    let near = actual
+        .iter()
+        .zip(expected)
+        .all(|(a, e)| (a - e).abs() < 1e-9);
+    assert!(near, "{:?} != {:?}", actual, expected);
+}
+
+#[wasm_bindgen_test]
+fn camera_path_without_keyframes_leaves_the_camera_alone() {
+    let mut scene = Scene::new();
+    let before = camera(&scene);
+    scene.set_animation_time(1.);
+    assert_eq!(camera(&scene), before);
+}
+
+#[wasm_bindgen_test]
+fn camera_path_with_one_keyframe_holds_still() {
+    let mut scene = Scene::new();
+    scene.add_keyframe(2., &[1., 2., 3.], &[1., 2., 13.], 50.);
+
+    for &t in &[0., 2., 5.] {
+        scene.set_animation_time(t);
+        let (eye, _, fov) = camera(&scene);
+        assert_near(&eye, &[1., 2., 3.]);
+        assert_near(&[fov], &[50.]);
+    }
+}
+
 #[wasm_bindgen_test]
-fn pass() {
-    assert_eq!(1 + 1, 2);
+fn camera_path_interpolates_between_two_keyframes() {
+    let mut scene = Scene::new();
+    scene.add_keyframe(4., &[4., 2., -6.], &[4., 2., 10.], 80.);
+    scene.add_keyframe(0., &[0., 0., -6.], &[0., 0., 10.], 60.);
+
+    // The ends hold still either way.
+    for &(t, eye, fov) in &[(-1., [0., 0., -6.], 60.), (9., [4., 2., -6.], 80.)] {
+        scene.set_animation_time(t);
+        let (actual, _, actual_fov) = camera(&scene);
+        assert_near(&actual, &eye);
+        assert_near(&[actual_fov], &[fov]);
+    }
+
+    // A quarter of the way along, the line is a quarter of the way there,
+    // whereas the spline, with the ends standing in for the keyframes beyond
+    // them, eases out of the first keyframe.
+    scene.set_path_interpolation(PathInterpolation::Linear);
+    scene.set_animation_time(1.);
+    let (eye, forward, fov) = camera(&scene);
+    assert_near(&eye, &[1., 0.5, -6.]);
+    assert_near(&forward, &[0., 0., 1.]);
+    assert_near(&[fov], &[65.]);
+
+    scene.set_path_interpolation(PathInterpolation::CatmullRom);
+    scene.set_animation_time(1.);
+    let (eye, forward, fov) = camera(&scene);
+    assert_near(&eye, &[0.8125, 0.40625, -6.]);
+    assert_near(&forward, &[0., 0., 1.]);
+    assert_near(&[fov], &[64.0625]);
+
+    // Halfway along, the spline is symmetric.
+    scene.set_animation_time(2.);
+    assert_near(&camera(&scene).0, &[2., 1., -6.]);
+}
+
+#[wasm_bindgen_test]
+fn camera_inside_an_opaque_sphere_sees_its_interior() {
+    // Sealed inside a sphere, the camera sees none of the scene's light, but
+    // it does see that of a light in there with it.
+    let mut scene = Scene::new();
+    scene.add_sphere(0., 0., -6., 3.);
+    let dark = rendered(&mut scene);
+    assert_eq!(rgb(&dark, WIDTH / 2, HEIGHT / 2), (0, 0, 0));
+
+    scene.add_light(0., 1., -6., 20.);
+    let lit = rendered(&mut scene);
+    let (r, g, b) = rgb(&lit, WIDTH / 2, HEIGHT / 2);
+    assert!(r > 0 && g > 0 && b > 0);
 }
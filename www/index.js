@@ -1,4 +1,4 @@
-import { Image, Scene } from "raymond";
+import { ColorSpace, Image, Scene } from "raymond";
 import { memory } from "raymond/raymond_bg";
 
 const scene = new Scene();
@@ -6,17 +6,49 @@ const scene = new Scene();
 const canvas = document.getElementById("canvas");
 const image = new Image(canvas.width, canvas.height);
 
+// Render straight into the Display P3 color space on wide-gamut displays.
+const colorSpace = window.matchMedia('(color-gamut: p3)').matches ? 'display-p3' : 'srgb';
+if (colorSpace === 'display-p3') {
+  image.setColorSpace(ColorSpace.DisplayP3);
+}
+
 const length = canvas.width * canvas.height;
 const pixels = new Uint8ClampedArray(memory.buffer, image.pixels(), length << 2);
-const imageData = new ImageData(pixels, canvas.width, canvas.height);
+const imageData = new ImageData(pixels, canvas.width, canvas.height, { colorSpace });
 
-const ctx = canvas.getContext('2d');
+const ctx = canvas.getContext('2d', { colorSpace });
 
 const render = () => {
   scene.render(image);
   ctx.putImageData(imageData, 0, 0);
 };
 
+// While the camera is moving, draw cheap low-resolution previews and only
+// refine the frame at full resolution once the keys have been idle for a bit.
+// The refinement is spread across animation frames, so it never blocks input.
+const REFINE_DELAY_MS = 150;
+const FRAME_BUDGET_MS = 12;
+let refineTimeout = null;
+let refineFrame = null;
+
+const refine = cursor => {
+  cursor = scene.renderBudgeted(image, FRAME_BUDGET_MS, cursor);
+  ctx.putImageData(imageData, 0, 0);
+
+  if (!cursor.done()) {
+    refineFrame = requestAnimationFrame(() => refine(cursor));
+  }
+};
+
+const preview = () => {
+  cancelAnimationFrame(refineFrame);
+  scene.renderPreview(image);
+  ctx.putImageData(imageData, 0, 0);
+
+  clearTimeout(refineTimeout);
+  refineTimeout = setTimeout(() => refine(), REFINE_DELAY_MS);
+};
+
 document.addEventListener('keydown', e => {
   if (e.ctrlKey || e.altKey || e.metaKey) {
     return;
@@ -48,7 +80,7 @@ document.addEventListener('keydown', e => {
   }
 
   e.preventDefault();
-  render();
+  preview();
 });
 
 render();